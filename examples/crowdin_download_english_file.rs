@@ -4,15 +4,17 @@ use std::io::{Seek, SeekFrom};
 use std::path::Path;
 
 use fml::crowdin;
+use fml::github_repo_info::GithubRepoInfo;
 
 #[tokio::main]
 async fn main() {
     fml::init_with_crowdin().await;
 
-    let directory_id = crowdin::find_directory_id("Factorio Mod Example (dima74)").await.unwrap();
-    let mut files = crowdin::list_files(directory_id).await;
+    let repo_info = GithubRepoInfo::new_single_mod("dima74/factorio-mod-example");
+    let directory_id = crowdin::find_directory_id(&repo_info.mods[0]).await.unwrap().unwrap();
+    let mut files = crowdin::list_files(directory_id).await.unwrap();
     let (_, file_id) = files.next().unwrap();
-    let mut file = crowdin::download_file(file_id).await;
+    let mut file = crowdin::download_file(file_id).await.unwrap();
     file.seek(SeekFrom::Start(0)).unwrap();
     let target = Path::new("temp/download.ini");
     let mut target = File::create(target).unwrap();
@@ -1,7 +1,7 @@
 #[tokio::main]
 async fn main() {
     fml::init_with_crowdin().await;
-    let path = fml::crowdin::download_all_translations().await;
+    let path = fml::crowdin::download_all_translations().await.unwrap();
     dbg!(&path);
     #[allow(clippy::empty_loop)]
     loop {}
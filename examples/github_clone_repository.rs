@@ -3,13 +3,9 @@ use fml::github_repo_info::GithubRepoInfo;
 #[tokio::main]
 async fn main() {
     fml::init();
-    let api = fml::github::as_app();
-    let owner = "dima74";
-    let repo = "factorio-mod-example";
-    let installation = api
-        .apps().get_repository_installation(owner, repo)
-        .await.unwrap();
+    let full_name = "dima74/factorio-mod-example";
+    let installation_id = fml::github::get_installation_id_for_repo(full_name).await.unwrap();
 
-    let repo_info = GithubRepoInfo::new_single_mod(&format!("{}/{}", owner, repo));
-    fml::github::clone_repository(&repo_info, installation.id).await;
+    let repo_info = GithubRepoInfo::new_single_mod(full_name);
+    fml::github::clone_repository(&repo_info, installation_id).await;
 }
@@ -13,8 +13,7 @@ async fn main() {
         let json = std::fs::read_to_string(cache_path).unwrap();
         serde_json::from_str(&json).unwrap()
     } else {
-        let api = fml::github::as_app();
-        let repositories = get_all_repositories(&api).await
+        let repositories = get_all_repositories().await
             .into_iter()
             .map(|(repo_info, _id)| repo_info)
             .collect::<Vec<_>>();
@@ -5,12 +5,11 @@ use fml::github::get_all_installations;
 #[tokio::main]
 async fn main() {
     fml::init();
-    let api = fml::github::as_app();
-    let installations = get_all_installations(&api).await;
+    let installations = get_all_installations().await;
     dbg!(installations.len());
 
     let installations = installations.iter()
-        .map(|it| (&it.account.login, it.id.0))
+        .map(|it| (&it.login, it.id.0))
         .collect::<Vec<_>>();
     dbg!(installations);
 }
@@ -0,0 +1,367 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::crowdin::{DirectoryId, FileId, ProjectInfo, StorageId};
+use crate::crowdin::file_type::FileType;
+use crate::crowdin::http::{crowdin_delete, crowdin_get_empty_query, crowdin_get_pagination, crowdin_get_pagination_empty_query, crowdin_patch, crowdin_post, crowdin_put, CrowdinError, CrowdinResult, DataWrapper, IdResponse, PatchOperation, UnitResponse};
+use crate::mod_directory::LanguageCode;
+use crate::util;
+
+/// Crowdin operations used by the service.
+/// [HttpCrowdinApi] is used in production, [super::fake::FakeCrowdinApi] in offline tests.
+#[async_trait]
+pub trait CrowdinApi: Send + Sync {
+    async fn get_project_info(&self) -> CrowdinResult<ProjectInfo>;
+
+    /// Direct children of `parent` directory, or root directories if `parent` is `None`
+    async fn list_directories(&self, parent: Option<DirectoryId>) -> CrowdinResult<Vec<(String, DirectoryId)>>;
+
+    async fn list_files(&self, directory_id: DirectoryId) -> CrowdinResult<Vec<(String, FileId)>>;
+
+    async fn create_directory(&self, name: &str, parent: Option<DirectoryId>) -> CrowdinResult<DirectoryId>;
+
+    async fn upload_file_to_storage(&self, file_content: String, file_name: &str) -> CrowdinResult<StorageId>;
+
+    /// File is not translated to `excluded_languages`
+    async fn add_english_file(
+        &self,
+        directory_id: DirectoryId,
+        storage_id: StorageId,
+        file_name: &str,
+        file_type: FileType,
+        excluded_languages: &[LanguageCode],
+    ) -> CrowdinResult<FileId>;
+
+    async fn update_english_file(&self, file_id: FileId, storage_id: StorageId) -> CrowdinResult<()>;
+
+    async fn set_excluded_languages(&self, file_id: FileId, excluded_languages: &[LanguageCode]) -> CrowdinResult<()>;
+
+    /// Translations are kept
+    async fn rename_file(&self, file_id: FileId, name: &str) -> CrowdinResult<()>;
+
+    async fn move_file(&self, file_id: FileId, directory_id: DirectoryId) -> CrowdinResult<()>;
+
+    async fn delete_file(&self, file_id: FileId) -> CrowdinResult<()>;
+
+    async fn rename_directory(&self, directory_id: DirectoryId, name: &str) -> CrowdinResult<()>;
+
+    /// Deletes directory together with its files and subdirectories
+    async fn delete_directory(&self, directory_id: DirectoryId) -> CrowdinResult<()>;
+
+    async fn add_localization_file(
+        &self,
+        english_file_id: FileId,
+        storage_id: StorageId,
+        language_code: &LanguageCode,
+    ) -> CrowdinResult<()>;
+
+    /// Builds whole project, but extracts only given directories (all if `None`).
+    /// Returns directory with structure `ru/Factorio Mod Example (dima74)/locale.ini`.
+    /// If `export_approved_only`, strings which are not approved by proofreader are skipped
+    async fn download_all_translations(
+        &self,
+        directories: Option<&HashSet<String>>,
+        export_approved_only: bool,
+    ) -> CrowdinResult<TempDir>;
+
+    /// Same as [Self::download_all_translations], but builds only given directory
+    async fn download_directory_translations(
+        &self,
+        directory_id: DirectoryId,
+        export_approved_only: bool,
+    ) -> CrowdinResult<TempDir>;
+
+    async fn download_file(&self, file_id: FileId) -> CrowdinResult<fs::File>;
+
+    /// Translation progress in percents for each project language, includes subdirectories
+    async fn get_directory_progress(&self, directory_id: DirectoryId) -> CrowdinResult<HashMap<LanguageCode, u8>>;
+}
+
+/// Upper bound of waiting for translations build, so stuck build doesn't block jobs queue forever
+pub const MAX_BUILD_DURATION: Duration = Duration::from_secs(30 * 60);
+
+pub struct HttpCrowdinApi {
+    project_id: String,
+}
+
+impl HttpCrowdinApi {
+    pub fn new(project_id: String) -> Self {
+        Self { project_id }
+    }
+
+    /// `/files` => `/projects/{project_id}/files`
+    fn path(&self, path: &str) -> String {
+        format!("/projects/{}{}", self.project_id, path)
+    }
+
+    /// Waits until translations build is finished and downloads it
+    async fn download_build(&self, build_id: i64, directories: Option<&HashSet<String>>) -> CrowdinResult<TempDir> {
+        let start = Instant::now();
+        while !self.is_build_finished(build_id).await? {
+            if start.elapsed() > MAX_BUILD_DURATION {
+                return Err(CrowdinError::BuildTimeout { build_id });
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        let url = self.get_build_download_url(build_id).await?;
+        // `ru/Factorio Mod Example (dima74)/locale.ini`
+        util::download_and_extract_zip_file(&url, |path| {
+            match (directories, path.iter().nth(1)) {
+                (Some(directories), Some(directory)) => directories.contains(directory.to_str().unwrap()),
+                _ => true,
+            }
+        }).await
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.get
+    async fn is_build_finished(&self, build_id: i64) -> CrowdinResult<bool> {
+        #[derive(Deserialize)]
+        struct Response { status: String }
+        let url = self.path(&format!("/translations/builds/{}", build_id));
+        let response = crowdin_get_empty_query::<Response>(&url).await?;
+        match response.status.as_str() {
+            "created" | "inProgress" => Ok(false),
+            "finished" => Ok(true),
+            _ => Err(CrowdinError::BuildFailed { build_id, status: response.status }),
+        }
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.download.download
+    async fn get_build_download_url(&self, build_id: i64) -> CrowdinResult<String> {
+        #[derive(Deserialize)]
+        struct Response { url: String }
+        let url = self.path(&format!("/translations/builds/{}/download", build_id));
+        Ok(crowdin_get_empty_query::<Response>(&url).await?.url)
+    }
+}
+
+#[derive(Serialize)]
+struct BuildRequest {
+    #[serde(rename = "skipUntranslatedStrings")]
+    skip_untranslated_strings: bool,
+    #[serde(rename = "exportApprovedOnly", skip_serializing_if = "std::ops::Not::not")]
+    export_approved_only: bool,
+}
+
+#[async_trait]
+impl CrowdinApi for HttpCrowdinApi {
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.get
+    async fn get_project_info(&self) -> CrowdinResult<ProjectInfo> {
+        crowdin_get_empty_query(&self.path("")).await
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.directories.getMany
+    async fn list_directories(&self, parent: Option<DirectoryId>) -> CrowdinResult<Vec<(String, DirectoryId)>> {
+        #[derive(Deserialize)]
+        struct Directory {
+            id: DirectoryId,
+            name: String,
+            /// Parent directory
+            #[serde(rename = "directoryId")]
+            directory_id: Option<DirectoryId>,
+        }
+        let directories: Vec<DataWrapper<Directory>> = crowdin_get_pagination(&self.path("/directories"), |request| {
+            match parent {
+                Some(parent) => request.query(&[("directoryId", parent)]),
+                None => request,
+            }
+        }).await?;
+        // Without `directoryId` query parameter Crowdin returns directories from all levels
+        let directories = directories.into_iter()
+            .filter(|d| d.data.directory_id == parent)
+            .map(|d| (d.data.name, d.data.id))
+            .collect();
+        Ok(directories)
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.getMany
+    async fn list_files(&self, directory_id: DirectoryId) -> CrowdinResult<Vec<(String, FileId)>> {
+        #[derive(Deserialize)]
+        struct File { id: FileId, name: String }
+        let files: Vec<DataWrapper<File>> = crowdin_get_pagination(&self.path("/files"), |request| {
+            request.query(&[("directoryId", directory_id)])
+        }).await?;
+        Ok(files.into_iter().map(|d| (d.data.name, d.data.id)).collect())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.directories.post
+    async fn create_directory(&self, name: &str, parent: Option<DirectoryId>) -> CrowdinResult<DirectoryId> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            #[serde(rename = "directoryId", skip_serializing_if = "Option::is_none")]
+            directory_id: Option<DirectoryId>,
+        }
+        let request = Request { name, directory_id: parent };
+        Ok(crowdin_post::<_, IdResponse>(&self.path("/directories"), request).await?.id)
+    }
+
+    async fn upload_file_to_storage(&self, file_content: String, file_name: &str) -> CrowdinResult<StorageId> {
+        super::http::upload_file_to_storage(file_content, file_name).await
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.post
+    async fn add_english_file(
+        &self,
+        directory_id: DirectoryId,
+        storage_id: StorageId,
+        file_name: &str,
+        file_type: FileType,
+        excluded_languages: &[LanguageCode],
+    ) -> CrowdinResult<FileId> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            #[serde(rename = "directoryId")]
+            directory_id: DirectoryId,
+            #[serde(rename = "storageId")]
+            storage_id: StorageId,
+            #[serde(rename = "name")]
+            file_name: &'a str,
+            r#type: &'static str,
+            #[serde(rename = "excludedTargetLanguages", skip_serializing_if = "<[_]>::is_empty")]
+            excluded_languages: &'a [LanguageCode],
+        }
+        let request = Request { directory_id, storage_id, file_name, r#type: file_type.crowdin_type(), excluded_languages };
+        Ok(crowdin_post::<_, IdResponse>(&self.path("/files"), request).await?.id)
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.put
+    async fn update_english_file(&self, file_id: FileId, storage_id: StorageId) -> CrowdinResult<()> {
+        #[derive(Serialize)]
+        struct Request {
+            #[serde(rename = "storageId")]
+            storage_id: StorageId,
+        }
+        let request = Request { storage_id };
+        let method = self.path(&format!("/files/{}", file_id));
+        crowdin_put::<_, UnitResponse>(&method, request).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.patch
+    async fn set_excluded_languages(&self, file_id: FileId, excluded_languages: &[LanguageCode]) -> CrowdinResult<()> {
+        let request = [PatchOperation::replace("/excludedTargetLanguages", excluded_languages)];
+        crowdin_patch::<_, UnitResponse>(&self.path(&format!("/files/{}", file_id)), request).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.patch
+    async fn rename_file(&self, file_id: FileId, name: &str) -> CrowdinResult<()> {
+        let request = [PatchOperation::replace("/name", name)];
+        crowdin_patch::<_, UnitResponse>(&self.path(&format!("/files/{}", file_id)), request).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.patch
+    async fn move_file(&self, file_id: FileId, directory_id: DirectoryId) -> CrowdinResult<()> {
+        let request = [PatchOperation::replace("/directoryId", directory_id)];
+        crowdin_patch::<_, UnitResponse>(&self.path(&format!("/files/{}", file_id)), request).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.delete
+    async fn delete_file(&self, file_id: FileId) -> CrowdinResult<()> {
+        crowdin_delete::<UnitResponse>(&self.path(&format!("/files/{}", file_id))).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.directories.patch
+    async fn rename_directory(&self, directory_id: DirectoryId, name: &str) -> CrowdinResult<()> {
+        let request = [PatchOperation::replace("/name", name)];
+        crowdin_patch::<_, UnitResponse>(&self.path(&format!("/directories/{}", directory_id)), request).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.directories.delete
+    async fn delete_directory(&self, directory_id: DirectoryId) -> CrowdinResult<()> {
+        crowdin_delete::<UnitResponse>(&self.path(&format!("/directories/{}", directory_id))).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.postOnLanguage
+    async fn add_localization_file(
+        &self,
+        // id of source english file
+        english_file_id: FileId,
+        // id of localized file
+        storage_id: StorageId,
+        language_code: &LanguageCode,
+    ) -> CrowdinResult<()> {
+        #[derive(Serialize)]
+        struct Request {
+            #[serde(rename = "fileId")]
+            english_file_id: FileId,
+            #[serde(rename = "storageId")]
+            storage_id: StorageId,
+            /// Defines whether to add translation if it's the same as the source string
+            #[serde(rename = "importEqSuggestions")]
+            import_eq_suggestions: bool,
+            /// Mark uploaded translations as approved
+            #[serde(rename = "autoApproveImported")]
+            auto_approve_imported: bool,
+        }
+        let request = Request {
+            english_file_id,
+            storage_id,
+            import_eq_suggestions: false,
+            auto_approve_imported: false,
+        };
+        let path = self.path(&format!("/translations/{}", language_code));
+        crowdin_post::<_, UnitResponse>(&path, request).await?;
+        Ok(())
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.post
+    async fn download_all_translations(
+        &self,
+        directories: Option<&HashSet<String>>,
+        export_approved_only: bool,
+    ) -> CrowdinResult<TempDir> {
+        let request = BuildRequest { skip_untranslated_strings: true, export_approved_only };
+        let build_id = crowdin_post::<_, IdResponse>(&self.path("/translations/builds"), request).await?.id;
+        self.download_build(build_id, directories).await
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.directories.post
+    async fn download_directory_translations(
+        &self,
+        directory_id: DirectoryId,
+        export_approved_only: bool,
+    ) -> CrowdinResult<TempDir> {
+        let request = BuildRequest { skip_untranslated_strings: true, export_approved_only };
+        let path = self.path(&format!("/translations/builds/directories/{}", directory_id));
+        let build_id = crowdin_post::<_, IdResponse>(&path, request).await?.id;
+        self.download_build(build_id, None).await
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.files.get
+    async fn download_file(&self, file_id: FileId) -> CrowdinResult<fs::File> {
+        #[derive(Deserialize)]
+        struct Response { url: String }
+        let url = self.path(&format!("/files/{}/download", file_id));
+        let response: Response = crowdin_get_empty_query(&url).await?;
+        util::download_file(&response.url).await
+    }
+
+    // https://developer.crowdin.com/api/v2/#operation/api.projects.directories.languages.progress.getMany
+    async fn get_directory_progress(&self, directory_id: DirectoryId) -> CrowdinResult<HashMap<LanguageCode, u8>> {
+        #[derive(Deserialize)]
+        struct Progress {
+            #[serde(rename = "languageId")]
+            language_id: LanguageCode,
+            #[serde(rename = "translationProgress")]
+            translation_progress: u8,
+        }
+        let url = self.path(&format!("/directories/{}/languages/progress", directory_id));
+        let progress: Vec<DataWrapper<Progress>> = crowdin_get_pagination_empty_query(&url).await?;
+        let progress = progress.into_iter()
+            .map(|it| (it.data.language_id, it.data.translation_progress))
+            .collect();
+        Ok(progress)
+    }
+}
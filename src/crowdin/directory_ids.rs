@@ -0,0 +1,154 @@
+//! Persistent mapping from mod (owner, repo, locale path) to its Crowdin directory.
+//!
+//! Directory name is derived from owner and repo (see [get_crowdin_directory_name]),
+//! so matching only by name silently loses directories when naming changes.
+//! Name is used only to find directories which are not in mapping yet
+//! (created before mapping existed or mapping file was lost), then mapping is recorded.
+//!
+//! Stored as `{jobs directory}/crowdin/directories.json` (outside of job files, which are in root of jobs directory),
+//! kept only in memory if [init] is not called.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crowdin::{get_crowdin_directory_name, DirectoryId};
+use crate::github_repo_info::GithubModInfo;
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MappedDirectory {
+    pub id: DirectoryId,
+    /// Name of directory when mapping was last used, it is name of directory in translations export
+    pub name: String,
+}
+
+#[derive(Default)]
+struct Mapping {
+    path: Option<PathBuf>,
+    /// `owner/repo/locale_path` => directory
+    directories: BTreeMap<String, MappedDirectory>,
+}
+
+static MAPPING: LazyLock<Mutex<Mapping>> = LazyLock::new(Default::default);
+
+fn mapping() -> MutexGuard<'static, Mapping> {
+    MAPPING.lock().unwrap()
+}
+
+pub fn init(path: PathBuf) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    let directories = match fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).expect("Can't parse crowdin directories mapping"),
+        Err(_) => BTreeMap::new(),
+    };
+    let mut mapping = mapping();
+    mapping.path = Some(path);
+    mapping.directories = directories;
+}
+
+fn key(mod_info: &GithubModInfo) -> String {
+    format!("{}/{}/{}", mod_info.owner, mod_info.repo, mod_info.locale_path)
+}
+
+pub fn get(mod_info: &GithubModInfo) -> Option<MappedDirectory> {
+    mapping().directories.get(&key(mod_info)).cloned()
+}
+
+/// Name of directory in translations export, resolved by [crate::crowdin::RootDirectories::resolve]
+pub fn get_name(mod_info: &GithubModInfo) -> String {
+    get(mod_info).map_or_else(|| get_crowdin_directory_name(mod_info), |it| it.name)
+}
+
+pub fn set(mod_info: &GithubModInfo, id: DirectoryId, name: &str) {
+    let directory = MappedDirectory { id, name: name.to_owned() };
+    let mut mapping = mapping();
+    if mapping.directories.get(&key(mod_info)) == Some(&directory) { return; }
+    mapping.directories.insert(key(mod_info), directory);
+    mapping.save();
+}
+
+/// Directory was renamed on Crowdin
+pub fn set_name(id: DirectoryId, name: &str) {
+    mapping().update(|directories| {
+        for directory in directories.values_mut().filter(|it| it.id == id) {
+            directory.name = name.to_owned();
+        }
+    });
+}
+
+/// Content of directory was moved to another directory, e.g. when repository is archived
+pub fn replace_id(old_id: DirectoryId, new_id: DirectoryId) {
+    mapping().update(|directories| {
+        for directory in directories.values_mut().filter(|it| it.id == old_id) {
+            directory.id = new_id;
+        }
+    });
+}
+
+/// Directory was deleted on Crowdin
+pub fn remove(id: DirectoryId) {
+    mapping().update(|directories| directories.retain(|_, it| it.id != id));
+}
+
+/// Directories of all mods of repository, empty if repository has no mapping yet
+pub fn get_repository_ids(full_name: &str) -> Vec<DirectoryId> {
+    get_ids_with_prefix(&format!("{}/", full_name))
+}
+
+/// Directories of all mods of all repositories of user or organization
+pub fn get_owner_ids(owner: &str) -> Vec<DirectoryId> {
+    get_ids_with_prefix(&format!("{}/", owner))
+}
+
+fn get_ids_with_prefix(prefix: &str) -> Vec<DirectoryId> {
+    mapping().directories.iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(_, directory)| directory.id)
+        .collect()
+}
+
+/// Repository renamed or transferred
+pub fn rename_repository(old_full_name: &str, new_full_name: &str) {
+    rename_prefix(&format!("{}/", old_full_name), &format!("{}/", new_full_name));
+}
+
+/// User or organization renamed
+pub fn rename_owner(old_owner: &str, new_owner: &str) {
+    rename_prefix(&format!("{}/", old_owner), &format!("{}/", new_owner));
+}
+
+fn rename_prefix(old_prefix: &str, new_prefix: &str) {
+    mapping().update(|directories| {
+        let keys = directories.keys()
+            .filter(|key| key.starts_with(old_prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            let directory = directories.remove(&key).unwrap();
+            directories.insert(format!("{}{}", new_prefix, &key[old_prefix.len()..]), directory);
+        }
+    });
+}
+
+impl Mapping {
+    fn update(&mut self, f: impl FnOnce(&mut BTreeMap<String, MappedDirectory>)) {
+        let old = self.directories.clone();
+        f(&mut self.directories);
+        if self.directories != old {
+            self.save();
+        }
+    }
+
+    /// Writes to temporary file first, so mapping file is never partially written
+    fn save(&self) {
+        let Some(path) = &self.path else { return; };
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, serde_json::to_string_pretty(&self.directories).unwrap()).unwrap();
+        fs::rename(temporary_path, path).unwrap();
+    }
+}
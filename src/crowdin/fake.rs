@@ -0,0 +1,495 @@
+//! In-memory implementation of [CrowdinApi] for offline tests.
+//! Models directories, english files, storages, uploaded translations and translation builds.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use tempfile::TempDir;
+
+use crate::crowdin::{DirectoryId, FileId, ProjectInfo, StorageId};
+use crate::crowdin::api::CrowdinApi;
+use crate::crowdin::file_type::FileType;
+use crate::crowdin::http::{CrowdinError, CrowdinResult};
+use crate::mod_directory::LanguageCode;
+use crate::util;
+
+const DEFAULT_LANGUAGES: &[&str] = &[
+    "ar", "be", "bg", "ca", "cs", "da", "de", "el", "es-ES", "fi", "fr", "hu", "it", "ja", "ko",
+    "nl", "no", "pl", "pt-BR", "pt-PT", "ro", "ru", "sv-SE", "tr", "uk", "zh-CN", "zh-TW",
+];
+
+pub struct FakeCrowdinApi {
+    source_language: LanguageCode,
+    languages: Vec<LanguageCode>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    directories: BTreeMap<DirectoryId, Directory>,
+    files: BTreeMap<FileId, File>,
+    /// storage id => file content
+    storages: HashMap<StorageId, String>,
+    /// (english file id, language) => translated file content
+    translations: HashMap<(FileId, LanguageCode), String>,
+    /// (english file id, language) => translated file content with only approved strings
+    approved_translations: HashMap<(FileId, LanguageCode), String>,
+    number_builds: usize,
+    number_directory_builds: usize,
+}
+
+struct Directory {
+    name: String,
+    parent: Option<DirectoryId>,
+}
+
+struct File {
+    directory_id: DirectoryId,
+    name: String,
+    file_type: FileType,
+    content: String,
+    excluded_languages: Vec<LanguageCode>,
+}
+
+impl Default for FakeCrowdinApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeCrowdinApi {
+    pub fn new() -> Self {
+        let languages = DEFAULT_LANGUAGES.iter().map(|it| it.to_string()).collect();
+        Self::with_languages(languages)
+    }
+
+    pub fn with_languages(languages: Vec<LanguageCode>) -> Self {
+        Self::with_source_language("en", languages)
+    }
+
+    /// Project for mods with other source language, see [crate::crowdin::init_with_apis]
+    pub fn with_source_language(source_language: &str, languages: Vec<LanguageCode>) -> Self {
+        Self { source_language: source_language.to_owned(), languages, state: Mutex::default() }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Root directories
+    pub fn directory_names(&self) -> Vec<String> {
+        self.state().directories.values()
+            .filter(|directory| directory.parent.is_none())
+            .map(|directory| directory.name.clone())
+            .collect()
+    }
+
+    pub fn file_names(&self, directory_name: &str) -> Vec<String> {
+        let state = self.state();
+        let Some(directory_id) = state.find_directory(directory_name) else { return vec![]; };
+        state.files.values()
+            .filter(|file| file.directory_id == directory_id)
+            .map(|file| file.name.clone())
+            .collect()
+    }
+
+    pub fn file_content(&self, directory_name: &str, file_name: &str) -> Option<String> {
+        let state = self.state();
+        let file_id = state.find_file(directory_name, file_name)?;
+        Some(state.files[&file_id].content.clone())
+    }
+
+    pub fn translation(&self, directory_name: &str, file_name: &str, language: &str) -> Option<String> {
+        let state = self.state();
+        let file_id = state.find_file(directory_name, file_name)?;
+        state.translations.get(&(file_id, language.to_owned())).cloned()
+    }
+
+    pub fn file_type(&self, directory_name: &str, file_name: &str) -> Option<FileType> {
+        let state = self.state();
+        let file_id = state.find_file(directory_name, file_name)?;
+        Some(state.files[&file_id].file_type)
+    }
+
+    pub fn excluded_languages(&self, directory_name: &str, file_name: &str) -> Vec<LanguageCode> {
+        let state = self.state();
+        let Some(file_id) = state.find_file(directory_name, file_name) else { return vec![]; };
+        state.files[&file_id].excluded_languages.clone()
+    }
+
+    /// Emulates translator work on Crowdin
+    pub fn set_translation(&self, directory_name: &str, file_name: &str, language: &str, content: &str) {
+        let mut state = self.state();
+        let file_id = state.find_file(directory_name, file_name)
+            .unwrap_or_else(|| panic!("No file {}/{}", directory_name, file_name));
+        state.translations.insert((file_id, language.to_owned()), content.to_owned());
+    }
+
+    /// Emulates proofreader work on Crowdin, `content` contains only approved strings
+    pub fn set_approved_translation(&self, directory_name: &str, file_name: &str, language: &str, content: &str) {
+        let mut state = self.state();
+        let file_id = state.find_file(directory_name, file_name)
+            .unwrap_or_else(|| panic!("No file {}/{}", directory_name, file_name));
+        state.approved_translations.insert((file_id, language.to_owned()), content.to_owned());
+    }
+
+    /// Number of whole project builds
+    pub fn number_builds(&self) -> usize {
+        self.state().number_builds
+    }
+
+    pub fn number_directory_builds(&self) -> usize {
+        self.state().number_directory_builds
+    }
+
+    /// Crowdin exports file for every language, untranslated strings are skipped
+    fn export_translations(
+        &self,
+        state: &State,
+        include_directory: impl Fn(DirectoryId) -> bool,
+        export_approved_only: bool,
+    ) -> TempDir {
+        let translations = if export_approved_only { &state.approved_translations } else { &state.translations };
+        let result = util::create_temporary_directory();
+        for language in &self.languages {
+            for &directory_id in state.directories.keys() {
+                if !include_directory(directory_id) { continue; }
+                let directory_path = result.path().join(language).join(state.directory_path(directory_id));
+                fs::create_dir_all(&directory_path).unwrap();
+                for (&file_id, file) in &state.files {
+                    if file.directory_id != directory_id { continue; }
+                    if file.excluded_languages.contains(language) { continue; }
+                    let content = translations.get(&(file_id, language.clone()))
+                        .map_or("", String::as_str);
+                    fs::write(directory_path.join(&file.name), content).unwrap();
+                }
+            }
+        }
+        result
+    }
+}
+
+impl State {
+    /// `path` is name of root directory or names separated by `/`, e.g. `_archived/Repo (owner)`
+    fn find_directory(&self, path: &str) -> Option<DirectoryId> {
+        let mut parent = None;
+        for name in path.split('/') {
+            parent = Some(self.find_child_directory(parent, name)?);
+        }
+        parent
+    }
+
+    fn find_child_directory(&self, parent: Option<DirectoryId>, name: &str) -> Option<DirectoryId> {
+        self.directories.iter()
+            .find(|(_, it)| it.parent == parent && it.name == name)
+            .map(|(&id, _)| id)
+    }
+
+    fn directory_path(&self, directory_id: DirectoryId) -> String {
+        let directory = &self.directories[&directory_id];
+        match directory.parent {
+            Some(parent) => format!("{}/{}", self.directory_path(parent), directory.name),
+            None => directory.name.clone(),
+        }
+    }
+
+    /// Whether `directory_id` is `ancestor_id` or its (possibly nested) subdirectory
+    fn is_inside_directory(&self, directory_id: DirectoryId, ancestor_id: DirectoryId) -> bool {
+        let mut current = Some(directory_id);
+        while let Some(id) = current {
+            if id == ancestor_id { return true; }
+            current = self.directories[&id].parent;
+        }
+        false
+    }
+
+    fn check_file_exists(&self, file_id: FileId) -> CrowdinResult<()> {
+        if !self.files.contains_key(&file_id) {
+            return Err(not_found(&format!("/files/{}", file_id)));
+        }
+        Ok(())
+    }
+
+    fn check_file_name_unique(&self, directory_id: DirectoryId, file_name: &str) -> CrowdinResult<()> {
+        let name_exists = self.files.values()
+            .any(|file| file.directory_id == directory_id && file.name == file_name);
+        if name_exists {
+            return Err(error("/files", StatusCode::BAD_REQUEST, "Name must be unique"));
+        }
+        Ok(())
+    }
+
+    fn find_file(&self, directory_name: &str, file_name: &str) -> Option<FileId> {
+        let directory_id = self.find_directory(directory_name)?;
+        self.files.iter()
+            .find(|(_, file)| file.directory_id == directory_id && file.name == file_name)
+            .map(|(&id, _)| id)
+    }
+
+    fn take_storage(&mut self, storage_id: StorageId) -> CrowdinResult<String> {
+        self.storages.remove(&storage_id)
+            .ok_or_else(|| not_found(&format!("/storages/{}", storage_id)))
+    }
+}
+
+/// Ids are unique across projects, as on Crowdin
+fn next_id() -> i64 {
+    static NEXT_ID: AtomicI64 = AtomicI64::new(1);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+fn not_found(path: &str) -> CrowdinError {
+    error(path, StatusCode::NOT_FOUND, "Not found")
+}
+
+fn error(path: &str, status: StatusCode, body: &str) -> CrowdinError {
+    CrowdinError::Status {
+        url: format!("fake://crowdin{}", path),
+        status,
+        body: body.to_owned(),
+        retry_after: None,
+    }
+}
+
+#[async_trait]
+impl CrowdinApi for FakeCrowdinApi {
+    async fn get_project_info(&self) -> CrowdinResult<ProjectInfo> {
+        Ok(ProjectInfo {
+            source_language_id: self.source_language.clone(),
+            target_language_ids: self.languages.clone(),
+            name: "Factorio mods localization".to_owned(),
+        })
+    }
+
+    async fn list_directories(&self, parent: Option<DirectoryId>) -> CrowdinResult<Vec<(String, DirectoryId)>> {
+        let directories = self.state().directories.iter()
+            .filter(|(_, directory)| directory.parent == parent)
+            .map(|(&id, directory)| (directory.name.clone(), id))
+            .collect();
+        Ok(directories)
+    }
+
+    async fn list_files(&self, directory_id: DirectoryId) -> CrowdinResult<Vec<(String, FileId)>> {
+        let state = self.state();
+        if !state.directories.contains_key(&directory_id) {
+            return Err(not_found(&format!("/directories/{}", directory_id)));
+        }
+        let files = state.files.iter()
+            .filter(|(_, file)| file.directory_id == directory_id)
+            .map(|(&id, file)| (file.name.clone(), id))
+            .collect();
+        Ok(files)
+    }
+
+    async fn create_directory(&self, name: &str, parent: Option<DirectoryId>) -> CrowdinResult<DirectoryId> {
+        let mut state = self.state();
+        if state.find_child_directory(parent, name).is_some() {
+            return Err(error("/directories", StatusCode::BAD_REQUEST, "Name must be unique"));
+        }
+        let id = next_id();
+        state.directories.insert(id, Directory { name: name.to_owned(), parent });
+        Ok(id)
+    }
+
+    async fn upload_file_to_storage(&self, file_content: String, _file_name: &str) -> CrowdinResult<StorageId> {
+        let mut state = self.state();
+        let id = next_id();
+        state.storages.insert(id, file_content);
+        Ok(id)
+    }
+
+    async fn add_english_file(
+        &self,
+        directory_id: DirectoryId,
+        storage_id: StorageId,
+        file_name: &str,
+        file_type: FileType,
+        excluded_languages: &[LanguageCode],
+    ) -> CrowdinResult<FileId> {
+        let mut state = self.state();
+        if !state.directories.contains_key(&directory_id) {
+            return Err(not_found(&format!("/directories/{}", directory_id)));
+        }
+        state.check_file_name_unique(directory_id, file_name)?;
+        let content = state.take_storage(storage_id)?;
+        let id = next_id();
+        let file = File {
+            directory_id,
+            name: file_name.to_owned(),
+            file_type,
+            content,
+            excluded_languages: excluded_languages.to_vec(),
+        };
+        state.files.insert(id, file);
+        Ok(id)
+    }
+
+    async fn update_english_file(&self, file_id: FileId, storage_id: StorageId) -> CrowdinResult<()> {
+        let mut state = self.state();
+        state.check_file_exists(file_id)?;
+        let content = state.take_storage(storage_id)?;
+        state.files.get_mut(&file_id).unwrap().content = content;
+        Ok(())
+    }
+
+    async fn set_excluded_languages(&self, file_id: FileId, excluded_languages: &[LanguageCode]) -> CrowdinResult<()> {
+        let mut state = self.state();
+        state.check_file_exists(file_id)?;
+        state.files.get_mut(&file_id).unwrap().excluded_languages = excluded_languages.to_vec();
+        Ok(())
+    }
+
+    async fn rename_file(&self, file_id: FileId, name: &str) -> CrowdinResult<()> {
+        let mut state = self.state();
+        state.check_file_exists(file_id)?;
+        state.check_file_name_unique(state.files[&file_id].directory_id, name)?;
+        state.files.get_mut(&file_id).unwrap().name = name.to_owned();
+        Ok(())
+    }
+
+    async fn move_file(&self, file_id: FileId, directory_id: DirectoryId) -> CrowdinResult<()> {
+        let mut state = self.state();
+        state.check_file_exists(file_id)?;
+        if !state.directories.contains_key(&directory_id) {
+            return Err(not_found(&format!("/directories/{}", directory_id)));
+        }
+        state.check_file_name_unique(directory_id, &state.files[&file_id].name)?;
+        state.files.get_mut(&file_id).unwrap().directory_id = directory_id;
+        Ok(())
+    }
+
+    async fn delete_file(&self, file_id: FileId) -> CrowdinResult<()> {
+        let mut state = self.state();
+        state.check_file_exists(file_id)?;
+        state.files.remove(&file_id);
+        state.translations.retain(|(id, _), _| *id != file_id);
+        state.approved_translations.retain(|(id, _), _| *id != file_id);
+        Ok(())
+    }
+
+    async fn rename_directory(&self, directory_id: DirectoryId, name: &str) -> CrowdinResult<()> {
+        let mut state = self.state();
+        let Some(directory) = state.directories.get(&directory_id) else {
+            return Err(not_found(&format!("/directories/{}", directory_id)));
+        };
+        if state.find_child_directory(directory.parent, name).is_some() {
+            return Err(error(&format!("/directories/{}", directory_id), StatusCode::BAD_REQUEST, "Name must be unique"));
+        }
+        state.directories.get_mut(&directory_id).unwrap().name = name.to_owned();
+        Ok(())
+    }
+
+    async fn delete_directory(&self, directory_id: DirectoryId) -> CrowdinResult<()> {
+        let mut state = self.state();
+        if !state.directories.contains_key(&directory_id) {
+            return Err(not_found(&format!("/directories/{}", directory_id)));
+        }
+        let deleted_directories = state.directories.keys()
+            .copied()
+            .filter(|&id| state.is_inside_directory(id, directory_id))
+            .collect::<HashSet<_>>();
+        let deleted_files = state.files.iter()
+            .filter(|(_, file)| deleted_directories.contains(&file.directory_id))
+            .map(|(&id, _)| id)
+            .collect::<HashSet<_>>();
+        state.directories.retain(|id, _| !deleted_directories.contains(id));
+        state.files.retain(|id, _| !deleted_files.contains(id));
+        state.translations.retain(|(id, _), _| !deleted_files.contains(id));
+        state.approved_translations.retain(|(id, _), _| !deleted_files.contains(id));
+        Ok(())
+    }
+
+    async fn add_localization_file(
+        &self,
+        english_file_id: FileId,
+        storage_id: StorageId,
+        language_code: &LanguageCode,
+    ) -> CrowdinResult<()> {
+        let mut state = self.state();
+        if !state.files.contains_key(&english_file_id) {
+            return Err(not_found(&format!("/files/{}", english_file_id)));
+        }
+        if !self.languages.contains(language_code) {
+            return Err(error(&format!("/translations/{}", language_code), StatusCode::BAD_REQUEST, "Unknown language"));
+        }
+        if state.files[&english_file_id].excluded_languages.contains(language_code) {
+            return Err(error(&format!("/translations/{}", language_code), StatusCode::BAD_REQUEST, "Language is excluded for file"));
+        }
+        let content = state.take_storage(storage_id)?;
+        state.translations.insert((english_file_id, language_code.clone()), content);
+        Ok(())
+    }
+
+    async fn download_all_translations(
+        &self,
+        directories: Option<&HashSet<String>>,
+        export_approved_only: bool,
+    ) -> CrowdinResult<TempDir> {
+        let mut state = self.state();
+        state.number_builds += 1;
+        let include_directory = |id| {
+            // Nested directories are included together with their root directory
+            let root = state.directory_path(id).split('/').next().unwrap().to_owned();
+            directories.is_none_or(|it| it.contains(&root))
+        };
+        Ok(self.export_translations(&state, include_directory, export_approved_only))
+    }
+
+    async fn download_directory_translations(
+        &self,
+        directory_id: DirectoryId,
+        export_approved_only: bool,
+    ) -> CrowdinResult<TempDir> {
+        let mut state = self.state();
+        if !state.directories.contains_key(&directory_id) {
+            return Err(not_found(&format!("/translations/builds/directories/{}", directory_id)));
+        }
+        state.number_directory_builds += 1;
+        let include_directory = |id| state.is_inside_directory(id, directory_id);
+        Ok(self.export_translations(&state, include_directory, export_approved_only))
+    }
+
+    async fn download_file(&self, file_id: FileId) -> CrowdinResult<fs::File> {
+        let state = self.state();
+        let file = state.files.get(&file_id)
+            .ok_or_else(|| not_found(&format!("/files/{}/download", file_id)))?;
+        let mut result = tempfile::tempfile().unwrap();
+        result.write_all(file.content.as_bytes()).unwrap();
+        Ok(result)
+    }
+
+    async fn get_directory_progress(&self, directory_id: DirectoryId) -> CrowdinResult<HashMap<LanguageCode, u8>> {
+        let state = self.state();
+        if !state.directories.contains_key(&directory_id) {
+            return Err(not_found(&format!("/directories/{}/languages/progress", directory_id)));
+        }
+        let files = state.files.iter()
+            .filter(|(_, file)| state.is_inside_directory(file.directory_id, directory_id))
+            .collect::<Vec<_>>();
+        let total = files.iter().map(|(_, file)| count_keys(&file.content)).sum::<usize>();
+        let progress = self.languages.iter()
+            .map(|language| {
+                let translated = files.iter()
+                    .filter_map(|(&file_id, _)| state.translations.get(&(file_id, language.clone())))
+                    .map(|it| count_keys(it))
+                    .sum::<usize>();
+                let progress = if total == 0 { 0 } else { translated * 100 / total };
+                (language.clone(), progress.min(100) as u8)
+            })
+            .collect();
+        Ok(progress)
+    }
+}
+
+/// Progress is approximated by number of keys, Crowdin uses number of words
+fn count_keys(content: &str) -> usize {
+    content.lines()
+        .map(str::trim_start)
+        .filter(|line| !line.starts_with(';') && !line.starts_with('#') && line.contains('='))
+        .count()
+}
@@ -0,0 +1,55 @@
+//! Types of files translated on Crowdin.
+//! Factorio locale files (`.cfg`) are uploaded as `.ini`, see [super::replace_cfg_to_ini].
+//! Other files (`changelog.txt`, mod portal description, etc.) are listed in `additional_files` option of config
+//! and keep their names on Crowdin.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FileType {
+    Ini,
+    Json,
+    PlainText,
+    Markdown,
+}
+
+impl FileType {
+    /// Detected by extension, `None` if extension is not supported
+    pub fn from_path(path: &str) -> Option<Self> {
+        let (_, extension) = path.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "cfg" | "ini" => Some(Self::Ini),
+            "json" => Some(Self::Json),
+            "txt" => Some(Self::PlainText),
+            "md" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+
+    /// `type` of file in Crowdin API
+    pub fn crowdin_type(self) -> &'static str {
+        match self {
+            Self::Ini => "ini",
+            Self::Json => "json",
+            Self::PlainText => "txt",
+            Self::Markdown => "md",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(FileType::from_path("locale/en/locale.cfg"), Some(FileType::Ini));
+        assert_eq!(FileType::from_path("info.json"), Some(FileType::Json));
+        assert_eq!(FileType::from_path("changelog.txt"), Some(FileType::PlainText));
+        assert_eq!(FileType::from_path("docs/README.MD"), Some(FileType::Markdown));
+        assert_eq!(FileType::from_path("thumbnail.png"), None);
+        assert_eq!(FileType::from_path("LICENSE"), None);
+    }
+}
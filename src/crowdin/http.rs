@@ -1,13 +1,122 @@
+use std::fmt;
 use std::ops::Deref;
+use std::sync::LazyLock;
+use std::time::Duration;
 
-use reqwest::{Method, RequestBuilder};
+use log::warn;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use reqwest::header::RETRY_AFTER;
 use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
 
 use crate::crowdin::StorageId;
-use crate::myenv::{CROWDIN_API_KEY, CROWDIN_PROJECT_ID};
+use crate::myenv::{CROWDIN_API_BASE_URL, CROWDIN_API_KEY, CROWDIN_ORGANIZATION, CROWDIN_STORAGE_URL};
 
-const BASE_URL: &str = "https://api.crowdin.com/api/v2";
+static BASE_URL: LazyLock<String> = LazyLock::new(|| {
+    get_base_url(CROWDIN_API_BASE_URL.as_deref(), CROWDIN_ORGANIZATION.as_deref())
+});
+
+// https://developer.crowdin.com/api/v2/#operation/api.storages.post
+static STORAGE_URL: LazyLock<String> = LazyLock::new(|| {
+    CROWDIN_STORAGE_URL.clone().unwrap_or_else(|| format!("{}/storages", BASE_URL.deref()))
+});
+
+/// Explicit url has priority, then Crowdin Enterprise organization, then crowdin.com
+fn get_base_url(base_url: Option<&str>, organization: Option<&str>) -> String {
+    if let Some(base_url) = base_url {
+        return base_url.trim_end_matches('/').to_owned();
+    }
+    match organization {
+        Some(organization) => format!("https://{}.api.crowdin.com/api/v2", organization),
+        None => "https://api.crowdin.com/api/v2".to_owned(),
+    }
+}
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+
+#[derive(Debug)]
+pub enum CrowdinError {
+    /// Failed to send request or read response (connection error, timeout, etc)
+    Request { url: String, source: reqwest::Error },
+    /// Crowdin responded with 4xx or 5xx code
+    Status { url: String, status: StatusCode, body: String, retry_after: Option<Duration> },
+    /// Response body has unexpected format
+    InvalidResponse { url: String, source: serde_json::Error },
+    /// Translations build finished with status other than "finished"
+    BuildFailed { build_id: i64, status: String },
+    /// Translations build is not finished in [crate::crowdin::api::MAX_BUILD_DURATION]
+    BuildTimeout { build_id: i64 },
+    /// Downloaded file is not valid zip archive
+    InvalidArchive { url: String, source: zip::result::ZipError },
+}
+
+pub type CrowdinResult<T> = Result<T, CrowdinError>;
+
+impl CrowdinError {
+    /// Whether request may succeed if repeated later
+    fn is_transient(&self) -> bool {
+        match self {
+            CrowdinError::Request { source, .. } => {
+                source.is_timeout() || source.is_connect() || source.is_request() || source.is_body()
+            }
+            CrowdinError::Status { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            CrowdinError::InvalidResponse { .. }
+            | CrowdinError::BuildFailed { .. }
+            | CrowdinError::BuildTimeout { .. }
+            | CrowdinError::InvalidArchive { .. } => false,
+        }
+    }
+
+    /// Whether request was not processed by Crowdin, so it can be repeated even if it is not idempotent
+    fn is_not_processed(&self) -> bool {
+        match self {
+            CrowdinError::Request { source, .. } => source.is_connect(),
+            CrowdinError::Status { status, .. } => *status == StatusCode::TOO_MANY_REQUESTS,
+            CrowdinError::InvalidResponse { .. }
+            | CrowdinError::BuildFailed { .. }
+            | CrowdinError::BuildTimeout { .. }
+            | CrowdinError::InvalidArchive { .. } => false,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            CrowdinError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CrowdinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrowdinError::Request { url, source } => {
+                write!(f, "Request to {} failed: {}", url, source)
+            }
+            CrowdinError::Status { url, status, body, .. } => {
+                write!(f, "Request to {} failed with code {}, response: `{}`", url, status.as_u16(), body)
+            }
+            CrowdinError::InvalidResponse { url, source } => {
+                write!(f, "Request to {} returned unexpected response: {}", url, source)
+            }
+            CrowdinError::BuildFailed { build_id, status } => {
+                write!(f, "Translations build {} finished with status `{}`", build_id, status)
+            }
+            CrowdinError::BuildTimeout { build_id } => {
+                write!(f, "Translations build {} is not finished in time", build_id)
+            }
+            CrowdinError::InvalidArchive { url, source } => {
+                write!(f, "File downloaded from {} is not valid zip archive: {}", url, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CrowdinError {}
 
 #[derive(Deserialize)]
 pub struct DataWrapper<T> { pub data: T }
@@ -18,39 +127,99 @@ pub struct IdResponse { pub id: i64 }
 #[derive(Deserialize)]
 pub struct UnitResponse {}
 
+/// `crowdin_path` is relative to base url, e.g. `/projects/1/files`
 async fn send_request<T: DeserializeOwned>(
     crowdin_path: &str,
     http_method: Method,
-    before_send: impl FnOnce(RequestBuilder) -> RequestBuilder
-) -> T {
-    let url = format!("{}/projects/{}{}", BASE_URL, CROWDIN_PROJECT_ID.deref(), crowdin_path);
-    let request = reqwest::Client::new()
-        .request(http_method, &url)
-        .bearer_auth(CROWDIN_API_KEY.deref());
-    let request = before_send(request);
-
-    let response = request.send().await.unwrap();
-    let status = response.status();
-    if status.is_client_error() || status.is_server_error() {
-        let response_text = response.text().await.unwrap();
-        panic!("Request to {} failed with code {}, response: `{}`", url, status.as_u16(), response_text);
+    before_send: impl Fn(RequestBuilder) -> RequestBuilder
+) -> CrowdinResult<T> {
+    let url = format!("{}{}", BASE_URL.deref(), crowdin_path);
+    let is_idempotent = matches!(http_method, Method::GET | Method::PUT | Method::PATCH | Method::DELETE);
+    send_with_retries(&url, is_idempotent, || {
+        let request = reqwest::Client::new()
+            .request(http_method.clone(), &url)
+            .bearer_auth(CROWDIN_API_KEY.deref());
+        before_send(request)
+    }).await
+}
+
+/// Retries on 429, 5xx and network errors with exponential backoff.
+/// Non-idempotent requests (POST) are retried only on 429 and connection errors,
+/// since otherwise Crowdin may have already created resource (e.g. directory or file).
+/// If Crowdin sends `Retry-After` header, it is used instead of backoff delay.
+async fn send_with_retries<T: DeserializeOwned>(
+    url: &str,
+    is_idempotent: bool,
+    create_request: impl Fn() -> RequestBuilder,
+) -> CrowdinResult<T> {
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let result = send_once(url, create_request()).await;
+        match result {
+            Err(error) if can_retry(&error, is_idempotent) && attempt < MAX_ATTEMPTS => {
+                let sleep_duration = error.retry_after().unwrap_or(delay).min(MAX_RETRY_DELAY);
+                warn!("[crowdin] attempt {}/{} failed, retrying in {:?}: {}", attempt, MAX_ATTEMPTS, sleep_duration, error);
+                tokio::time::sleep(sleep_duration).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn can_retry(error: &CrowdinError, is_idempotent: bool) -> bool {
+    if is_idempotent {
+        error.is_transient()
+    } else {
+        error.is_not_processed()
     }
+}
 
-    response.json::<DataWrapper<T>>().await.unwrap().data
+async fn send_once<T: DeserializeOwned>(url: &str, request: RequestBuilder) -> CrowdinResult<T> {
+    let to_request_error = |source| CrowdinError::Request { url: url.to_owned(), source };
+    let response = request.send().await.map_err(to_request_error)?;
+    let response = check_status(url, response).await?;
+    let mut response_text = response.text().await.map_err(to_request_error)?;
+    if response_text.is_empty() {
+        // 204 No Content, e.g. for DELETE requests
+        response_text = r#"{"data": {}}"#.to_owned();
+    }
+    serde_json::from_str::<DataWrapper<T>>(&response_text)
+        .map(|it| it.data)
+        .map_err(|source| CrowdinError::InvalidResponse { url: url.to_owned(), source })
+}
+
+pub async fn check_status(url: &str, response: Response) -> CrowdinResult<Response> {
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(response);
+    }
+    let retry_after = response.headers()
+        .get(RETRY_AFTER)
+        .and_then(|it| it.to_str().ok())
+        .and_then(|it| it.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    Err(CrowdinError::Status { url: url.to_owned(), status, body, retry_after })
 }
 
 async fn crowdin_get<Res: DeserializeOwned>(
     path: &str,
-    before_send: impl FnOnce(RequestBuilder) -> RequestBuilder
-) -> Res {
+    before_send: impl Fn(RequestBuilder) -> RequestBuilder
+) -> CrowdinResult<Res> {
     send_request(path, Method::GET, before_send).await
 }
 
-pub async fn crowdin_get_empty_query<Res: DeserializeOwned>(path: &str) -> Res {
+pub async fn crowdin_get_empty_query<Res: DeserializeOwned>(path: &str) -> CrowdinResult<Res> {
     crowdin_get(path, |request| request).await
 }
 
-pub async fn crowdin_get_pagination<T: DeserializeOwned>(path: &str, before_send: impl Fn(RequestBuilder) -> RequestBuilder) -> Vec<T> {
+pub async fn crowdin_get_pagination<T: DeserializeOwned>(
+    path: &str,
+    before_send: impl Fn(RequestBuilder) -> RequestBuilder,
+) -> CrowdinResult<Vec<T>> {
     const LIMIT: usize = 500;
     let mut result = Vec::new();
     for i in (0..).step_by(LIMIT) {
@@ -60,38 +229,80 @@ pub async fn crowdin_get_pagination<T: DeserializeOwned>(path: &str, before_send
             let request = request.query(&[("limit", LIMIT)]);
             #[allow(clippy::let_and_return)]
             request
-        }).await;
+        }).await?;
         if items.is_empty() { break; }
         result.extend(items);
     }
-    result
+    Ok(result)
 }
 
-pub async fn crowdin_get_pagination_empty_query<T: DeserializeOwned>(path: &str) -> Vec<T> {
+pub async fn crowdin_get_pagination_empty_query<T: DeserializeOwned>(path: &str) -> CrowdinResult<Vec<T>> {
     crowdin_get_pagination(path, |request| request).await
 }
 
-pub async fn crowdin_post<Req: Serialize, Res: DeserializeOwned>(path: &str, data: Req) -> Res {
+pub async fn crowdin_post<Req: Serialize, Res: DeserializeOwned>(path: &str, data: Req) -> CrowdinResult<Res> {
     send_request(path, Method::POST, |request| request.json(&data)).await
 }
 
-pub async fn crowdin_post_empty_body<Res: DeserializeOwned>(path: &str) -> Res {
+pub async fn crowdin_post_empty_body<Res: DeserializeOwned>(path: &str) -> CrowdinResult<Res> {
     send_request(path, Method::POST, |request| request).await
 }
 
-pub async fn crowdin_put<Req: Serialize, Res: DeserializeOwned>(method: &str, data: Req) -> Res {
+pub async fn crowdin_put<Req: Serialize, Res: DeserializeOwned>(method: &str, data: Req) -> CrowdinResult<Res> {
     send_request(method, Method::PUT, |request| request.json(&data)).await
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.storages.post
-pub async fn upload_file_to_storage(file_content: String, file_name: &str) -> StorageId {
-    let url = format!("{}/storages", BASE_URL);
-    reqwest::Client::new()
-        .post(&url)
-        .body(file_content)
-        .header("Crowdin-API-FileName", file_name)
-        .bearer_auth(CROWDIN_API_KEY.deref())
-        .send().await.unwrap()
-        .json::<DataWrapper<IdResponse>>().await.unwrap()
-        .data.id
+pub async fn crowdin_patch<Req: Serialize, Res: DeserializeOwned>(path: &str, data: Req) -> CrowdinResult<Res> {
+    send_request(path, Method::PATCH, |request| request.json(&data)).await
+}
+
+pub async fn crowdin_delete<Res: DeserializeOwned>(path: &str) -> CrowdinResult<Res> {
+    send_request(path, Method::DELETE, |request| request).await
+}
+
+/// Operation of JSON Patch request body (RFC 6902), used by Crowdin edit endpoints
+#[derive(Serialize)]
+pub struct PatchOperation<T> {
+    pub op: &'static str,
+    pub path: &'static str,
+    pub value: T,
+}
+
+impl<T> PatchOperation<T> {
+    pub fn replace(path: &'static str, value: T) -> Self {
+        Self { op: "replace", path, value }
+    }
+}
+
+pub async fn upload_file_to_storage(file_content: String, file_name: &str) -> CrowdinResult<StorageId> {
+    let url = STORAGE_URL.deref();
+    let response: IdResponse = send_with_retries(url, false, || {
+        reqwest::Client::new()
+            .post(url)
+            .body(file_content.clone())
+            .header("Crowdin-API-FileName", file_name)
+            .bearer_auth(CROWDIN_API_KEY.deref())
+    }).await?;
+    Ok(response.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_base_url() {
+        assert_eq!(get_base_url(None, None), "https://api.crowdin.com/api/v2");
+        assert_eq!(get_base_url(None, Some("acme")), "https://acme.api.crowdin.com/api/v2");
+        assert_eq!(get_base_url(Some("http://127.0.0.1:8000/api/v2/"), Some("acme")), "http://127.0.0.1:8000/api/v2");
+    }
+
+    #[test]
+    fn test_can_retry() {
+        let error = |status| CrowdinError::Status { url: "".to_owned(), status, body: "".to_owned(), retry_after: None };
+        assert!(can_retry(&error(StatusCode::INTERNAL_SERVER_ERROR), true));
+        assert!(!can_retry(&error(StatusCode::INTERNAL_SERVER_ERROR), false));
+        assert!(can_retry(&error(StatusCode::TOO_MANY_REQUESTS), false));
+        assert!(!can_retry(&error(StatusCode::BAD_REQUEST), true));
+    }
 }
@@ -1,301 +1,822 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::future::Future;
 use std::ops::Deref;
 use std::path::Path;
-use std::sync::{LazyLock, OnceLock};
-use std::time::Duration;
+use std::sync::{Arc, LazyLock, OnceLock};
 
 use log::info;
 use octocrab::models::InstallationId;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tempfile::TempDir;
 
-use crate::crowdin::http::{crowdin_get_empty_query, crowdin_get_pagination, crowdin_get_pagination_empty_query, crowdin_post, crowdin_put, DataWrapper, IdResponse, UnitResponse, upload_file_to_storage};
-use crate::github_repo_info::{GithubModInfo, GithubRepoInfo};
+use crate::crowdin::api::{CrowdinApi, HttpCrowdinApi};
+use crate::crowdin::file_type::FileType;
+pub use crate::crowdin::http::{CrowdinError, CrowdinResult};
+use crate::github_repo_info::{GithubModInfo, GithubRepoInfo, RemovedFilesPolicy};
+use crate::jobs::history;
+use crate::locale::{info_json, LocaleFile};
 use crate::mod_directory::{LanguageCode, ModDirectory};
-use crate::myenv::is_development;
+use crate::myenv::{is_development, CROWDIN_ADDITIONAL_PROJECT_IDS, CROWDIN_PROJECT_ID};
 use crate::util;
 
+pub mod api;
+pub mod directory_ids;
+pub mod fake;
+pub mod file_type;
 pub mod http;
 
-pub static PROJECT_LANGUAGE_CODES: OnceLock<Vec<String>> = OnceLock::new();
+/// Each Crowdin project has single source language.
+/// Main project has english source language, mods with other `source_language` use additional projects
+struct Project {
+    api: Arc<dyn CrowdinApi>,
+    source_language: LanguageCode,
+    /// Target languages
+    language_codes: Vec<LanguageCode>,
+}
+
+static PROJECTS: OnceLock<Vec<Project>> = OnceLock::new();
+
+tokio::task_local! {
+    /// Source language of project used by Crowdin operations, see [with_source_language]
+    static SOURCE_LANGUAGE: LanguageCode;
+}
 
 pub async fn init() {
-    let info = get_project_info().await;
-    if !is_development() {
-        assert_eq!(info.name, "Factorio mods localization");
+    let additional_project_ids = CROWDIN_ADDITIONAL_PROJECT_IDS.as_deref().unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|it| !it.is_empty())
+        .map(str::to_owned);
+    let apis = std::iter::once(CROWDIN_PROJECT_ID.clone())
+        .chain(additional_project_ids)
+        .map(|project_id| Arc::new(HttpCrowdinApi::new(project_id)) as Arc<dyn CrowdinApi>)
+        .collect();
+    init_with_apis(apis).await;
+}
+
+/// For offline tests, see [fake::FakeCrowdinApi]
+pub async fn init_with_api(api: Arc<dyn CrowdinApi>) {
+    init_with_apis(vec![api]).await;
+}
+
+/// First project is main one
+pub async fn init_with_apis(apis: Vec<Arc<dyn CrowdinApi>>) {
+    let mut projects: Vec<Project> = Vec::new();
+    for api in apis {
+        let info = api.get_project_info().await.expect("Can't get crowdin project info");
+        if projects.is_empty() {
+            if !is_development() {
+                assert_eq!(info.name, "Factorio mods localization");
+            }
+            assert!(info.target_language_ids.len() > 20);
+        }
+        let source_language = info.source_language_id;
+        if projects.iter().any(|it| it.source_language == source_language) {
+            panic!("Several Crowdin projects with source language {}", source_language);
+        }
+        projects.push(Project { api, source_language, language_codes: info.target_language_ids });
     }
+    if PROJECTS.set(projects).is_err() {
+        panic!("Crowdin API is already initialized");
+    }
+}
+
+fn projects() -> &'static [Project] {
+    PROJECTS.get().expect("Crowdin API is not initialized")
+}
+
+/// Project of current [with_source_language] scope, main project outside of any scope
+fn project() -> &'static Project {
+    let projects = projects();
+    SOURCE_LANGUAGE
+        .try_with(|source_language| {
+            projects.iter()
+                .find(|it| it.source_language == *source_language)
+                .unwrap_or_else(|| panic!("No Crowdin project with source language {}", source_language))
+        })
+        .unwrap_or(&projects[0])
+}
 
-    let codes = info.target_language_ids;
-    assert!(codes.len() > 20);
-    PROJECT_LANGUAGE_CODES.set(codes).unwrap();
+fn api() -> &'static dyn CrowdinApi {
+    project().api.as_ref()
 }
 
-type DirectoryId = i64;
+/// Crowdin operations inside `future` use project with given source language,
+/// which should be checked by [has_project_with_source_language]
+pub async fn with_source_language<F: Future>(source_language: &str, future: F) -> F::Output {
+    SOURCE_LANGUAGE.scope(normalize_language_code(source_language), future).await
+}
+
+pub fn has_project_with_source_language(source_language: &str) -> bool {
+    let source_language = normalize_language_code(source_language);
+    projects().iter().any(|it| it.source_language == source_language)
+}
+
+/// Source languages of all projects, main project is first
+pub fn source_languages() -> Vec<LanguageCode> {
+    projects().iter().map(|it| it.source_language.clone()).collect()
+}
+
+/// Target languages of current project
+pub fn project_language_codes() -> &'static [LanguageCode] {
+    &project().language_codes
+}
+
+pub type DirectoryId = i64;
 /// id of english file
-type FileId = i64;
+pub type FileId = i64;
 /// id of english/localized file in storage
-type StorageId = i64;
+pub type StorageId = i64;
 
 #[derive(Deserialize)]
-struct ProjectInfo {
+pub struct ProjectInfo {
+    #[serde(rename = "sourceLanguageId")]
+    pub source_language_id: String,
     #[serde(rename = "targetLanguageIds")]
-    target_language_ids: Vec<String>,
-    name: String,
+    pub target_language_ids: Vec<String>,
+    pub name: String,
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.get
-async fn get_project_info() -> ProjectInfo {
-    crowdin_get_empty_query("").await
+/// Directory of mod, see [directory_ids]
+pub async fn find_directory_id(mod_info: &GithubModInfo) -> CrowdinResult<Option<DirectoryId>> {
+    let directories = RootDirectories::list().await?;
+    Ok(directories.resolve(mod_info).map(|(id, _name)| id))
 }
 
-pub async fn find_directory_id(crowdin_name: &str) -> Option<DirectoryId> {
-    list_directories().await
+async fn find_root_directory_id(crowdin_name: &str) -> CrowdinResult<Option<DirectoryId>> {
+    let directory_id = list_directories().await?
         .find(|(name, _id)| name == crowdin_name)
-        .map(|(_name, id)| id)
+        .map(|(_name, id)| id);
+    Ok(directory_id)
+}
+
+/// Snapshot of root directories, allows to resolve many mods using single request
+pub struct RootDirectories {
+    names: HashMap<DirectoryId, String>,
+}
+
+impl RootDirectories {
+    pub async fn list() -> CrowdinResult<Self> {
+        let names = list_directories().await?
+            .map(|(name, id)| (id, name))
+            .collect();
+        Ok(Self { names })
+    }
+
+    /// Mapped directory is used if it still exists, otherwise directory is found by name and mapping is recorded
+    pub fn resolve(&self, mod_info: &GithubModInfo) -> Option<(DirectoryId, String)> {
+        if let Some(mapped) = directory_ids::get(mod_info) {
+            if let Some(name) = self.names.get(&mapped.id) {
+                directory_ids::set(mod_info, mapped.id, name);
+                return Some((mapped.id, name.clone()));
+            }
+        }
+        let crowdin_name = get_crowdin_directory_name(mod_info);
+        let (&id, _name) = self.names.iter().find(|(_id, name)| **name == crowdin_name)?;
+        directory_ids::set(mod_info, id, &crowdin_name);
+        Some((id, crowdin_name))
+    }
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.directories.getMany
-pub async fn list_directories() -> impl Iterator<Item=(String, DirectoryId)> {
-    #[derive(Deserialize)]
-    struct Directory { id: DirectoryId, name: String }
-    let directories: Vec<DataWrapper<Directory>> = crowdin_get_pagination_empty_query("/directories").await;
-    directories.into_iter().map(|d| (d.data.name, d.data.id))
+/// Root directories, each corresponds to one mod
+pub async fn list_directories() -> CrowdinResult<impl Iterator<Item=(String, DirectoryId)>> {
+    Ok(api().list_directories(None).await?.into_iter())
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.files.getMany
-pub async fn list_files(directory_id: DirectoryId) -> impl Iterator<Item=(String, FileId)> {
-    #[derive(Deserialize)]
-    struct File { id: FileId, name: String }
-    let files: Vec<DataWrapper<File>> = crowdin_get_pagination("/files", |request| {
-        request.query(&[("directoryId", directory_id)])
-    }).await;
-    files.into_iter().map(|d| (d.data.name, d.data.id))
+pub async fn list_files(directory_id: DirectoryId) -> CrowdinResult<impl Iterator<Item=(String, FileId)>> {
+    Ok(api().list_files(directory_id).await?.into_iter())
+}
+
+pub async fn create_directory(name: &str) -> CrowdinResult<DirectoryId> {
+    api().create_directory(name, None).await
+}
+
+/// Files and subdirectories of directory on any depth.
+/// Paths are relative to directory and separated by `/`, e.g. `subfolder/locale.ini`,
+/// so they match paths of files inside language folder of mod
+struct DirectoryTree {
+    files: HashMap<String, FileId>,
+    /// Directory itself has empty path
+    directories: HashMap<String, DirectoryId>,
+}
+
+impl DirectoryTree {
+    async fn list(directory_id: DirectoryId) -> CrowdinResult<Self> {
+        let mut files = HashMap::new();
+        let mut directories = HashMap::new();
+        let mut queue = vec![(String::new(), directory_id)];
+        while let Some((path, directory_id)) = queue.pop() {
+            for (name, file_id) in list_files(directory_id).await? {
+                files.insert(join_path(&path, &name), file_id);
+            }
+            for (name, subdirectory_id) in api().list_directories(Some(directory_id)).await? {
+                queue.push((join_path(&path, &name), subdirectory_id));
+            }
+            directories.insert(path, directory_id);
+        }
+        Ok(Self { files, directories })
+    }
+
+    /// Missing directories on the path are created
+    async fn get_or_create_directory(&mut self, path: &str) -> CrowdinResult<DirectoryId> {
+        let mut current_path = String::new();
+        let mut current_id = self.directories[""];
+        for name in path.split('/').filter(|it| !it.is_empty()) {
+            current_path = join_path(&current_path, name);
+            current_id = match self.directories.get(&current_path) {
+                Some(&id) => id,
+                None => {
+                    let id = api().create_directory(name, Some(current_id)).await?;
+                    self.directories.insert(current_path.clone(), id);
+                    id
+                }
+            };
+        }
+        Ok(current_id)
+    }
+}
+
+fn join_path(directory: &str, name: &str) -> String {
+    if directory.is_empty() { name.to_owned() } else { format!("{}/{}", directory, name) }
+}
+
+/// `subfolder/locale.ini` => (`subfolder`, `locale.ini`)
+fn split_path(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+/// Root directory for directories of uninstalled repositories,
+/// whose translations should be kept. It is not exported to repositories.
+pub const ARCHIVED_DIRECTORY_NAME: &str = "_archived";
+
+/// Root directory for english files which are removed from installed repositories,
+/// separate from [ARCHIVED_DIRECTORY_NAME] so they are not treated as uninstalled repositories.
+/// It is not exported to repositories.
+pub const ARCHIVED_FILES_DIRECTORY_NAME: &str = "_archived_files";
+
+/// Returns `{root}/{name}` directory, creating it if needed
+async fn get_or_create_archived_directory(root: &str, name: &str) -> CrowdinResult<DirectoryId> {
+    let archived_id = match find_root_directory_id(root).await? {
+        Some(id) => id,
+        None => create_directory(root).await?,
+    };
+    let directory_id = api().list_directories(Some(archived_id)).await?
+        .into_iter()
+        .find(|(it, _id)| it == name)
+        .map(|(_name, id)| id);
+    match directory_id {
+        Some(id) => Ok(id),
+        None => api().create_directory(name, Some(archived_id)).await,
+    }
+}
+
+/// Subdirectories of `_archived`, each corresponds to one mod
+pub async fn list_archived_directories() -> CrowdinResult<Vec<(String, DirectoryId)>> {
+    list_subdirectories(ARCHIVED_DIRECTORY_NAME).await
+}
+
+/// Subdirectories of `_archived_files`, each contains removed files of one mod
+pub async fn list_archived_files_directories() -> CrowdinResult<Vec<(String, DirectoryId)>> {
+    list_subdirectories(ARCHIVED_FILES_DIRECTORY_NAME).await
+}
+
+async fn list_subdirectories(root: &str) -> CrowdinResult<Vec<(String, DirectoryId)>> {
+    match find_root_directory_id(root).await? {
+        Some(root_id) => api().list_directories(Some(root_id)).await,
+        None => Ok(vec![]),
+    }
+}
+
+/// Moves files of root directory to `_archived/{name}` and deletes root directory.
+/// Translations are kept, but directory is hidden from translators and not exported to GitHub.
+pub async fn archive_directory(directory_id: DirectoryId, name: &str) -> CrowdinResult<()> {
+    let archived_id = get_or_create_archived_directory(ARCHIVED_DIRECTORY_NAME, name).await?;
+    // Mapping is kept, so archived directory can be found by id after uninstall
+    directory_ids::replace_id(directory_id, archived_id);
+    move_directory_content(directory_id, archived_id).await
+}
+
+/// Reverse of [archive_directory], returns id of restored root directory.
+/// Archived directory is found by mapped id first, then by name
+async fn restore_archived_directory(mod_info: &GithubModInfo, name: &str) -> CrowdinResult<Option<DirectoryId>> {
+    let archived = list_archived_directories().await?;
+    let mapped_id = directory_ids::get(mod_info).map(|it| it.id);
+    let archived_id = archived.iter()
+        .find(|(_name, id)| Some(*id) == mapped_id)
+        .or_else(|| archived.iter().find(|(it, _id)| it == name))
+        .map(|(_name, id)| *id);
+    let Some(archived_id) = archived_id else { return Ok(None); };
+    let directory_id = create_directory(name).await?;
+    move_directory_content(archived_id, directory_id).await?;
+    Ok(Some(directory_id))
+}
+
+/// Renames root and archived directories for which `get_new_name(name, id)` returns new name,
+/// translations are kept. Directories with removed files of renamed directories are renamed too.
+/// Returns pairs of old and new names.
+pub async fn rename_directories(get_new_name: impl Fn(&str, DirectoryId) -> Option<String>) -> CrowdinResult<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    for source_language in source_languages() {
+        with_source_language(&source_language, async {
+            let directories = list_directories().await?
+                .chain(list_archived_directories().await?)
+                .collect::<Vec<_>>();
+            for (name, directory_id) in directories {
+                let Some(new_name) = get_new_name(&name, directory_id) else { continue; };
+                info!("[crowdin] rename directory: {} => {}", name, new_name);
+                api().rename_directory(directory_id, &new_name).await?;
+                directory_ids::set_name(directory_id, &new_name);
+                result.push((name, new_name));
+            }
+            for (name, directory_id) in list_archived_files_directories().await? {
+                let Some((_, new_name)) = result.iter().find(|(old_name, _)| *old_name == name) else { continue; };
+                info!("[crowdin] rename directory: {}/{} => {}", ARCHIVED_FILES_DIRECTORY_NAME, name, new_name);
+                api().rename_directory(directory_id, new_name).await?;
+            }
+            Ok(())
+        }).await?;
+    }
+    Ok(result)
+}
+
+/// Deletes directory together with files and translations
+pub async fn delete_directory(directory_id: DirectoryId) -> CrowdinResult<()> {
+    api().delete_directory(directory_id).await?;
+    directory_ids::remove(directory_id);
+    Ok(())
+}
+
+/// Files with same path in target directory are replaced, source directory is deleted
+async fn move_directory_content(source_id: DirectoryId, target_id: DirectoryId) -> CrowdinResult<()> {
+    let source = DirectoryTree::list(source_id).await?;
+    let mut target = DirectoryTree::list(target_id).await?;
+    for (path, file_id) in source.files {
+        let (directory_path, file_name) = split_path(&path);
+        let directory_id = target.get_or_create_directory(directory_path).await?;
+        move_file_replacing(file_id, file_name, directory_id).await?;
+    }
+    delete_directory(source_id).await
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.directories.post
-pub async fn create_directory(name: &str) -> DirectoryId {
-    #[derive(Serialize)]
-    struct Request<'a> { name: &'a str }
-    let request = Request { name };
-    crowdin_post::<_, IdResponse>("/directories", request).await.id
+async fn move_file_replacing(file_id: FileId, file_name: &str, directory_id: DirectoryId) -> CrowdinResult<()> {
+    let previous = list_files(directory_id).await?.find(|(name, _id)| name == file_name);
+    if let Some((_name, previous_id)) = previous {
+        delete_file(previous_id).await?;
+    }
+    move_file(file_id, directory_id).await
 }
 
 pub async fn filter_repositories(
     repositories: Vec<(GithubRepoInfo, InstallationId)>
-) -> Vec<(GithubRepoInfo, InstallationId)> {
-    let directories = list_directories().await
-        .map(|(name, _id)| name)
-        .collect::<HashSet<_>>();
-    repositories
+) -> CrowdinResult<Vec<(GithubRepoInfo, InstallationId)>> {
+    let mut directories = HashMap::new();
+    for source_language in source_languages() {
+        let project_directories = with_source_language(&source_language, RootDirectories::list()).await?;
+        directories.insert(source_language, project_directories);
+    }
+    let repositories = repositories
         .into_iter()
         .filter_map(|(repo_info, api)| {
-            let repo_info = repo_info.filter_mods_present_on_crowdin(&directories)?;
+            let repo_info = repo_info.filter_mods_present_on_crowdin(|it| {
+                directories.get(&normalize_language_code(&it.source_language))
+                    .is_some_and(|directories| directories.resolve(it).is_some())
+            })?;
             Some((repo_info, api))
         })
-        .collect()
+        .collect();
+    Ok(repositories)
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.files.post
-async fn add_english_file(directory_id: DirectoryId, storage_id: StorageId, file_name: &str) -> FileId {
-    #[derive(Serialize)]
-    struct Request<'a> {
-        #[serde(rename = "directoryId")]
-        directory_id: DirectoryId,
-        #[serde(rename = "storageId")]
-        storage_id: StorageId,
-        #[serde(rename = "name")]
-        file_name: &'a str,
-        r#type: &'static str,
-    }
-    let request = Request { directory_id, storage_id, file_name, r#type: "ini" };
-    crowdin_post::<_, IdResponse>("/files", request).await.id
+async fn add_english_file(
+    directory_id: DirectoryId,
+    storage_id: StorageId,
+    file_name: &str,
+    file_type: FileType,
+    excluded_languages: &[LanguageCode],
+) -> CrowdinResult<FileId> {
+    api().add_english_file(directory_id, storage_id, file_name, file_type, excluded_languages).await
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.files.put
-async fn update_english_file(file_id: FileId, storage_id: StorageId) {
-    #[derive(Serialize)]
-    struct Request {
-        #[serde(rename = "storageId")]
-        storage_id: StorageId,
-    }
-    let request = Request { storage_id };
-    let method = format!("/files/{}", file_id);
-    crowdin_put::<_, UnitResponse>(&method, request).await;
+async fn update_english_file(file_id: FileId, storage_id: StorageId) -> CrowdinResult<()> {
+    api().update_english_file(file_id, storage_id).await
+}
+
+async fn set_excluded_languages(file_id: FileId, excluded_languages: &[LanguageCode]) -> CrowdinResult<()> {
+    api().set_excluded_languages(file_id, excluded_languages).await
+}
+
+async fn rename_file(file_id: FileId, name: &str) -> CrowdinResult<()> {
+    api().rename_file(file_id, name).await
+}
+
+async fn move_file(file_id: FileId, directory_id: DirectoryId) -> CrowdinResult<()> {
+    api().move_file(file_id, directory_id).await
+}
+
+async fn delete_file(file_id: FileId) -> CrowdinResult<()> {
+    api().delete_file(file_id).await
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.translations.postOnLanguage
 pub async fn add_localization_file(
     // id of source english file
     english_file_id: FileId,
     // id of localized file
     storage_id: StorageId,
     language_code: &LanguageCode,
-) {
-    #[derive(Serialize)]
-    struct Request {
-        #[serde(rename = "fileId")]
-        english_file_id: FileId,
-        #[serde(rename = "storageId")]
-        storage_id: StorageId,
-        /// Defines whether to add translation if it's the same as the source string
-        #[serde(rename = "importEqSuggestions")]
-        import_eq_suggestions: bool,
-        /// Mark uploaded translations as approved
-        #[serde(rename = "autoApproveImported")]
-        auto_approve_imported: bool,
-    }
-    let request = Request {
-        english_file_id,
-        storage_id,
-        import_eq_suggestions: false,
-        auto_approve_imported: false,
-    };
-    let path = format!("/translations/{}", language_code);
-    crowdin_post::<_, UnitResponse>(&path, request).await;
+) -> CrowdinResult<()> {
+    api().add_localization_file(english_file_id, storage_id, language_code).await
+}
+
+async fn upload_file_to_storage(file_content: String, file_name: &str) -> CrowdinResult<StorageId> {
+    api().upload_file_to_storage(file_content, file_name).await
 }
 
-pub async fn download_all_translations() -> TempDir {
-    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.post
-    async fn build_translations() -> i64 {
-        #[derive(Serialize)]
-        struct Request {
-            #[serde(rename = "skipUntranslatedStrings")]
-            skip_untranslated_strings: bool,
+pub async fn download_all_translations() -> CrowdinResult<TempDir> {
+    let result = api().download_all_translations(None, false).await?;
+    for (language_path, _) in util::read_dir(result.path()) {
+        for archived_name in [ARCHIVED_DIRECTORY_NAME, ARCHIVED_FILES_DIRECTORY_NAME] {
+            let archived_path = language_path.join(archived_name);
+            if archived_path.exists() {
+                fs::remove_dir_all(archived_path).unwrap();
+            }
         }
-        let request = Request { skip_untranslated_strings: true };
-        crowdin_post::<_, IdResponse>("/translations/builds", request).await.id
     }
-    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.get
-    async fn is_build_finished(build_id: i64) -> bool {
-        #[derive(Deserialize)]
-        struct Response { status: String }
-        let url = format!("/translations/builds/{}", build_id);
-        let response = crowdin_get_empty_query::<Response>(&url).await;
-        response.status != "inProgress"
+    util::remove_empty_translation_files(result.path());
+    Ok(result)
+}
+
+/// Which translations to build on Crowdin
+pub enum TranslationsBuild {
+    /// Whole project, faster when most of mods are updated
+    Project,
+    /// Only directories of mods being updated
+    Directories,
+}
+
+/// Returns translations only of given mods, which should be resolved by [filter_repositories].
+/// Translations of mod are in `{language}/{directory_ids::get_name(mod)}`.
+/// Mods with other source language are in other project, and build options are same for whole build,
+/// so mods are built separately for each source language and `export_only_approved` value
+pub async fn download_translations_for_mods(mods: &[&GithubModInfo], build: TranslationsBuild) -> CrowdinResult<TempDir> {
+    let mut groups: BTreeMap<(LanguageCode, bool), Vec<&GithubModInfo>> = BTreeMap::new();
+    for &mod_info in mods {
+        let source_language = normalize_language_code(&mod_info.source_language);
+        groups.entry((source_language, mod_info.export_only_approved)).or_default().push(mod_info);
+    }
+    let result = util::create_temporary_directory();
+    for ((source_language, export_approved_only), mods) in groups {
+        let download = download_translations_group(&mods, &build, export_approved_only, result.path());
+        with_source_language(&source_language, download).await?;
     }
-    // https://developer.crowdin.com/api/v2/#operation/api.projects.translations.builds.download.download
-    async fn get_build_download_url(build_id: i64) -> String {
-        #[derive(Deserialize)]
-        struct Response { url: String }
-        let url = format!("/translations/builds/{}/download", build_id);
-        crowdin_get_empty_query::<Response>(&url).await.url
+    util::remove_empty_translation_files(result.path());
+    Ok(result)
+}
+
+async fn download_translations_group(
+    mods: &[&GithubModInfo],
+    build: &TranslationsBuild,
+    export_approved_only: bool,
+    result: &Path,
+) -> CrowdinResult<()> {
+    match build {
+        TranslationsBuild::Project => {
+            let directories = mods.iter()
+                .map(|mod_info| directory_ids::get_name(mod_info))
+                .collect::<HashSet<_>>();
+            let translations = api().download_all_translations(Some(&directories), export_approved_only).await?;
+            move_translations(translations.path(), result);
+        }
+        TranslationsBuild::Directories => {
+            let directories = RootDirectories::list().await?;
+            for mod_info in mods {
+                let Some((directory_id, _name)) = directories.resolve(mod_info) else { continue; };
+                let translations = api().download_directory_translations(directory_id, export_approved_only).await?;
+                move_translations(translations.path(), result);
+            }
+        }
     }
+    Ok(())
+}
 
-    let build_id = build_translations().await;
-    while !is_build_finished(build_id).await {
-        tokio::time::sleep(Duration::from_secs(1)).await;
+/// `ru/Factorio Mod Example (dima74)` => `target/ru/Factorio Mod Example (dima74)`
+fn move_translations(source: &Path, target: &Path) {
+    for (language_path, language) in util::read_dir(source) {
+        let target_language_path = target.join(language);
+        fs::create_dir_all(&target_language_path).unwrap();
+        for (directory_path, directory_name) in util::read_dir(&language_path) {
+            fs::rename(directory_path, target_language_path.join(directory_name)).unwrap();
+        }
     }
-    let url = get_build_download_url(build_id).await;
-    let result = util::download_and_extract_zip_file(&url).await;
-    util::remove_empty_ini_files(result.path());
-    result
 }
 
-// https://developer.crowdin.com/api/v2/#operation/api.projects.files.get
-pub async fn download_file(file_id: FileId) -> fs::File {
-    #[derive(Deserialize)]
-    struct Response { url: String }
-    let url = format!("/files/{}/download", file_id);
-    let response: Response = crowdin_get_empty_query(&url).await;
-    util::download_file(&response.url).await
+pub async fn download_file(file_id: FileId) -> CrowdinResult<fs::File> {
+    api().download_file(file_id).await
+}
+
+async fn read_crowdin_file(file_id: FileId) -> CrowdinResult<String> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = download_file(file_id).await?;
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).unwrap();
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Translation progress of mod directory in percents for each language, empty if there is no directory
+pub async fn get_translation_progress(mod_info: &GithubModInfo) -> CrowdinResult<HashMap<LanguageCode, u8>> {
+    with_source_language(&mod_info.source_language, async {
+        let Some(directory_id) = find_directory_id(mod_info).await? else { return Ok(HashMap::new()); };
+        api().get_directory_progress(directory_id).await
+    }).await
+}
+
+/// File uploaded to Crowdin
+struct SourceFile {
+    /// Path on Crowdin relative to mod directory, e.g. `subfolder/locale.ini`
+    name: String,
+    /// Path in repository, e.g. `locale/en/subfolder/locale.cfg`, used for history
+    repository_path: String,
+    content: String,
+    file_type: FileType,
+}
+
+fn read_file(path: &Path) -> String {
+    String::from_utf8_lossy(&fs::read(path).unwrap()).into_owned()
 }
 
 pub struct CrowdinDirectory {
     crowdin_id: DirectoryId,
-    #[allow(unused)]
     crowdin_name: String,
     pub mod_directory: ModDirectory,
 }
 
 impl CrowdinDirectory {
-    pub async fn get_or_create(mod_directory: ModDirectory) -> (CrowdinDirectory, bool) {
-        let crowdin_name = get_crowdin_directory_name(&mod_directory.mod_info);
-        let (crowdin_id, created) = match find_directory_id(&crowdin_name).await {
-            Some(crowdin_id) => (crowdin_id, false),
-            None => (create_directory(&crowdin_name).await, true),
+    pub async fn get_or_create(mod_directory: ModDirectory) -> CrowdinResult<(CrowdinDirectory, bool)> {
+        let mod_info = &mod_directory.mod_info;
+        let existing = RootDirectories::list().await?.resolve(mod_info);
+        let (crowdin_id, crowdin_name, created) = match existing {
+            Some((crowdin_id, crowdin_name)) => (crowdin_id, crowdin_name, false),
+            None => {
+                let crowdin_name = get_crowdin_directory_name(mod_info);
+                // Repository was uninstalled and then installed again
+                let (crowdin_id, created) = match restore_archived_directory(mod_info, &crowdin_name).await? {
+                    Some(crowdin_id) => {
+                        info!("[{}] restored archived crowdin directory", mod_info);
+                        (crowdin_id, false)
+                    }
+                    None => (create_directory(&crowdin_name).await?, true),
+                };
+                directory_ids::set(mod_info, crowdin_id, &crowdin_name);
+                (crowdin_id, crowdin_name, created)
+            }
         };
-        (Self { crowdin_id, crowdin_name, mod_directory }, created)
+        Ok((Self { crowdin_id, crowdin_name, mod_directory }, created))
     }
 
-    pub async fn has_existing(mod_directory: &ModDirectory) -> bool {
-        let crowdin_name = get_crowdin_directory_name(&mod_directory.mod_info);
-        find_directory_id(&crowdin_name).await.is_some()
+    pub async fn has_existing(mod_directory: &ModDirectory) -> CrowdinResult<bool> {
+        Ok(find_directory_id(&mod_directory.mod_info).await?.is_some())
     }
 
-    pub async fn add_english_and_localization_files(&self) {
-        let english_file_ids = self.add_english_files().await;
-        self.add_localization_files(english_file_ids).await;
+    pub async fn add_english_and_localization_files(&self) -> CrowdinResult<()> {
+        let english_file_ids = self.add_english_files().await?;
+        self.add_localization_files(english_file_ids).await
     }
 
-    pub async fn add_english_files(&self) -> HashMap<String, FileId> {
-        let existing_crowdin_files: HashMap<String, FileId> = list_files(self.crowdin_id).await.collect();
+    pub async fn add_english_files(&self) -> CrowdinResult<HashMap<String, FileId>> {
+        self.sync_english_files(&[]).await
+    }
+
+    /// Makes files on Crowdin match english files in repository:
+    /// - `renamed_files` (pairs of old and new `.cfg` file names) are renamed, so translations are kept
+    /// - new files are added and existing are updated
+    /// - files without corresponding english file are deleted or archived, see [RemovedFilesPolicy]
+    ///
+    /// Subfolders of `locale/en` are mirrored as nested Crowdin directories,
+    /// file names are paths inside language folder, e.g. `subfolder/locale.cfg`.
+    /// Only renames inside same subfolder are supported.
+    pub async fn sync_english_files(&self, renamed_files: &[(String, String)]) -> CrowdinResult<HashMap<String, FileId>> {
+        let mut tree = DirectoryTree::list(self.crowdin_id).await?;
+        let mut existing_crowdin_files = std::mem::take(&mut tree.files);
+        let source_files = self.get_source_files();
+        let source_file_names = source_files.iter()
+            .map(|file| file.name.clone())
+            .collect::<HashSet<_>>();
+
+        for (old_name, new_name) in renamed_files {
+            let old_name = replace_cfg_to_ini(old_name);
+            let new_name = replace_cfg_to_ini(new_name);
+            if !source_file_names.contains(&new_name) || existing_crowdin_files.contains_key(&new_name) { continue; }
+            let (old_directory, _) = split_path(&old_name);
+            let (new_directory, new_file_name) = split_path(&new_name);
+            if old_directory != new_directory { continue; }
+            let Some(file_id) = existing_crowdin_files.remove(&old_name) else { continue; };
+            info!("[{}] rename file on crowdin: {} => {}", self.mod_directory.mod_info, old_name, new_name);
+            rename_file(file_id, new_file_name).await?;
+            existing_crowdin_files.insert(new_name, file_id);
+        }
+
         let mut result = HashMap::new();
-        for file_path in self.mod_directory.get_english_files() {
-            let file_name_ini = replace_cfg_to_ini(util::file_name(&file_path));
-            let file_id = self.add_or_update_english_file(&existing_crowdin_files, &file_path, &file_name_ini).await;
-            result.insert(file_name_ini, file_id);
+        for file in source_files {
+            let file_id = match existing_crowdin_files.get(&file.name) {
+                Some(&file_id) => {
+                    self.update_english_file(file_id, &file).await?;
+                    file_id
+                }
+                None => {
+                    let (directory_path, _) = split_path(&file.name);
+                    let directory_id = tree.get_or_create_directory(directory_path).await?;
+                    self.add_english_file(directory_id, &file).await?
+                }
+            };
+            result.insert(file.name, file_id);
+        }
+
+        for (file_name, file_id) in existing_crowdin_files {
+            if !source_file_names.contains(&file_name) {
+                self.remove_file(file_id, &file_name).await?;
+            }
+        }
+        self.delete_empty_subdirectories(&tree, &result).await?;
+        Ok(result)
+    }
+
+    /// English locale files, synthetic file generated from `info.json` and existing files of `additional_files` option
+    fn get_source_files(&self) -> Vec<SourceFile> {
+        let mod_directory = &self.mod_directory;
+        let mut result = mod_directory.get_english_files()
+            .into_iter()
+            .map(|path| SourceFile {
+                name: replace_cfg_to_ini(&mod_directory.get_file_name(&path)),
+                repository_path: mod_directory.get_repository_path(&path),
+                content: read_file(&path),
+                file_type: FileType::Ini,
+            })
+            .collect::<Vec<_>>();
+        if let Some(content) = mod_directory.get_info_json_english_file() {
+            result.push(SourceFile {
+                name: replace_cfg_to_ini(info_json::FILE_NAME),
+                repository_path: mod_directory.mod_info.info_json_path(),
+                content,
+                file_type: FileType::Ini,
+            });
+        }
+        for (path, file) in mod_directory.get_additional_files() {
+            result.push(SourceFile {
+                name: file.crowdin_name().to_owned(),
+                repository_path: mod_directory.get_repository_path(&path),
+                content: read_file(&path),
+                file_type: file.file_type(),
+            });
         }
         result
     }
 
-    async fn add_or_update_english_file(
-        &self,
-        existing_crowdin_files: &HashMap<String, FileId>,
-        file_path: &Path,
-        file_name_ini: &str,
-    ) -> FileId {
-        match existing_crowdin_files.get(file_name_ini) {
-            Some(&file_id) => {
-                self.update_english_file(file_id, file_path, file_name_ini).await;
-                file_id
+    /// Subdirectories whose files were all removed from repository
+    async fn delete_empty_subdirectories(&self, tree: &DirectoryTree, files: &HashMap<String, FileId>) -> CrowdinResult<()> {
+        let mut empty_directories = tree.directories.iter()
+            .filter(|(path, _id)| !path.is_empty())
+            .filter(|(path, _id)| !files.keys().any(|file| file.starts_with(&format!("{}/", path))))
+            .collect::<Vec<_>>();
+        empty_directories.sort();
+        let mut deleted: Vec<&str> = Vec::new();
+        for (path, &directory_id) in empty_directories {
+            // Nested directories are deleted together with parent
+            if deleted.iter().any(|it| path.starts_with(&format!("{}/", it))) { continue; }
+            info!("[{}] delete empty directory on crowdin: {}", self.mod_directory.mod_info, path);
+            delete_directory(directory_id).await?;
+            deleted.push(path);
+        }
+        Ok(())
+    }
+
+    async fn remove_file(&self, file_id: FileId, file_name: &str) -> CrowdinResult<()> {
+        let mod_info = &self.mod_directory.mod_info;
+        let repository_path = if FileType::from_path(file_name) == Some(FileType::Ini) {
+            format!("{}/{}/{}", mod_info.locale_path, mod_info.source_language, replace_ini_to_cfg(file_name))
+        } else {
+            // Additional file, its path is not known if it was removed from config
+            file_name.to_owned()
+        };
+        match mod_info.removed_english_files {
+            RemovedFilesPolicy::Delete => {
+                info!("[{}] delete file on crowdin: {}", mod_info, file_name);
+                delete_file(file_id).await?;
+                history::add_changed_file(&mod_info.full_name(), format!("{} (deleted on Crowdin)", repository_path));
             }
-            None => {
-                self.add_english_file(file_path, file_name_ini).await
+            RemovedFilesPolicy::Archive => {
+                info!("[{}] archive file on crowdin: {}", mod_info, file_name);
+                let archived_id = get_or_create_archived_directory(ARCHIVED_FILES_DIRECTORY_NAME, &self.crowdin_name).await?;
+                let (directory_path, name) = split_path(file_name);
+                let directory_id = DirectoryTree::list(archived_id).await?.get_or_create_directory(directory_path).await?;
+                // Previously archived file with same name is replaced
+                move_file_replacing(file_id, name, directory_id).await?;
+                history::add_changed_file(&mod_info.full_name(), format!("{} (archived on Crowdin)", repository_path));
             }
         }
+        Ok(())
     }
 
-    async fn add_english_file(&self, file: &Path, file_name: &str) -> FileId {
-        let storage_id = self.upload_file_to_storage(file, file_name).await;
-        add_english_file(self.crowdin_id, storage_id, file_name).await
+    /// `directory_id` is id of (possibly nested) directory containing file
+    async fn add_english_file(&self, directory_id: DirectoryId, file: &SourceFile) -> CrowdinResult<FileId> {
+        history::add_changed_file(&self.mod_directory.mod_info.full_name(), file.repository_path.clone());
+        let storage_id = self.upload_file_to_storage(file).await?;
+        let (_, name) = split_path(&file.name);
+        add_english_file(directory_id, storage_id, name, file.file_type, &self.get_excluded_languages()).await
+    }
+
+    /// File is uploaded only if its content differs from one on Crowdin.
+    /// Excluded languages are updated anyway, since config could be changed
+    async fn update_english_file(&self, file_id: FileId, file: &SourceFile) -> CrowdinResult<()> {
+        let content = self.get_upload_content(file);
+        if read_crowdin_file(file_id).await? != content {
+            history::add_changed_file(&self.mod_directory.mod_info.full_name(), file.repository_path.clone());
+            let storage_id = self.upload_file_to_storage(file).await?;
+            update_english_file(file_id, storage_id).await?;
+        } else {
+            info!("[{}] file is not changed: {}", self.mod_directory.mod_info, file.repository_path);
+        }
+        set_excluded_languages(file_id, &self.get_excluded_languages()).await
     }
 
-    async fn update_english_file(&self, file_id: FileId, file: &Path, file_name: &str) {
-        let storage_id = self.upload_file_to_storage(file, file_name).await;
-        update_english_file(file_id, storage_id).await;
+    /// Project languages disabled by `languages` and `exclude_languages` options of config
+    fn get_excluded_languages(&self) -> Vec<LanguageCode> {
+        project_language_codes().iter()
+            .filter(|code| !self.mod_directory.mod_info.languages.is_enabled(code))
+            .cloned()
+            .collect()
     }
 
-    async fn add_localization_files(&self, english_file_ids: HashMap<String, FileId>) {
+    async fn add_localization_files(&self, english_file_ids: HashMap<String, FileId>) -> CrowdinResult<()> {
+        let mod_info = &self.mod_directory.mod_info;
         for (language_code, files) in self.mod_directory.get_localizations() {
-            for file in files {
-                let file_name = replace_cfg_to_ini(util::file_name(&file));
-                let english_file_id = english_file_ids[&file_name];
-                self.add_localization_file(&file, &file_name, english_file_id, &language_code).await;
+            if !mod_info.languages.is_enabled(&language_code) {
+                info!("[{}] skip translations for disabled language {}", mod_info, language_code);
+                continue;
+            }
+            for path in files {
+                let file = SourceFile {
+                    name: replace_cfg_to_ini(&self.mod_directory.get_file_name(&path)),
+                    repository_path: self.mod_directory.get_repository_path(&path),
+                    content: read_file(&path),
+                    file_type: FileType::Ini,
+                };
+                let english_file_id = english_file_ids[&file.name];
+                self.add_localization_file(&file, english_file_id, &language_code).await?;
             }
         }
+
+        for (_path, file) in self.mod_directory.get_additional_files() {
+            let Some(&english_file_id) = english_file_ids.get(file.crowdin_name()) else { continue; };
+            for language_code in project_language_codes() {
+                if !mod_info.languages.is_enabled(language_code) { continue; }
+                let Some(path) = self.mod_directory.get_additional_file_translation_path(file, language_code) else { continue; };
+                if !path.is_file() { continue; }
+                let file = SourceFile {
+                    name: file.crowdin_name().to_owned(),
+                    repository_path: self.mod_directory.get_repository_path(&path),
+                    content: read_file(&path),
+                    file_type: file.file_type(),
+                };
+                self.add_localization_file(&file, english_file_id, language_code).await?;
+            }
+        }
+        Ok(())
     }
 
+    /// `file` is translation, its name is name of english file on Crowdin
     async fn add_localization_file(
         &self,
-        file: &Path,
-        file_name: &str,
+        file: &SourceFile,
         english_file_id: FileId,
         language_code: &LanguageCode,
-    ) {
-        let storage_id = self.upload_file_to_storage(file, file_name).await;
-        add_localization_file(english_file_id, storage_id, language_code).await;
+    ) -> CrowdinResult<()> {
+        history::add_changed_file(&self.mod_directory.mod_info.full_name(), file.repository_path.clone());
+        let storage_id = self.upload_file_to_storage(file).await?;
+        add_localization_file(english_file_id, storage_id, language_code).await
+    }
+
+    async fn upload_file_to_storage(&self, file: &SourceFile) -> CrowdinResult<StorageId> {
+        info!("[{}] upload file to storage: {}", self.mod_directory.mod_info, file.repository_path);
+        let (_, name) = split_path(&file.name);
+        upload_file_to_storage(self.get_upload_content(file), name).await
     }
 
-    async fn upload_file_to_storage(&self, file: &Path, file_name: &str) -> StorageId {
-        info!("[{}] upload file to storage: {}/{}", self.mod_directory.mod_info, util::file_name(file.parent().unwrap()), file_name);
-        let file_content = fs::read_to_string(file).unwrap();
-        let mut file_content = util::escape::escape_strings_in_ini_file(&file_content);
+    /// Only locale files are normalized, other files are uploaded as is
+    fn get_upload_content(&self, file: &SourceFile) -> String {
+        if file.file_type != FileType::Ini {
+            return file.content.clone();
+        }
+        let mut locale_file = LocaleFile::parse(&file.content);
+        // BOM would become part of first key on Crowdin
+        locale_file.has_bom = false;
+        util::escape::escape_strings_in_locale_file(&mut locale_file);
+        let file_content = locale_file.to_string();
         if file_content.is_empty() {
-            file_content = "; empty".to_owned();
+            return "; empty".to_owned();
         }
-        upload_file_to_storage(file_content, file_name).await
+        file_content
     }
 }
 
@@ -312,9 +833,9 @@ pub fn normalize_language_code(code: &str) -> String {
     }
 }
 
+/// Whether `code` is target language of current project
 pub fn is_correct_language_code(code: &str) -> bool {
-    let codes = PROJECT_LANGUAGE_CODES.get().unwrap();
-    codes.iter().any(|it| it == code)
+    project_language_codes().iter().any(|it| it == code)
 }
 
 pub fn get_crowdin_directory_name(mod_info: &GithubModInfo) -> String {
@@ -330,6 +851,32 @@ pub fn get_crowdin_directory_name(mod_info: &GithubModInfo) -> String {
     }
 }
 
+/// Whether directory could be created by [get_crowdin_directory_name] for some mod of repository.
+/// Used when config of repository is not available anymore (e.g. app is uninstalled).
+pub fn is_crowdin_directory_of_repository(directory_name: &str, full_name: &str) -> bool {
+    let Some((owner, repo)) = full_name.split_once('/') else { return false; };
+    let repo = util::case::to_title_case(repo);
+    let Some(name) = directory_name.strip_suffix(&format!(" ({})", owner)) else { return false; };
+    name == repo || name.strip_prefix(&repo).is_some_and(|it| it.starts_with(" - "))
+}
+
+/// Name of directory of repository `old_full_name` after repository is renamed or transferred to `new_full_name`
+pub fn get_renamed_repository_directory_name(directory_name: &str, old_full_name: &str, new_full_name: &str) -> Option<String> {
+    if !is_crowdin_directory_of_repository(directory_name, old_full_name) { return None; }
+    let (old_owner, old_repo) = old_full_name.split_once('/')?;
+    let (new_owner, new_repo) = new_full_name.split_once('/')?;
+    let name = directory_name.strip_suffix(&format!(" ({})", old_owner))?;
+    // Empty or ` - {crowdin_name}` for multimods
+    let mod_suffix = name.strip_prefix(&util::case::to_title_case(old_repo))?;
+    Some(format!("{}{} ({})", util::case::to_title_case(new_repo), mod_suffix, new_owner))
+}
+
+/// Name of directory of any repository of `old_owner` after user or organization is renamed to `new_owner`
+pub fn get_renamed_owner_directory_name(directory_name: &str, old_owner: &str, new_owner: &str) -> Option<String> {
+    let name = directory_name.strip_suffix(&format!(" ({})", old_owner))?;
+    Some(format!("{} ({})", name, new_owner))
+}
+
 pub fn replace_cfg_to_ini(name: &str) -> String {
     static DOT_CFG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(".cfg$").unwrap());
     name.replace(DOT_CFG_REGEX.deref(), ".ini")
@@ -339,3 +886,31 @@ pub fn replace_ini_to_cfg(name: &str) -> String {
     static DOT_INI_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(".ini$").unwrap());
     name.replace(DOT_INI_REGEX.deref(), ".cfg")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_crowdin_directory_of_repository() {
+        assert!(is_crowdin_directory_of_repository("Factorio Mod Example (dima74)", "dima74/factorio-mod-example"));
+        assert!(is_crowdin_directory_of_repository("Repo - Mod1 (owner)", "owner/repo"));
+        assert!(!is_crowdin_directory_of_repository("Repo (owner2)", "owner/repo"));
+        assert!(!is_crowdin_directory_of_repository("Repo Other (owner)", "owner/repo"));
+        assert!(!is_crowdin_directory_of_repository("Repo", "owner/repo"));
+    }
+
+    #[test]
+    fn test_get_renamed_repository_directory_name() {
+        let rename = |name| get_renamed_repository_directory_name(name, "owner/repo", "owner2/new-repo");
+        assert_eq!(rename("Repo (owner)").as_deref(), Some("New Repo (owner2)"));
+        assert_eq!(rename("Repo - Mod1 (owner)").as_deref(), Some("New Repo - Mod1 (owner2)"));
+        assert_eq!(rename("Other (owner)"), None);
+    }
+
+    #[test]
+    fn test_get_renamed_owner_directory_name() {
+        assert_eq!(get_renamed_owner_directory_name("Repo - Mod1 (owner)", "owner", "owner2").as_deref(), Some("Repo - Mod1 (owner2)"));
+        assert_eq!(get_renamed_owner_directory_name("Repo (other-owner)", "owner", "owner2"), None);
+    }
+}
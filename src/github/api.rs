@@ -0,0 +1,295 @@
+use std::ops::Deref;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use jsonwebtoken::EncodingKey;
+use log::info;
+use octocrab::{Error, Octocrab, Page};
+use octocrab::models::{AppId, InstallationId, Repository};
+use octocrab::models::pulls::PullRequest;
+use octocrab::models::repos::ContentItems;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::time::sleep;
+
+use crate::github::{CommitStatus, GITHUB_BRANCH_NAME, GITHUB_CONFIG_STATUS_CONTEXT, GITHUB_USER_NAME, GithubInstallation};
+use crate::myenv::{GITHUB_APP_ID, GITHUB_APP_PRIVATE_KEY, GITHUB_PERSONAL_ACCESS_TOKEN};
+use crate::util::EmptyBody;
+
+const MAX_PER_PAGE: u8 = 100;
+
+/// GitHub operations used by the service.
+/// Methods with `installation_id` are performed as GitHub app installation,
+/// methods for forks, stars and pull requests - as [GITHUB_USER_NAME] account.
+/// [OctocrabGithubApi] is used in production, [super::fake::FakeGithubApi] in offline tests.
+#[async_trait]
+pub trait GithubApi: Send + Sync {
+    async fn get_all_installations(&self) -> Vec<GithubInstallation>;
+
+    async fn get_installation_id_for_repo(&self, full_name: &str) -> Option<InstallationId>;
+
+    /// Returns full names of public repositories
+    async fn get_all_repositories_of_installation(&self, installation_id: InstallationId) -> Vec<String>;
+
+    /// Returns None if directory doesn't exist
+    async fn list_files_in_directory(&self, installation_id: InstallationId, full_name: &str, path: &str) -> Option<Vec<String>>;
+
+    async fn get_file_content(&self, installation_id: InstallationId, full_name: &str, path: &str) -> Option<String>;
+
+    /// Url which can be used for `git clone` and `git push`
+    async fn get_clone_url(&self, installation_id: InstallationId, full_name: &str) -> String;
+
+    async fn get_default_branch(&self, installation_id: InstallationId, full_name: &str) -> String;
+
+    async fn is_branch_protected(&self, installation_id: InstallationId, full_name: &str, branch: &str) -> bool;
+
+    /// Status with [GITHUB_CONFIG_STATUS_CONTEXT] context.
+    /// Errors are only logged, because app may be installed without permission for commit statuses.
+    async fn create_commit_status(&self, installation_id: InstallationId, full_name: &str, sha: &str, status: &CommitStatus);
+
+    /// Returns full names of forks
+    async fn list_forks(&self, full_name: &str) -> Vec<String>;
+
+    async fn create_fork(&self, full_name: &str);
+
+    /// Url of [GITHUB_USER_NAME]/`repo` which can be used for `git push`
+    fn get_fork_push_url(&self, repo: &str) -> String;
+
+    /// Creates pull request from [GITHUB_BRANCH_NAME] branch of fork.
+    /// Does nothing if pull request already exists or repository is archived.
+    async fn create_pull_request(&self, full_name: &str, base_branch: &str);
+
+    async fn star_repository(&self, full_name: &str);
+
+    async fn is_repository_starred(&self, full_name: &str) -> bool;
+}
+
+pub struct OctocrabGithubApi;
+
+fn get_credentials() -> (AppId, EncodingKey) {
+    let github_app_id: u64 = GITHUB_APP_ID.deref().parse().unwrap();
+    let github_app_key = GITHUB_APP_PRIVATE_KEY.deref().replace("\\n", "\n");
+    let github_app_key = EncodingKey::from_rsa_pem(github_app_key.as_bytes()).unwrap();
+    (AppId(github_app_id), github_app_key)
+}
+
+fn as_app() -> Octocrab {
+    let (app_id, key) = get_credentials();
+    Octocrab::builder().app(app_id, key).build().unwrap()
+}
+
+fn as_installation(installation_id: InstallationId) -> Octocrab {
+    as_app().installation(installation_id)
+}
+
+fn as_personal_account() -> Octocrab {
+    let personal_token = GITHUB_PERSONAL_ACCESS_TOKEN.to_owned();
+    Octocrab::builder()
+        .personal_token(personal_token)
+        .build()
+        .unwrap()
+}
+
+trait PageExt<T> {
+    async fn all_pages(self, api: &Octocrab) -> octocrab::Result<Vec<T>>;
+}
+
+impl<T: DeserializeOwned> PageExt<T> for Page<T> {
+    async fn all_pages(self, api: &Octocrab) -> octocrab::Result<Vec<T>> {
+        api.all_pages(self).await
+    }
+}
+
+async fn get_content(installation_api: &Octocrab, full_name: &str, path: &str) -> octocrab::Result<ContentItems> {
+    let (owner, repo) = full_name.split_once('/').unwrap();
+    let result = installation_api
+        .repos(owner, repo)
+        .get_content()
+        .path(path)
+        .send()
+        .await;
+    if let Err(Error::GitHub { source, .. }) = &result {
+        if path.is_empty() && source.errors.is_none() && source.message == "This repository is empty." {
+            return Ok(ContentItems { items: vec![] });
+        }
+    }
+    result
+}
+
+fn check_create_pull_request_response(result: octocrab::Result<PullRequest>, full_name: &str) {
+    let Err(err) = result else { return; };
+    if is_error_pull_request_already_exists(&err) {
+        // PR exists - no need to reopen, force push is enough
+        return;
+    }
+    if is_error_repository_archived(&err) {
+        // Ignore archived repositories, can't create PRs for them
+        return;
+    }
+    panic!("[{}] Can't create pull request: {}", full_name, err);
+}
+
+fn is_error_pull_request_already_exists(error: &Error) -> bool {
+    let Error::GitHub { source, .. } = &error else { return false; };
+    if source.message != "Validation Failed" { return false; };
+    let Some([error, ..]) = source.errors.as_deref() else { return false; };
+    let serde_json::Value::Object(error) = error else { return false; };
+    let Some(serde_json::Value::String(message)) = error.get("message") else { return false; };
+    message.starts_with("A pull request already exists for")
+}
+
+fn is_error_repository_archived(error: &Error) -> bool {
+    let error_str = format!("{}", error);
+    error_str.contains("Repository was archived so is read-only")
+}
+
+#[async_trait]
+impl GithubApi for OctocrabGithubApi {
+    async fn get_all_installations(&self) -> Vec<GithubInstallation> {
+        let api = as_app();
+        api
+            .apps().installations().per_page(MAX_PER_PAGE)
+            .send().await.unwrap()
+            .all_pages(&api).await.unwrap()
+            .into_iter()
+            .map(|installation| GithubInstallation {
+                id: installation.id,
+                login: installation.account.login,
+                repository_selection: installation.repository_selection,
+            })
+            .collect()
+    }
+
+    async fn get_installation_id_for_repo(&self, full_name: &str) -> Option<InstallationId> {
+        let (owner, repo) = full_name.split_once('/').unwrap();
+        as_app()
+            .apps()
+            .get_repository_installation(owner, repo).await
+            .map(|it| it.id)
+            .ok()
+    }
+
+    async fn get_all_repositories_of_installation(&self, installation_id: InstallationId) -> Vec<String> {
+        let installation_api = as_installation(installation_id);
+        let parameters = serde_json::json!({"per_page": MAX_PER_PAGE});
+        let repositories: Page<Repository> = installation_api
+            .get("/installation/repositories", Some(&parameters)).await.unwrap();
+        let repositories = repositories.all_pages(&installation_api).await.unwrap();
+        repositories
+            .into_iter()
+            .filter(|it| !it.private.unwrap())
+            .map(|it| it.full_name.unwrap())
+            .collect()
+    }
+
+    async fn list_files_in_directory(&self, installation_id: InstallationId, full_name: &str, path: &str) -> Option<Vec<String>> {
+        let installation_api = as_installation(installation_id);
+        let files = get_content(&installation_api, full_name, path).await.ok()?
+            .items
+            .into_iter()
+            .map(|file| file.name)
+            .collect();
+        Some(files)
+    }
+
+    async fn get_file_content(&self, installation_id: InstallationId, full_name: &str, path: &str) -> Option<String> {
+        let installation_api = as_installation(installation_id);
+        let mut content = get_content(&installation_api, full_name, path).await.ok()?;
+        content.items.first_mut()?.decoded_content()
+    }
+
+    async fn get_clone_url(&self, installation_id: InstallationId, full_name: &str) -> String {
+        use secrecy::ExposeSecret;
+        let api = as_app();
+        let (_, installation_token) = api.installation_and_token(installation_id).await.unwrap();
+        let installation_token = installation_token.expose_secret();
+        format!("https://x-access-token:{}@github.com/{}.git", installation_token, full_name)
+    }
+
+    async fn get_default_branch(&self, installation_id: InstallationId, full_name: &str) -> String {
+        #[derive(Deserialize)]
+        struct Response { default_branch: String }
+        let url = format!("/repos/{}", full_name);
+        let response: Response = as_installation(installation_id).get(&url, None::<&()>).await.unwrap();
+        response.default_branch
+    }
+
+    async fn is_branch_protected(&self, installation_id: InstallationId, full_name: &str, branch: &str) -> bool {
+        #[derive(Deserialize)]
+        struct Response { protected: bool }
+        let url = format!("/repos/{}/branches/{}", full_name, branch);
+        let result: Response = as_installation(installation_id).get(&url, None::<&()>).await.unwrap();
+        result.protected
+    }
+
+    async fn create_commit_status(&self, installation_id: InstallationId, full_name: &str, sha: &str, status: &CommitStatus) {
+        let url = format!("/repos/{}/statuses/{}", full_name, sha);
+        let body = serde_json::json!({
+            "state": if status.success { "success" } else { "failure" },
+            "description": status.description,
+            "target_url": status.target_url,
+            "context": GITHUB_CONFIG_STATUS_CONTEXT,
+        });
+        let result: octocrab::Result<EmptyBody> = as_installation(installation_id).post(&url, Some(&body)).await;
+        if let Err(error) = result {
+            info!("[{}] Can't create commit status: {}", full_name, error);
+        }
+    }
+
+    async fn list_forks(&self, full_name: &str) -> Vec<String> {
+        let api = as_personal_account();
+        let (owner, repo) = full_name.split_once('/').unwrap();
+        api
+            .repos(owner, repo)
+            .list_forks()
+            .send().await.unwrap()
+            .all_pages(&api).await.unwrap()
+            .into_iter()
+            .map(|fork| fork.full_name.unwrap())
+            .collect()
+    }
+
+    async fn create_fork(&self, full_name: &str) {
+        let (owner, repo) = full_name.split_once('/').unwrap();
+        info!("[{}] forking repository...", full_name);
+        as_personal_account()
+            .repos(owner, repo)
+            .create_fork()
+            .send().await.unwrap();
+        sleep(Duration::from_secs(120)).await;
+    }
+
+    fn get_fork_push_url(&self, repo: &str) -> String {
+        let personal_token = GITHUB_PERSONAL_ACCESS_TOKEN.deref();
+        format!("https://x-access-token:{}@github.com/{}/{}.git", personal_token, GITHUB_USER_NAME, repo)
+    }
+
+    async fn create_pull_request(&self, full_name: &str, base_branch: &str) {
+        // GitHub needs some time to process pushed branch
+        sleep(Duration::from_secs(30)).await;
+        let (owner, repo) = full_name.split_once('/').unwrap();
+        let title = "Update translations from Crowdin";
+        let body = "See https://github.com/dima74/factorio-mods-localization for details";
+        let head_branch = format!("{}:{}", GITHUB_USER_NAME, GITHUB_BRANCH_NAME);
+        let result = as_personal_account()
+            .pulls(owner, repo)
+            .create(title, head_branch, base_branch)
+            .body(body)
+            .maintainer_can_modify(true)
+            .send().await;
+        check_create_pull_request_response(result, full_name);
+    }
+
+    async fn star_repository(&self, full_name: &str) {
+        let _response: octocrab::Result<EmptyBody> = as_personal_account()
+            .put(format!("/user/starred/{}", full_name), None::<&()>)
+            .await;
+    }
+
+    async fn is_repository_starred(&self, full_name: &str) -> bool {
+        let response: octocrab::Result<EmptyBody> = as_personal_account()
+            .get(format!("/user/starred/{}", full_name), None::<&()>)
+            .await;
+        response.is_ok()
+    }
+}
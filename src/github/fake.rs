@@ -0,0 +1,325 @@
+//! Implementation of [GithubApi] for offline tests.
+//! Repositories are local bare git repositories, so cloning and pushing work as usual.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use octocrab::models::InstallationId;
+use octocrab::models::webhook_events::payload::PushWebhookEventPayload;
+use tempfile::TempDir;
+
+use crate::github::{CommitStatus, GITHUB_USER_NAME, GithubInstallation};
+use crate::github::api::GithubApi;
+use crate::util;
+
+const DEFAULT_BRANCH: &str = "main";
+
+pub struct FakeGithubApi {
+    _root: TempDir,
+    /// Absolute path of `_root`, needed for `file://` urls
+    root_path: PathBuf,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// owner => installation id
+    installations: BTreeMap<String, InstallationId>,
+    repositories: BTreeMap<String, Repository>,
+    /// full name => full name of fork
+    forks: BTreeMap<String, String>,
+    starred: BTreeSet<String>,
+    /// (full name, base branch)
+    pull_requests: Vec<(String, String)>,
+    /// (full name, sha, status)
+    commit_statuses: Vec<(String, String, CommitStatus)>,
+}
+
+#[derive(Default)]
+struct Repository {
+    private: bool,
+    protected_branches: HashSet<String>,
+}
+
+impl Default for FakeGithubApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeGithubApi {
+    pub fn new() -> Self {
+        // Fake is usually stored in static and never dropped,
+        // so it is created in system temporary directory instead of working directory
+        let root = tempfile::Builder::new().prefix("FML.").tempdir().unwrap();
+        let root_path = fs::canonicalize(root.path()).unwrap();
+        Self { _root: root, root_path, state: Mutex::default() }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn repository_path(&self, full_name: &str) -> PathBuf {
+        self.root_path.join(format!("{}.git", full_name))
+    }
+
+    /// Creates repository with single commit, app is installed for repository owner
+    pub fn add_repository(&self, full_name: &str, files: &[(&str, &str)]) -> InstallationId {
+        let path = self.repository_path(full_name);
+        fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "--bare", "--initial-branch", DEFAULT_BRANCH]).unwrap();
+
+        let (owner, _repo) = full_name.split_once('/').unwrap();
+        let installation_id = {
+            let mut state = self.state();
+            let next_id = InstallationId(state.installations.len() as u64 + 1);
+            let installation_id = *state.installations.entry(owner.to_owned()).or_insert(next_id);
+            state.repositories.insert(full_name.to_owned(), Repository::default());
+            installation_id
+        };
+
+        let files = files.iter()
+            .map(|&(path, content)| (path, Some(content)))
+            .collect::<Vec<_>>();
+        self.commit_files(full_name, &files);
+        installation_id
+    }
+
+    pub fn set_private(&self, full_name: &str) {
+        self.state().repositories.get_mut(full_name).unwrap().private = true;
+    }
+
+    /// App is uninstalled for repository, repository itself is kept
+    pub fn remove_repository(&self, full_name: &str) {
+        self.state().repositories.remove(full_name);
+    }
+
+    pub fn set_branch_protected(&self, full_name: &str, branch: &str) {
+        let mut state = self.state();
+        let repository = state.repositories.get_mut(full_name).unwrap();
+        repository.protected_branches.insert(branch.to_owned());
+    }
+
+    /// Emulates `git push` by repository owner.
+    /// `None` content means that file is deleted.
+    /// Returns payload of corresponding push webhook.
+    pub fn commit_files(&self, full_name: &str, files: &[(&str, Option<&str>)]) -> PushWebhookEventPayload {
+        let worktree = util::create_temporary_directory();
+        let worktree = worktree.path();
+        let url = file_url(&self.repository_path(full_name));
+        git(worktree, &["clone", &url, "."]).unwrap();
+        for &(path, content) in files {
+            let path = worktree.join(path);
+            match content {
+                Some(content) => {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(path, content).unwrap();
+                }
+                None => fs::remove_file(path).unwrap(),
+            }
+        }
+        git(worktree, &["add", "--all"]).unwrap();
+        git(worktree, &[
+            "-c", "user.name=Owner",
+            "-c", "user.email=owner@example.com",
+            "-c", "commit.gpgsign=false",
+            "commit", "--allow-empty", "-m", "Update",
+        ]).unwrap();
+        let branch = format!("HEAD:refs/heads/{}", DEFAULT_BRANCH);
+        git(worktree, &["push", "origin", &branch]).unwrap();
+        create_push_payload(worktree, full_name)
+    }
+
+    /// `full_name` may be name of fork, e.g. `factorio-mods-helper/repo`
+    pub fn file_content(&self, full_name: &str, branch: &str, path: &str) -> Option<String> {
+        let object = format!("{}:{}", branch, path);
+        git(&self.repository_path(full_name), &["show", &object])
+    }
+
+    pub fn pull_requests(&self) -> Vec<(String, String)> {
+        self.state().pull_requests.clone()
+    }
+
+    pub fn is_starred(&self, full_name: &str) -> bool {
+        self.state().starred.contains(full_name)
+    }
+
+    pub fn is_forked(&self, full_name: &str) -> bool {
+        self.state().forks.contains_key(full_name)
+    }
+
+    /// Returns (sha, status) in order of creation
+    pub fn commit_statuses(&self, full_name: &str) -> Vec<(String, CommitStatus)> {
+        self.state().commit_statuses.iter()
+            .filter(|(name, _, _)| name == full_name)
+            .map(|(_, sha, status)| (sha.clone(), status.clone()))
+            .collect()
+    }
+}
+
+/// Returns None if command failed
+fn git(path: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(args)
+        .output()
+        .expect("Failed to execute git command");
+    if !output.status.success() { return None; }
+    Some(String::from_utf8(output.stdout).unwrap())
+}
+
+fn file_url(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn create_push_payload(worktree: &Path, full_name: &str) -> PushWebhookEventPayload {
+    let after = git(worktree, &["rev-parse", "HEAD"]).unwrap().trim().to_owned();
+    let before = git(worktree, &["rev-parse", "HEAD~1"])
+        .map_or_else(|| "0".repeat(40), |it| it.trim().to_owned());
+    let tree_id = git(worktree, &["rev-parse", "HEAD^{tree}"]).unwrap().trim().to_owned();
+    let (mut added, mut modified, mut removed) = (vec![], vec![], vec![]);
+    let changes = git(worktree, &["show", "--name-status", "--no-renames", "--format=", "HEAD"]).unwrap();
+    for line in changes.lines() {
+        let (status, path) = line.split_once('\t').unwrap();
+        match status {
+            "A" => added.push(path),
+            "D" => removed.push(path),
+            _ => modified.push(path),
+        }
+    }
+    let user = serde_json::json!({ "name": "Owner", "email": "owner@example.com" });
+    let commit = serde_json::json!({
+        "id": after,
+        "tree_id": tree_id,
+        "distinct": true,
+        "message": "Update",
+        "timestamp": "2024-01-01T00:00:00Z",
+        "url": format!("https://github.com/{}/commit/{}", full_name, after),
+        "author": user,
+        "committer": user,
+        "added": added,
+        "modified": modified,
+        "removed": removed,
+    });
+    let payload = serde_json::json!({
+        "ref": format!("refs/heads/{}", DEFAULT_BRANCH),
+        "before": before,
+        "after": after,
+        "base_ref": null,
+        "compare": format!("https://github.com/{}/compare/{}...{}", full_name, before, after),
+        "created": false,
+        "deleted": false,
+        "forced": false,
+        "commits": [commit],
+        "head_commit": commit,
+        "pusher": user,
+    });
+    serde_json::from_value(payload).unwrap()
+}
+
+#[async_trait]
+impl GithubApi for FakeGithubApi {
+    async fn get_all_installations(&self) -> Vec<GithubInstallation> {
+        self.state().installations.iter()
+            .map(|(login, &id)| GithubInstallation {
+                id,
+                login: login.clone(),
+                repository_selection: Some("all".to_owned()),
+            })
+            .collect()
+    }
+
+    async fn get_installation_id_for_repo(&self, full_name: &str) -> Option<InstallationId> {
+        let state = self.state();
+        if !state.repositories.contains_key(full_name) { return None; }
+        let (owner, _repo) = full_name.split_once('/').unwrap();
+        state.installations.get(owner).copied()
+    }
+
+    async fn get_all_repositories_of_installation(&self, installation_id: InstallationId) -> Vec<String> {
+        let state = self.state();
+        state.repositories.iter()
+            .filter(|(full_name, repository)| {
+                let (owner, _repo) = full_name.split_once('/').unwrap();
+                !repository.private && state.installations.get(owner) == Some(&installation_id)
+            })
+            .map(|(full_name, _)| full_name.clone())
+            .collect()
+    }
+
+    async fn list_files_in_directory(&self, _installation_id: InstallationId, full_name: &str, path: &str) -> Option<Vec<String>> {
+        let repository_path = self.repository_path(full_name);
+        let tree = if path.is_empty() {
+            if git(&repository_path, &["rev-parse", "HEAD"]).is_none() {
+                // Repository is empty
+                return Some(vec![]);
+            }
+            "HEAD".to_owned()
+        } else {
+            format!("HEAD:{}", path)
+        };
+        let files = git(&repository_path, &["ls-tree", "--name-only", &tree])?;
+        Some(files.lines().map(ToOwned::to_owned).collect())
+    }
+
+    async fn get_file_content(&self, _installation_id: InstallationId, full_name: &str, path: &str) -> Option<String> {
+        self.file_content(full_name, "HEAD", path)
+    }
+
+    async fn get_clone_url(&self, _installation_id: InstallationId, full_name: &str) -> String {
+        file_url(&self.repository_path(full_name))
+    }
+
+    async fn get_default_branch(&self, _installation_id: InstallationId, full_name: &str) -> String {
+        let branch = git(&self.repository_path(full_name), &["symbolic-ref", "--short", "HEAD"]).unwrap();
+        branch.trim().to_owned()
+    }
+
+    async fn is_branch_protected(&self, _installation_id: InstallationId, full_name: &str, branch: &str) -> bool {
+        self.state().repositories[full_name].protected_branches.contains(branch)
+    }
+
+    async fn create_commit_status(&self, _installation_id: InstallationId, full_name: &str, sha: &str, status: &CommitStatus) {
+        let commit_status = (full_name.to_owned(), sha.to_owned(), status.clone());
+        self.state().commit_statuses.push(commit_status);
+    }
+
+    async fn list_forks(&self, full_name: &str) -> Vec<String> {
+        self.state().forks.get(full_name).cloned().into_iter().collect()
+    }
+
+    async fn create_fork(&self, full_name: &str) {
+        let (_owner, repo) = full_name.split_once('/').unwrap();
+        let fork_full_name = format!("{}/{}", GITHUB_USER_NAME, repo);
+        let fork_path = self.repository_path(&fork_full_name);
+        fs::create_dir_all(&fork_path).unwrap();
+        let url = file_url(&self.repository_path(full_name));
+        git(&fork_path, &["clone", "--bare", &url, "."]).unwrap();
+        self.state().forks.insert(full_name.to_owned(), fork_full_name);
+    }
+
+    fn get_fork_push_url(&self, repo: &str) -> String {
+        file_url(&self.repository_path(&format!("{}/{}", GITHUB_USER_NAME, repo)))
+    }
+
+    async fn create_pull_request(&self, full_name: &str, base_branch: &str) {
+        let pull_request = (full_name.to_owned(), base_branch.to_owned());
+        let mut state = self.state();
+        if !state.pull_requests.contains(&pull_request) {
+            state.pull_requests.push(pull_request);
+        }
+    }
+
+    async fn star_repository(&self, full_name: &str) {
+        self.state().starred.insert(full_name.to_owned());
+    }
+
+    async fn is_repository_starred(&self, full_name: &str) -> bool {
+        self.is_starred(full_name)
+    }
+}
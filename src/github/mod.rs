@@ -0,0 +1,312 @@
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use log::info;
+use octocrab::Octocrab;
+use octocrab::models::InstallationId;
+
+use crate::git_util;
+use crate::github::api::{GithubApi, OctocrabGithubApi};
+use crate::github_config::{parse_github_repo_info, ConfigError};
+use crate::github_repo_info::{GithubRepoInfo};
+use crate::mod_directory::RepositoryDirectory;
+use crate::server::status::get_status_page_url;
+use crate::sentry::sentry_report_error;
+use crate::util::create_temporary_directory;
+
+pub mod api;
+pub mod fake;
+
+pub const GITHUB_USER_NAME: &str = "factorio-mods-helper";
+pub const GITHUB_BRANCH_NAME: &str = "crowdin-fml";
+/// Config can be written in JSON, YAML or TOML, but only one of these files is allowed, see [crate::github_config]
+pub const GITHUB_CONFIG_FILE_NAMES: [&str; 4] = [
+    "factorio-mods-localization.json",
+    "factorio-mods-localization.yaml",
+    "factorio-mods-localization.yml",
+    "factorio-mods-localization.toml",
+];
+/// Context of commit status with result of config validation
+pub const GITHUB_CONFIG_STATUS_CONTEXT: &str = "factorio-mods-localization/config";
+/// GitHub rejects longer descriptions of commit status
+const COMMIT_STATUS_DESCRIPTION_MAX_LENGTH: usize = 140;
+
+static API: OnceLock<Arc<dyn GithubApi>> = OnceLock::new();
+
+/// For offline tests, see [fake::FakeGithubApi].
+/// Must be called before any other function of this module.
+pub fn init_with_api(api: Arc<dyn GithubApi>) {
+    if API.set(api).is_err() {
+        panic!("GitHub API is already initialized");
+    }
+}
+
+fn api() -> &'static dyn GithubApi {
+    API.get_or_init(|| Arc::new(OctocrabGithubApi)).as_ref()
+}
+
+pub struct GithubInstallation {
+    pub id: InstallationId,
+    /// User or organization which installed the app
+    pub login: String,
+    pub repository_selection: Option<String>,
+}
+
+pub async fn get_all_installations() -> Vec<GithubInstallation> {
+    api().get_all_installations().await
+}
+
+pub async fn get_installation_id_for_user(login: &str) -> Option<InstallationId> {
+    get_all_installations().await
+        .into_iter()
+        .find(|it| it.login == login)
+        .map(|it| it.id)
+}
+
+pub async fn get_installation_id_for_repo(full_name: &str) -> Option<InstallationId> {
+    api().get_installation_id_for_repo(full_name).await
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum GetRepoInfoError {
+    InvalidConfig(ConfigError),
+    LocaleDirectoryMissing,
+    LocaleEnDirectoryMissingOrEmpty,
+}
+
+pub async fn get_repo_info(
+    installation_id: InstallationId,
+    full_name: &str,
+) -> Result<GithubRepoInfo, GetRepoInfoError> {
+    let root_items = list_files_in_directory(installation_id, full_name, "").await
+        .unwrap_or_else(|| panic!("[{}] Can't list files in repository root", full_name));
+    let config_files = root_items.iter()
+        .filter(|it| GITHUB_CONFIG_FILE_NAMES.contains(&it.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if config_files.len() > 1 {
+        return Err(GetRepoInfoError::InvalidConfig(ConfigError::MultipleConfigFiles(config_files)));
+    }
+    if let Some(config_file) = config_files.first() {
+        let content = api().get_file_content(installation_id, full_name, config_file).await.unwrap();
+        parse_github_repo_info(full_name, config_file, &content)
+            .map_err(GetRepoInfoError::InvalidConfig)
+    } else {
+        if !root_items.iter().any(|it| it == "locale") {
+            return Err(GetRepoInfoError::LocaleDirectoryMissing);
+        }
+        let locale_en_items = list_files_in_directory(installation_id, full_name, "locale/en").await;
+        match locale_en_items {
+            Some(locale_en_items) if !locale_en_items.is_empty() => {
+                Ok(GithubRepoInfo::new_single_mod(full_name))
+            }
+            _ => {
+                Err(GetRepoInfoError::LocaleEnDirectoryMissingOrEmpty)
+            }
+        }
+    }
+}
+
+pub async fn list_files_in_directory(installation_id: InstallationId, full_name: &str, path: &str) -> Option<Vec<String>> {
+    api().list_files_in_directory(installation_id, full_name, path).await
+}
+
+pub async fn get_all_repositories() -> Vec<(GithubRepoInfo, InstallationId)> {
+    let mut result = Vec::new();
+    let installations = get_all_installations().await;
+    for installation in installations {
+        let repositories = get_all_repositories_of_installation(installation.id).await;
+        for repository in repositories {
+            let repo_info = get_repo_info(installation.id, &repository).await;
+            if let Ok(repo_info) = repo_info {
+                result.push((repo_info, installation.id));
+            }
+        }
+    }
+    result
+}
+
+pub async fn get_all_repositories_of_installation(installation_id: InstallationId) -> Vec<String> {
+    api().get_all_repositories_of_installation(installation_id).await
+}
+
+pub async fn clone_repository(
+    repo_info: &GithubRepoInfo,
+    installation_id: InstallationId,
+) -> RepositoryDirectory {
+    info!("[{}] clone repository", repo_info.full_name);
+    let directory = create_temporary_directory();
+    clone_repository_to(repo_info, installation_id, directory.path()).await;
+    RepositoryDirectory::new(&repo_info.full_name, directory)
+}
+
+async fn clone_repository_to(
+    repo_info: &GithubRepoInfo,
+    installation_id: InstallationId,
+    path: &Path,
+) {
+    let url = api().get_clone_url(installation_id, &repo_info.full_name).await;
+    git_util::clone(&url, path, repo_info.branch.as_deref());
+}
+
+pub async fn create_pull_request(full_name: &str, base_branch: &str) {
+    api().create_pull_request(full_name, base_branch).await;
+}
+
+pub fn get_fork_push_url(repo: &str) -> String {
+    api().get_fork_push_url(repo)
+}
+
+pub async fn get_default_branch(installation_id: InstallationId, full_name: &str) -> String {
+    api().get_default_branch(installation_id, full_name).await
+}
+
+pub async fn is_branch_protected(installation_id: InstallationId, full_name: &str, branch: &str) -> bool {
+    api().is_branch_protected(installation_id, full_name, branch).await
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommitStatus {
+    pub success: bool,
+    pub description: String,
+    /// Status page of repository, it contains full error message
+    pub target_url: String,
+}
+
+/// Shows result of config validation near commit `sha` on GitHub
+pub async fn set_config_status(installation_id: InstallationId, full_name: &str, sha: &str, error: Option<&ConfigError>) {
+    let description = match error {
+        None => "Config is valid".to_owned(),
+        Some(error) => format!("Invalid config: {}", error),
+    };
+    let description = if description.chars().count() > COMMIT_STATUS_DESCRIPTION_MAX_LENGTH {
+        let truncated = description.chars().take(COMMIT_STATUS_DESCRIPTION_MAX_LENGTH - 3).collect::<String>();
+        format!("{}...", truncated)
+    } else {
+        description
+    };
+    let status = CommitStatus {
+        success: error.is_none(),
+        description,
+        target_url: get_status_page_url(Some(full_name)),
+    };
+    api().create_commit_status(installation_id, full_name, sha, &status).await;
+}
+
+pub async fn fork_repository(full_name: &str) -> bool {
+    if let Some(is_fork_name_correct) = check_fork_exists(full_name).await {
+        return is_fork_name_correct;
+    }
+    fork_repository_without_check(full_name).await;
+    true
+}
+
+pub async fn fork_repository_without_check(full_name: &str) {
+    api().create_fork(full_name).await;
+}
+
+// None => no fork
+// Some(false) => fork with different name
+// Some(true) => fork exists and can be used
+async fn check_fork_exists(full_name: &str) -> Option<bool> {
+    let (owner, repo) = full_name.split_once('/').unwrap();
+    let forks = api().list_forks(full_name).await;
+    for fork_full_name in forks {
+        let (fork_owner, fork_repo) = fork_full_name.split_once('/').unwrap();
+        if fork_owner == GITHUB_USER_NAME {
+            return if fork_repo == repo {
+                Some(true)  // fork already exists
+            } else {
+                let message = format!("Fork name {} doesn't match repository {}/{}", fork_repo, owner, repo);
+                sentry_report_error(&message);
+                Some(false)
+            };
+        }
+    }
+    None
+}
+
+#[derive(Default)]
+pub struct GetNotForkedResult {
+    pub not_forked: Vec<String>,
+    pub forked_with_diferrent_name: Vec<String>,
+}
+
+pub async fn get_not_forked_repositories() -> GetNotForkedResult {
+    let repositories = get_all_repositories().await;
+    let mut result = GetNotForkedResult::default();
+    for (repo_info, _id) in repositories {
+        let full_name = repo_info.full_name;
+        match check_fork_exists(&full_name).await {
+            None => result.not_forked.push(full_name),
+            Some(false) => result.forked_with_diferrent_name.push(full_name),
+            Some(true) => continue,
+        }
+    }
+    result
+}
+
+pub async fn star_repository(full_name: &str) {
+    api().star_repository(full_name).await;
+}
+
+pub async fn is_repository_starred(full_name: &str) -> bool {
+    api().is_repository_starred(full_name).await
+}
+
+pub async fn get_not_starred_repositories() -> Vec<String> {
+    let repositories = get_all_repositories().await;
+    let mut not_starred = Vec::new();
+    for (repo_info, _id) in repositories {
+        let full_name = repo_info.full_name;
+        if !is_repository_starred(&full_name).await {
+            not_starred.push(full_name);
+        }
+    }
+    not_starred
+}
+
+/// Uses OAuth token of user, so not part of [GithubApi]
+pub async fn get_current_user(api_oauth: &Octocrab) -> String {
+    let response: octocrab::models::Author = api_oauth
+        .get("/user", None::<&()>)
+        .await.unwrap();
+    response.login
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::github_repo_info::GithubModInfo;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_has_locale_en() {
+        let installation_id = get_installation_id_for_user("dima74").await.unwrap();
+        assert_eq!(
+            get_repo_info(installation_id, "dima74/factorio-mod-example").await,
+            Ok(GithubRepoInfo {
+                full_name: "dima74/factorio-mod-example".to_owned(),
+                mods: vec![GithubModInfo::new_root("dima74/factorio-mod-example")],
+                weekly_update_from_crowdin: true,
+                branch: None,
+            }),
+        );
+        assert_eq!(
+            get_repo_info(installation_id, "dima74/factorio-multimod-example").await,
+            Ok(GithubRepoInfo {
+                full_name: "dima74/factorio-multimod-example".to_owned(),
+                mods: vec![
+                    GithubModInfo::new_for_test("dima74/factorio-multimod-example", "Mod1/locale", "Name1"),
+                    GithubModInfo::new_for_test("dima74/factorio-multimod-example", "Mod3/Data/locale", "Name3"),
+                ],
+                weekly_update_from_crowdin: true,
+                branch: None,
+            }),
+        );
+        assert_eq!(
+            get_repo_info(installation_id, "dima74/factorio-mods-localization").await,
+            Err(GetRepoInfoError::LocaleDirectoryMissing),
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! `factorio-mods-localization.json` - config file in root of the repository.
 //! It should be in the *default* branch, even if some other "branch" is specified in config.
+//! Same config can be written as `factorio-mods-localization.yaml` or `factorio-mods-localization.toml`
+//! (only new format), but repository can have only one of these files.
 //!
 //! # Format of `factorio-mods-localization.json`
 //! Old format (deprecated):
@@ -12,18 +14,27 @@
 //! {
 //!   "mods": ["mod1", "mod2"],
 //!   "weekly_update_from_crowdin": false,
-//!   "branch": "dev"
+//!   "branch": "dev",
+//!   "removed_english_files": "delete",
+//!   "languages": ["de", "fr"],
+//!   "exclude_languages": ["ru"],
+//!   "min_translation_progress": 50,
+//!   "export_only_approved": true,
+//!   "source_language": "ru",
+//!   "additional_files": [{"source": "changelog.txt", "translation": "changelogs/{language}.txt"}]
 //! }
 //! ```
 //!
-//! Alternative format for "mods":
+//! Alternative format for "mods", `languages`, `exclude_languages` and `additionalFiles` of mod override ones of repository:
 //! ```json
 //! {
-//!   "mods": [{"localePath": "custom/path", "crowdinName": "Foo"}]
+//!   "mods": [{"localePath": "custom/path", "crowdinName": "Foo", "languages": ["fr"]}]
 //!   ...
 //! }
 //! ```
 //!
+//! JSON Schema of new format is generated by [config_schema] and committed to `factorio-mods-localization.schema.json`.
+//!
 //! # Examples
 //!
 //! ## Single mod in github repository (no `factorio-mods-localization.json`)
@@ -38,29 +49,175 @@
 //! ├── Mod2
 //! │   ├── locale/en
 
-use crate::github_repo_info::{GithubModInfo, GithubRepoInfo};
-use serde::Deserialize;
-use std::collections::HashSet;
+use crate::github_repo_info::{AdditionalFile, GithubModInfo, GithubRepoInfo, LanguageFilter, MinTranslationProgress, RemovedFilesPolicy, CROWDIN_NAME_PATTERN, DEFAULT_SOURCE_LANGUAGE, LOCALE_PATH_PATTERN};
+use crate::server::config_schema::get_config_schema_url;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::LazyLock;
 
-#[derive(Deserialize)]
+/// Reason why config is rejected.
+/// Shown to repository owner in commit status of config file and on status page.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    /// Invalid JSON/YAML/TOML or value of unexpected type.
+    /// `position` is 1-based (line, column), it is unknown for some YAML and TOML errors
+    Syntax { message: String, position: Option<(usize, usize)> },
+    /// Several config files with different extensions, see [crate::github::GITHUB_CONFIG_FILE_NAMES]
+    MultipleConfigFiles(Vec<String>),
+    /// Name of config file with unknown extension
+    UnsupportedConfigFile(String),
+    /// Path to field, e.g. `mods[1].crowdin_name`
+    UnknownField(String),
+    NoMods,
+    DuplicateCrowdinName(String),
+    InvalidCrowdinName(String),
+    InvalidLocalePath(String),
+    InvalidMinTranslationProgress,
+    InvalidSourceLanguage(String),
+    /// `source` of additional file
+    InvalidAdditionalFile(String),
+    DuplicateAdditionalFileName(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { message, position: None } => write!(f, "{}", message),
+            Self::Syntax { message, position: Some((line, column)) } => write!(f, "{} at line {} column {}", message, line, column),
+            Self::MultipleConfigFiles(files) => write!(f, "only one config file is allowed, found {}", files.join(", ")),
+            Self::UnsupportedConfigFile(file_name) => write!(f, "unsupported config file `{}`, use .json, .yaml or .toml", file_name),
+            Self::UnknownField(field) => write!(f, "unknown field `{}`", field),
+            Self::NoMods => write!(f, "`mods` is empty"),
+            Self::DuplicateCrowdinName(name) => write!(f, "duplicate crowdinName `{}`", name),
+            Self::InvalidCrowdinName(name) => write!(f, "invalid crowdinName `{}`, allowed characters are `a-zA-Z0-9._-`", name),
+            Self::InvalidLocalePath(path) => write!(f, "invalid localePath `{}`, it must be relative path without `.`, spaces and special characters", path),
+            Self::InvalidMinTranslationProgress => write!(f, "`min_translation_progress` must be between 0 and 100"),
+            Self::InvalidSourceLanguage(code) => write!(f, "invalid source_language `{}`", code),
+            Self::InvalidAdditionalFile(source) => write!(f, "invalid additional file `{}`", source),
+            Self::DuplicateAdditionalFileName(name) => write!(f, "several additional files have name `{}`", name),
+        }
+    }
+}
+
+impl ConfigError {
+    /// Position is removed from end of `message` if it is already there
+    fn syntax(message: String, position: Option<(usize, usize)>) -> Self {
+        let message = match position {
+            Some((line, column)) => {
+                let suffix = format!(" at line {} column {}", line, column);
+                message.strip_suffix(&suffix).map(ToOwned::to_owned).unwrap_or(message)
+            }
+            None => message,
+        };
+        Self::Syntax { message, position }
+    }
+
+    /// TOML errors contain byte range instead of line and column
+    fn from_toml_error(error: &toml::de::Error, content: &str) -> Self {
+        let position = error.span()
+            .and_then(|span| content.get(..span.start))
+            .map(|before| {
+                let line = before.matches('\n').count() + 1;
+                let column = before.rsplit('\n').next().unwrap().chars().count() + 1;
+                (line, column)
+            });
+        Self::syntax(error.message().to_owned(), position)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::syntax(error.to_string(), Some((error.line(), error.column())))
+    }
+}
+
+impl From<serde_yaml::Error> for ConfigError {
+    fn from(error: serde_yaml::Error) -> Self {
+        let position = error.location().map(|it| (it.line(), it.column()));
+        Self::syntax(error.to_string(), position)
+    }
+}
+
+/// Config file of factorio-mods-localization, placed in root of the repository
+#[derive(Deserialize, JsonSchema)]
 struct Config {
+    /// Url of JSON Schema, used by editors for autocompletion
+    #[serde(rename = "$schema")]
+    _schema: Option<String>,
+    /// Mods in subfolders of repository, by default repository contains single mod in root
     mods: Option<ConfigMods>,
+    /// Whether translations are pushed to repository every week (default true)
     weekly_update_from_crowdin: Option<bool>,
+    /// Branch from which english files are tracked and to which translations are pushed (default is default branch)
     branch: Option<String>,
+    /// What to do with file on Crowdin when english file is removed (default `archive`)
+    removed_english_files: Option<RemovedFilesPolicy>,
+    #[serde(flatten)]
+    languages: LanguageFilter,
+    /// Minimum translation progress in percent for language to be added to repository.
+    /// Applies only to languages which are not yet in repository, other languages are always updated
+    min_translation_progress: Option<MinTranslationProgress>,
+    /// Translations not approved by proofreader on Crowdin are not added to repository
+    export_only_approved: Option<bool>,
+    /// Language in which mod is written (default `en`)
+    #[schemars(regex = "LANGUAGE_CODE_PATTERN")]
+    source_language: Option<String>,
+    additional_files: Option<Vec<AdditionalFile>>,
+    /// `deny_unknown_fields` doesn't work together with `flatten`
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Deserialize)]
+// `untagged` is used only for schema, see `Deserialize` implementation below
+#[derive(JsonSchema)]
 #[serde(untagged)]
 enum ConfigMods {
+    /// Names of subfolders with mods, they are used as names on Crowdin
     Short(Vec<String>),
     Full(Vec<ConfigMod>),
 }
 
-#[derive(Deserialize)]
+const CONFIG_MODS_FORMAT: &str = "`mods` must be list of subfolders (`[\"Mod1\", \"Mod2\"]`) \
+    or list of objects with `localePath` and `crowdinName` (`[{\"localePath\": \"Mod1/locale\", \"crowdinName\": \"Mod1\"}]`)";
+
+/// Untagged enum reports only "data did not match any variant",
+/// so variant is chosen by type of items and error describes expected format
+impl<'de> Deserialize<'de> for ConfigMods {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let Some(items) = value.as_array() else { return Err(D::Error::custom(CONFIG_MODS_FORMAT)); };
+        let result = if items.iter().all(serde_json::Value::is_string) {
+            serde_json::from_value(value).map(Self::Short)
+        } else if items.iter().all(serde_json::Value::is_object) {
+            serde_json::from_value(value).map(Self::Full)
+        } else {
+            return Err(D::Error::custom(CONFIG_MODS_FORMAT));
+        };
+        result.map_err(D::Error::custom)
+    }
+}
+
+/// Mod in subfolder of repository
+#[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct ConfigMod {
+    /// Path to locale folder relative to repository root
+    #[schemars(regex = "LOCALE_PATH_PATTERN")]
     locale_path: String,
+    /// Name of mod directory on Crowdin
+    #[schemars(regex = "CROWDIN_NAME_PATTERN")]
     crowdin_name: String,
+    #[serde(flatten)]
+    languages: LanguageFilter,
+    additional_files: Option<Vec<AdditionalFile>>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -69,34 +226,93 @@ struct ConfigOld(Vec<String>);
 impl From<ConfigOld> for Config {
     fn from(config: ConfigOld) -> Self {
         Config {
+            _schema: None,
             mods: Some(ConfigMods::Short(config.0)),
             weekly_update_from_crowdin: None,
             branch: None,
+            removed_english_files: None,
+            languages: LanguageFilter::default(),
+            min_translation_progress: None,
+            export_only_approved: None,
+            source_language: None,
+            additional_files: None,
+            unknown_fields: BTreeMap::new(),
         }
     }
 }
 
-pub fn parse_github_repo_info_json(full_name: &str, json: &str) -> Option<GithubRepoInfo> {
-    let config: Config = parse_config(json)?;
-    let mods = convert_mods(full_name, config.mods)?;
-    if !check_no_duplicates(&mods) { return None; }
+/// Increased when option is added or changed, checked by `tests/config_schema.rs`
+pub const CONFIG_SCHEMA_VERSION: u32 = 5;
+
+/// JSON Schema of new format of config, generated from [Config]
+pub fn config_schema() -> serde_json::Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(Config)).unwrap();
+    // `deny_unknown_fields` is ignored by schemars for structs with flattened fields
+    schema["additionalProperties"] = false.into();
+    schema["definitions"]["ConfigMod"]["additionalProperties"] = false.into();
+    let schema_object = schema.as_object_mut().unwrap();
+    schema_object.insert("$id".to_owned(), get_config_schema_url().into());
+    schema_object.insert("$comment".to_owned(), format!("Version {}", CONFIG_SCHEMA_VERSION).into());
+    schema_object.insert("title".to_owned(), "factorio-mods-localization config".into());
+    schema
+}
+
+pub fn parse_github_repo_info_json(full_name: &str, json: &str) -> Result<GithubRepoInfo, ConfigError> {
+    let config = parse_config_json(json)?;
+    convert_config(full_name, config)
+}
+
+/// Format is detected by extension of `file_name`, see [crate::github::GITHUB_CONFIG_FILE_NAMES]
+pub fn parse_github_repo_info(full_name: &str, file_name: &str, content: &str) -> Result<GithubRepoInfo, ConfigError> {
+    let config = match file_name.rsplit_once('.') {
+        Some((_, "json")) => parse_config_json(content)?,
+        Some((_, "yaml" | "yml")) => serde_yaml::from_str(content)?,
+        Some((_, "toml")) => toml::from_str(content)
+            .map_err(|error| ConfigError::from_toml_error(&error, content))?,
+        _ => return Err(ConfigError::UnsupportedConfigFile(file_name.to_owned())),
+    };
+    convert_config(full_name, config)
+}
+
+fn convert_config(full_name: &str, config: Config) -> Result<GithubRepoInfo, ConfigError> {
+    if let Some(field) = config.unknown_fields.keys().next() {
+        return Err(ConfigError::UnknownField(field.clone()));
+    }
+    let mut mods = convert_mods(full_name, config.mods)?;
+    check_no_duplicates(&mods)?;
+    let min_translation_progress = config.min_translation_progress.unwrap_or_default();
+    if !min_translation_progress.is_valid() { return Err(ConfigError::InvalidMinTranslationProgress); }
+    let source_language = config.source_language.unwrap_or_else(|| DEFAULT_SOURCE_LANGUAGE.to_owned());
+    if !check_language_code(&source_language) { return Err(ConfigError::InvalidSourceLanguage(source_language)); }
+    for mod_ in &mut mods {
+        mod_.removed_english_files = config.removed_english_files.unwrap_or_default();
+        mod_.languages = std::mem::take(&mut mod_.languages).or(&config.languages);
+        mod_.min_translation_progress = min_translation_progress.clone();
+        mod_.export_only_approved = config.export_only_approved.unwrap_or(false);
+        mod_.source_language = source_language.clone();
+        if mod_.additional_files.is_empty() {
+            mod_.additional_files = config.additional_files.clone().unwrap_or_default();
+        }
+        AdditionalFile::check_all(&mod_.additional_files)?;
+    }
     GithubRepoInfo::new_from_config(full_name, mods, config.weekly_update_from_crowdin, config.branch)
 }
 
-fn parse_config(json: &str) -> Option<Config> {
+/// Old format is supported only for JSON
+fn parse_config_json(json: &str) -> Result<Config, ConfigError> {
     if let Ok(config) = serde_json::from_str::<ConfigOld>(json) {
-        return Some(config.into());
+        return Ok(config.into());
     }
-    serde_json::from_str(json).ok()
+    Ok(serde_json::from_str(json)?)
 }
 
 fn convert_mods(
     full_name: &str,
     mods: Option<ConfigMods>,
-) -> Option<Vec<GithubModInfo>> {
+) -> Result<Vec<GithubModInfo>, ConfigError> {
     let Some(mods) = mods else {
         // { "weekly_update_from_crowdin": false }
-        return Some(vec![GithubModInfo::new_root(full_name)]);
+        return Ok(vec![GithubModInfo::new_root(full_name)]);
     };
 
     match mods {
@@ -109,154 +325,194 @@ fn convert_mods(
         ConfigMods::Full(mods) => {
             mods
                 .into_iter()
-                .map(|mod_| {
-                    GithubModInfo::new_custom(full_name, Some(mod_.locale_path), mod_.crowdin_name)
+                .enumerate()
+                .map(|(index, mod_)| {
+                    if let Some(field) = mod_.unknown_fields.keys().next() {
+                        return Err(ConfigError::UnknownField(format!("mods[{}].{}", index, field)));
+                    }
+                    let mut mod_info = GithubModInfo::new_custom(full_name, Some(mod_.locale_path), mod_.crowdin_name)?;
+                    mod_info.languages = mod_.languages;
+                    mod_info.additional_files = mod_.additional_files.unwrap_or_default();
+                    Ok(mod_info)
                 })
                 .collect()
         }
     }
 }
 
-fn check_no_duplicates(mods: &[GithubModInfo]) -> bool {
-    let mods_set = mods
-        .iter()
-        .map(|mod_| mod_.crowdin_name.as_ref())
-        .collect::<HashSet<_>>();
-    mods.len() == mods_set.len()
+/// Language codes are like `en`, `pt-BR` or `zh-CN`
+const LANGUAGE_CODE_PATTERN: &str = r"^[a-zA-Z]{2,3}(-[a-zA-Z]{2,4})?$";
+
+fn check_language_code(code: &str) -> bool {
+    static LANGUAGE_CODE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(LANGUAGE_CODE_PATTERN).unwrap()
+    });
+    LANGUAGE_CODE_REGEX.is_match(code)
+}
+
+fn check_no_duplicates(mods: &[GithubModInfo]) -> Result<(), ConfigError> {
+    let mut names = HashSet::new();
+    for name in mods.iter().filter_map(|mod_| mod_.crowdin_name.as_ref()) {
+        if !names.insert(name) {
+            return Err(ConfigError::DuplicateCrowdinName(name.clone()));
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crowdin::file_type::FileType;
+
+    fn mod_paths(repo_info: &GithubRepoInfo) -> Vec<(&str, Option<&str>)> {
+        repo_info.mods.iter()
+            .map(|it| (it.locale_path.as_str(), it.crowdin_name.as_deref()))
+            .collect()
+    }
 
     #[test]
     fn test_parse_mods_old_version() {
-        assert_eq!(
-            parse_github_repo_info_json("owner/repo", r#"["mod1", "mod2"]"#),
-            Some(GithubRepoInfo {
-                full_name: "owner/repo".to_owned(),
-                mods: vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "mod1/locale".to_owned(),
-                        crowdin_name: Some("mod1".to_owned()),
-                    },
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "mod2/locale".to_owned(),
-                        crowdin_name: Some("mod2".to_owned()),
-                    },
-                ],
-                weekly_update_from_crowdin: true,
-                branch: None,
-            })
-        );
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"["mod1", "mod2"]"#).unwrap();
+        assert_eq!(mod_paths(&repo_info), vec![("mod1/locale", Some("mod1")), ("mod2/locale", Some("mod2"))]);
     }
-    
+
     #[test]
     fn test_parse_mods_short_version() {
-        assert_eq!(
-            parse_github_repo_info_json("owner/repo", r#"{"mods": ["mod1", "mod2"]}"#),
-            Some(GithubRepoInfo {
-                full_name: "owner/repo".to_owned(),
-                mods:
-                vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "mod1/locale".to_owned(),
-                        crowdin_name: Some("mod1".to_owned()),
-                    },
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "mod2/locale".to_owned(),
-                        crowdin_name: Some("mod2".to_owned()),
-                    },
-                ],
-                weekly_update_from_crowdin: true,
-                branch: None,
-            })
-        );
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"mods": ["mod1", "mod2"]}"#).unwrap();
+        assert_eq!(mod_paths(&repo_info), vec![("mod1/locale", Some("mod1")), ("mod2/locale", Some("mod2"))]);
     }
 
     #[test]
     fn test_parse_mods_long_version() {
-        assert_eq!(
-            parse_github_repo_info_json("owner/repo", r#"{"mods": [{"localePath": "custom/path", "crowdinName": "Foo"}]}"#),
-            Some(GithubRepoInfo {
-                full_name: "owner/repo".to_owned(),
-                mods:
-                vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "custom/path".to_owned(),
-                        crowdin_name: Some("Foo".to_owned()),
-                    },
-                ],
-                weekly_update_from_crowdin: true,
-                branch: None,
-            })
-        )
+        let json = r#"{"mods": [{"localePath": "custom/path", "crowdinName": "Foo"}]}"#;
+        let repo_info = parse_github_repo_info_json("owner/repo", json).unwrap();
+        assert_eq!(mod_paths(&repo_info), vec![("custom/path", Some("Foo"))]);
     }
 
     #[test]
     fn test_parse_weekly_update_from_crowdin() {
-        assert_eq!(
-            parse_github_repo_info_json("owner/repo", r#"{"weekly_update_from_crowdin": false}"#),
-            Some(GithubRepoInfo {
-                full_name: "owner/repo".to_owned(),
-                mods: vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "locale".to_owned(),
-                        crowdin_name: None,
-                    },
-                ],
-                weekly_update_from_crowdin: false,
-                branch: None,
-            })
-        );
-        assert_eq!(
-            parse_github_repo_info_json("owner/repo", r#"{"weekly_update_from_crowdin": true}"#),
-            Some(GithubRepoInfo {
-                full_name: "owner/repo".to_owned(),
-                mods: vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "locale".to_owned(),
-                        crowdin_name: None,
-                    },
-                ],
-                weekly_update_from_crowdin: true,
-                branch: None,
-            })
-        );
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"weekly_update_from_crowdin": false}"#).unwrap();
+        assert!(!repo_info.weekly_update_from_crowdin);
+        assert_eq!(mod_paths(&repo_info), vec![("locale", None)]);
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"weekly_update_from_crowdin": true}"#).unwrap();
+        assert!(repo_info.weekly_update_from_crowdin);
+        let repo_info = parse_github_repo_info_json("owner/repo", "{}").unwrap();
+        assert!(repo_info.weekly_update_from_crowdin);
     }
 
     #[test]
     fn test_parse_branch() {
-        assert_eq!(
-            parse_github_repo_info_json("owner/repo", r#"{"branch": "dev"}"#),
-            Some(GithubRepoInfo {
-                full_name: "owner/repo".to_owned(),
-                mods: vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "locale".to_owned(),
-                        crowdin_name: None,
-                    },
-                ],
-                weekly_update_from_crowdin: true,
-                branch: Some("dev".to_owned()),
-            })
-        );
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"branch": "dev"}"#).unwrap();
+        assert_eq!(repo_info.branch, Some("dev".to_owned()));
+        let repo_info = parse_github_repo_info_json("owner/repo", "{}").unwrap();
+        assert_eq!(repo_info.branch, None);
+    }
+
+    #[test]
+    fn test_parse_removed_english_files() {
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"mods": ["mod1", "mod2"], "removed_english_files": "archive"}"#).unwrap();
+        assert!(repo_info.mods.iter().all(|it| it.removed_english_files == RemovedFilesPolicy::Archive));
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"removed_english_files": "delete"}"#).unwrap();
+        assert_eq!(repo_info.mods[0].removed_english_files, RemovedFilesPolicy::Delete);
+        let repo_info = parse_github_repo_info_json("owner/repo", "{}").unwrap();
+        assert_eq!(repo_info.mods[0].removed_english_files, RemovedFilesPolicy::Archive);
+        assert!(matches!(parse_github_repo_info_json("owner/repo", r#"{"removed_english_files": "keep"}"#), Err(ConfigError::Syntax { .. })));
+    }
+
+    #[test]
+    fn test_parse_languages() {
+        let json = r#"
+        {
+            "mods": [
+                {"localePath": "mod1/locale", "crowdinName": "mod1"},
+                {"localePath": "mod2/locale", "crowdinName": "mod2", "languages": ["fr", "pt-br"]}
+            ],
+            "exclude_languages": ["ru"]
+        }
+        "#;
+        let repo_info = parse_github_repo_info_json("owner/repo", json).unwrap();
+        let [mod1, mod2] = &repo_info.mods[..] else { panic!() };
+        assert!(mod1.languages.is_enabled("de"));
+        assert!(!mod1.languages.is_enabled("ru"));
+        assert!(mod2.languages.is_enabled("fr"));
+        assert!(mod2.languages.is_enabled("pt-BR"));
+        assert!(!mod2.languages.is_enabled("de"));
+        assert!(!mod2.languages.is_enabled("ru"));
+
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"languages": ["fr"]}"#).unwrap();
+        assert_eq!(repo_info.mods[0].languages.languages, Some(vec!["fr".to_owned()]));
+        assert!(matches!(parse_github_repo_info_json("owner/repo", r#"{"languages": "fr"}"#), Err(ConfigError::Syntax { .. })));
+    }
+
+    #[test]
+    fn test_parse_export_only_approved() {
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"mods": ["mod1", "mod2"], "export_only_approved": true}"#).unwrap();
+        assert!(repo_info.mods.iter().all(|it| it.export_only_approved));
+        let repo_info = parse_github_repo_info_json("owner/repo", "{}").unwrap();
+        assert!(!repo_info.mods[0].export_only_approved);
+    }
+
+    #[test]
+    fn test_parse_source_language() {
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"mods": ["mod1", "mod2"], "source_language": "ru"}"#).unwrap();
+        assert!(repo_info.mods.iter().all(|it| it.source_language == "ru"));
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"source_language": "zh-CN"}"#).unwrap();
+        assert_eq!(repo_info.mods[0].source_language, "zh-CN");
+        let repo_info = parse_github_repo_info_json("owner/repo", "{}").unwrap();
+        assert_eq!(repo_info.mods[0].source_language, "en");
+        assert_eq!(parse_github_repo_info_json("owner/repo", r#"{"source_language": "../en"}"#), Err(ConfigError::InvalidSourceLanguage("../en".to_owned())));
+        assert_eq!(parse_github_repo_info_json("owner/repo", r#"{"source_language": ""}"#), Err(ConfigError::InvalidSourceLanguage("".to_owned())));
+    }
+
+    #[test]
+    fn test_parse_additional_files() {
+        let json = r#"{"additional_files": [{"source": "changelog.txt", "translation": "changelogs/{language}.txt"}]}"#;
+        let repo_info = parse_github_repo_info_json("owner/repo", json).unwrap();
+        let file = &repo_info.mods[0].additional_files[0];
+        assert_eq!(file.file_type(), FileType::PlainText);
+        assert_eq!(file.crowdin_name(), "changelog.txt");
+        assert_eq!(file.translation_path("de"), "changelogs/de.txt");
+
+        let json = r#"{
+            "mods": [{"localePath": "mod1/locale", "crowdinName": "mod1", "additionalFiles": [{"source": "mod1/README", "translation": "mod1/README.{language}", "type": "markdown"}]}],
+            "additional_files": [{"source": "changelog.txt", "translation": "changelogs/{language}.txt"}]
+        }"#;
+        let repo_info = parse_github_repo_info_json("owner/repo", json).unwrap();
+        assert_eq!(repo_info.mods[0].additional_files.len(), 1);
+        assert_eq!(repo_info.mods[0].additional_files[0].file_type(), FileType::Markdown);
+
+        let invalid = [
+            r#"[{"source": "changelog.txt", "translation": "changelog.txt"}]"#,
+            r#"[{"source": "changelog.txt", "translation": "../{language}.txt"}]"#,
+            r#"[{"source": "changelog.txt", "translation": ".git/hooks/{language}"}]"#,
+            r#"[{"source": "/etc/passwd.txt", "translation": "{language}.txt"}]"#,
+            r#"[{"source": "LICENSE", "translation": "{language}/LICENSE"}]"#,
+            r#"[{"source": "extra.cfg", "translation": "{language}/extra.cfg"}]"#,
+            r#"[{"source": "a/changelog.txt", "translation": "a/{language}.txt"}, {"source": "b/changelog.txt", "translation": "b/{language}.txt"}]"#,
+        ];
+        for files in invalid {
+            let json = format!(r#"{{"additional_files": {}}}"#, files);
+            assert!(parse_github_repo_info_json("owner/repo", &json).is_err(), "{}", files);
+        }
+    }
+
+    #[test]
+    fn test_parse_min_translation_progress() {
+        let repo_info = parse_github_repo_info_json("owner/repo", r#"{"min_translation_progress": 50}"#).unwrap();
+        assert_eq!(repo_info.mods[0].min_translation_progress.get("de"), 50);
+
+        let json = r#"{"mods": ["mod1", "mod2"], "min_translation_progress": {"de": 80, "pt-br": 30}}"#;
+        let repo_info = parse_github_repo_info_json("owner/repo", json).unwrap();
+        let progress = &repo_info.mods[1].min_translation_progress;
+        assert_eq!(progress.get("de"), 80);
+        assert_eq!(progress.get("pt-BR"), 30);
+        assert_eq!(progress.get("fr"), 0);
+
+        let repo_info = parse_github_repo_info_json("owner/repo", "{}").unwrap();
+        assert!(repo_info.mods[0].min_translation_progress.is_empty());
+        assert_eq!(parse_github_repo_info_json("owner/repo", r#"{"min_translation_progress": 101}"#), Err(ConfigError::InvalidMinTranslationProgress));
+        assert!(matches!(parse_github_repo_info_json("owner/repo", r#"{"min_translation_progress": "50"}"#), Err(ConfigError::Syntax { .. })));
     }
 
     #[test]
@@ -270,25 +526,88 @@ mod tests {
         "#;
         assert_eq!(
             parse_github_repo_info_json("owner/repo", json),
-            Some(GithubRepoInfo {
+            Ok(GithubRepoInfo {
                 full_name: "owner/repo".to_owned(),
                 mods: vec![
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "mod1/locale".to_owned(),
-                        crowdin_name: Some("mod1".to_owned()),
-                    },
-                    GithubModInfo {
-                        owner: "owner".to_owned(),
-                        repo: "repo".to_owned(),
-                        locale_path: "mod2/locale".to_owned(),
-                        crowdin_name: Some("mod2".to_owned()),
-                    },
+                    GithubModInfo::new_for_test("owner/repo", "mod1/locale", "mod1"),
+                    GithubModInfo::new_for_test("owner/repo", "mod2/locale", "mod2"),
                 ],
                 weekly_update_from_crowdin: false,
                 branch: Some("dev".to_owned()),
             })
         );
     }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |json: &str| parse_github_repo_info_json("owner/repo", json).unwrap_err();
+        assert_eq!(
+            parse("{\n  \"mods\": [\"mod1\",]\n}"),
+            ConfigError::Syntax { message: "trailing comma".to_owned(), position: Some((2, 19)) },
+        );
+        assert_eq!(parse(r#"{"weekly_update": false}"#), ConfigError::UnknownField("weekly_update".to_owned()));
+        assert_eq!(
+            parse(r#"{"mods": [{"localePath": "a/locale", "crowdinName": "a", "crowdin_name": "b"}]}"#),
+            ConfigError::UnknownField("mods[0].crowdin_name".to_owned()),
+        );
+        assert_eq!(parse(r#"{"mods": []}"#), ConfigError::NoMods);
+        for json in [r#"{"mods": "mod1"}"#, r#"{"mods": ["mod1", {"localePath": "a/locale", "crowdinName": "a"}]}"#] {
+            let message = parse(json).to_string();
+            assert!(message.starts_with("`mods` must be list of subfolders"), "{}", message);
+        }
+        assert_eq!(
+            parse(r#"{"mods": [{"path": "a/locale", "name": "a"}]}"#).to_string(),
+            "missing field `localePath` at line 1 column 45",
+        );
+        assert_eq!(
+            parse(r#"{"mods": [{"localePath": "a/locale", "crowdinName": "a"}, {"localePath": "b/locale", "crowdinName": "a"}]}"#),
+            ConfigError::DuplicateCrowdinName("a".to_owned()),
+        );
+        assert_eq!(parse(r#"{"mods": ["my mod"]}"#), ConfigError::InvalidCrowdinName("my mod".to_owned()));
+        assert_eq!(
+            parse(r#"{"mods": [{"localePath": "../locale", "crowdinName": "a"}]}"#),
+            ConfigError::InvalidLocalePath("../locale".to_owned()),
+        );
+        assert_eq!(
+            parse(r#"{"additional_files": [{"source": "../changelog.txt", "translation": "{language}.txt"}]}"#),
+            ConfigError::InvalidAdditionalFile("../changelog.txt".to_owned()),
+        );
+        assert_eq!(
+            parse(r#"{"mods": ["mod1", "mod1"]}"#).to_string(),
+            "duplicate crowdinName `mod1`",
+        );
+    }
+
+    #[test]
+    fn test_parse_yaml_and_toml() {
+        let json = r#"{"mods": [{"localePath": "mod1/locale", "crowdinName": "mod1", "languages": ["de"]}], "min_translation_progress": 50}"#;
+        let yaml = "mods:\n  - localePath: mod1/locale\n    crowdinName: mod1\n    languages: [de]\nmin_translation_progress: 50\n";
+        let toml = "min_translation_progress = 50\n\n[[mods]]\nlocalePath = \"mod1/locale\"\ncrowdinName = \"mod1\"\nlanguages = [\"de\"]\n";
+        let expected = parse_github_repo_info_json("owner/repo", json);
+        assert!(expected.is_ok());
+        assert_eq!(parse_github_repo_info("owner/repo", "factorio-mods-localization.json", json), expected);
+        assert_eq!(parse_github_repo_info("owner/repo", "factorio-mods-localization.yaml", yaml), expected);
+        assert_eq!(parse_github_repo_info("owner/repo", "factorio-mods-localization.yml", yaml), expected);
+        assert_eq!(parse_github_repo_info("owner/repo", "factorio-mods-localization.toml", toml), expected);
+
+        let parse = |file_name: &str, content: &str| parse_github_repo_info("owner/repo", file_name, content).unwrap_err();
+        assert_eq!(
+            parse("factorio-mods-localization.yaml", "weekly_update: false\n"),
+            ConfigError::UnknownField("weekly_update".to_owned()),
+        );
+        assert_eq!(
+            parse("factorio-mods-localization.toml", "branch = \"dev\"\nmods = [\"mod1\", \"mod1\"]\n"),
+            ConfigError::DuplicateCrowdinName("mod1".to_owned()),
+        );
+        let error = parse("factorio-mods-localization.toml", "branch = \"dev\"\nexport_only_approved = 1\n");
+        assert!(matches!(error, ConfigError::Syntax { position: Some((2, 24)), .. }), "{:?}", error);
+        let error = parse("factorio-mods-localization.yaml", "branch: dev\nmods: [mod1\n");
+        assert!(matches!(error, ConfigError::Syntax { position: Some((3, 1)), .. }), "{:?}", error);
+        // old format is supported only for JSON
+        assert!(parse_github_repo_info("owner/repo", "factorio-mods-localization.yaml", "- mod1\n").is_err());
+        assert_eq!(
+            parse("factorio-mods-localization.ini", "mods = mod1\n"),
+            ConfigError::UnsupportedConfigFile("factorio-mods-localization.ini".to_owned()),
+        );
+    }
 }
@@ -1,10 +1,14 @@
+use log::info;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
 use std::sync::LazyLock;
 
-use crate::crowdin::get_crowdin_directory_name;
+use crate::crowdin::{get_crowdin_directory_name, normalize_language_code};
+use crate::crowdin::file_type::FileType;
+use crate::github_config::ConfigError;
 
 /// One [`GithubRepoInfo`] can contain multiple [`GithubModInfo`].
 /// [`GithubRepoInfo`] corresponds 1-1 to github repository.
@@ -38,9 +42,9 @@ impl GithubRepoInfo {
         mods: Vec<GithubModInfo>,
         weekly_update_from_crowdin: Option<bool>,
         branch: Option<String>,
-    ) -> Option<Self> {
-        if mods.is_empty() { return None; }
-        Some(Self::new(full_name, mods, weekly_update_from_crowdin, branch))
+    ) -> Result<Self, ConfigError> {
+        if mods.is_empty() { return Err(ConfigError::NoMods); }
+        Ok(Self::new(full_name, mods, weekly_update_from_crowdin, branch))
     }
 
     pub fn new_single_mod(full_name: &str) -> Self {
@@ -58,9 +62,16 @@ impl GithubRepoInfo {
 
     pub fn filter_mods_present_on_crowdin(
         mut self,
-        directories_crowdin: &HashSet<String>,
+        is_present_on_crowdin: impl Fn(&GithubModInfo) -> bool,
     ) -> Option<Self> {
-        self.mods.retain(|it| directories_crowdin.contains(&get_crowdin_directory_name(it)));
+        self.mods.retain(|it| {
+            let present = is_present_on_crowdin(it);
+            if !present {
+                let crowdin_name = get_crowdin_directory_name(it);
+                info!("[update-github-from-crowdin] [{}] skipping mod because there is no crowdin directory `{}`", it, crowdin_name);
+            }
+            present
+        });
         if self.mods.is_empty() { return None; }
         Some(self)
     }
@@ -82,6 +93,167 @@ pub struct GithubModInfo {
     pub repo: String,
     pub locale_path: String,
     pub crowdin_name: Option<String>,
+    pub removed_english_files: RemovedFilesPolicy,
+    pub languages: LanguageFilter,
+    pub min_translation_progress: MinTranslationProgress,
+    /// Translations not approved by proofreader on Crowdin are not added to repository
+    pub export_only_approved: bool,
+    /// Language in which mod is written, name of directory inside `locale_path`.
+    /// Crowdin project is chosen by this language, see [crate::crowdin::with_source_language]
+    pub source_language: String,
+    pub additional_files: Vec<AdditionalFile>,
+}
+
+pub const DEFAULT_SOURCE_LANGUAGE: &str = "en";
+
+/// Patterns are also used in JSON Schema of config, see [crate::github_config::config_schema].
+/// Segments of locale path can't be empty or contain `.`, spaces and characters not allowed in file names.
+pub const LOCALE_PATH_PATTERN: &str = r#"^[^/. <>:"\\|?*]+(/[^/. <>:"\\|?*]+)*$"#;
+pub const CROWDIN_NAME_PATTERN: &str = r"^[a-zA-Z0-9._-]+$";
+
+/// What to do with file on Crowdin when corresponding english file is removed from repository
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RemovedFilesPolicy {
+    /// Delete file from Crowdin together with its translations
+    Delete,
+    /// Move file to `_archived_files` directory on Crowdin, so translations are not lost
+    #[default]
+    Archive,
+}
+
+/// Languages in which mod is translated on Crowdin and exported to repository.
+/// Codes are compared after [normalize_language_code], so both `pt-br` and `pt-BR` are accepted.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LanguageFilter {
+    /// If specified, only these languages are enabled
+    pub languages: Option<Vec<String>>,
+    /// Languages which are not translated
+    pub exclude_languages: Option<Vec<String>>,
+}
+
+impl LanguageFilter {
+    pub fn is_enabled(&self, language: &str) -> bool {
+        let language = normalize_language_code(language);
+        let contains = |codes: &Vec<String>| codes.iter().any(|it| normalize_language_code(it) == language);
+        self.languages.as_ref().is_none_or(contains)
+            && !self.exclude_languages.as_ref().is_some_and(contains)
+    }
+
+    /// Options specified for mod take precedence over options of repository
+    pub fn or(self, repository: &LanguageFilter) -> Self {
+        Self {
+            languages: self.languages.or_else(|| repository.languages.clone()),
+            exclude_languages: self.exclude_languages.or_else(|| repository.exclude_languages.clone()),
+        }
+    }
+}
+
+/// Minimum translation progress on Crowdin (in percents) for new language to be added to repository:
+/// - `50` - same threshold for all languages
+/// - `{"de": 80, "fr": 50}` - threshold for each language, languages which are not listed have no threshold
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum MinTranslationProgress {
+    All(#[schemars(range(max = 100))] u8),
+    PerLanguage(BTreeMap<String, u8>),
+}
+
+impl Default for MinTranslationProgress {
+    fn default() -> Self {
+        Self::All(0)
+    }
+}
+
+impl MinTranslationProgress {
+    pub fn get(&self, language: &str) -> u8 {
+        match self {
+            Self::All(progress) => *progress,
+            Self::PerLanguage(progress) => {
+                let language = normalize_language_code(language);
+                progress.iter()
+                    .find(|(code, _)| normalize_language_code(code) == language)
+                    .map_or(0, |(_, &progress)| progress)
+            }
+        }
+    }
+
+    /// No threshold for all languages, so progress doesn't need to be requested from Crowdin
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::All(progress) => *progress == 0,
+            Self::PerLanguage(progress) => progress.values().all(|&it| it == 0),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::All(progress) => *progress <= 100,
+            Self::PerLanguage(progress) => progress.values().all(|&it| it <= 100),
+        }
+    }
+}
+
+/// File outside of locale folder which is translated on Crowdin, e.g. `changelog.txt` or mod portal description.
+/// Paths are relative to repository root, `translation` contains `{language}` placeholder:
+/// `{"source": "description.md", "translation": "descriptions/{language}.md"}`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AdditionalFile {
+    /// Path of english file relative to repository root
+    pub source: String,
+    /// Path of translations relative to repository root, `{language}` is replaced with language code
+    pub translation: String,
+    /// Detected by extension of `source` if not specified
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<FileType>,
+}
+
+impl AdditionalFile {
+    const LANGUAGE_PLACEHOLDER: &'static str = "{language}";
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type.or_else(|| FileType::from_path(&self.source)).unwrap()
+    }
+
+    /// Additional files are placed in root of mod directory on Crowdin
+    pub fn crowdin_name(&self) -> &str {
+        self.source.rsplit('/').next().unwrap()
+    }
+
+    pub fn translation_path(&self, language: &str) -> String {
+        self.translation.replace(Self::LANGUAGE_PLACEHOLDER, language)
+    }
+
+    /// Files with same names on Crowdin or with `.cfg` extension (which would clash with locale files) are not allowed
+    pub fn check_all(files: &[AdditionalFile]) -> Result<(), ConfigError> {
+        let mut names = HashSet::new();
+        for file in files {
+            if !file.is_valid() {
+                return Err(ConfigError::InvalidAdditionalFile(file.source.clone()));
+            }
+            if !names.insert(file.crowdin_name()) {
+                return Err(ConfigError::DuplicateAdditionalFileName(file.crowdin_name().to_owned()));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_valid(&self) -> bool {
+        Self::check_path(&self.source)
+            && Self::check_path(&self.translation)
+            && self.translation.contains(Self::LANGUAGE_PLACEHOLDER)
+            && self.file_type.or_else(|| FileType::from_path(&self.source)).is_some()
+            && FileType::from_path(self.crowdin_name()) != Some(FileType::Ini)
+    }
+
+    /// Relative path which doesn't point outside of repository or inside `.git`
+    fn check_path(path: &str) -> bool {
+        !path.contains('\\')
+            && path.split('/').all(|segment| {
+                !segment.is_empty() && segment != "." && segment != ".." && !segment.eq_ignore_ascii_case(".git")
+            })
+    }
 }
 
 // Used only for logging
@@ -107,14 +279,18 @@ impl GithubModInfo {
         full_name: &str,
         locale_path: Option<String>,
         crowdin_name: String,
-    ) -> Option<Self> {
+    ) -> Result<Self, ConfigError> {
+        if !Self::check_crowdin_name(&crowdin_name) {
+            return Err(ConfigError::InvalidCrowdinName(crowdin_name));
+        }
+
         let locale_path = locale_path
             .unwrap_or_else(|| format!("{crowdin_name}/locale"));
+        if !Self::check_locale_path(&locale_path) {
+            return Err(ConfigError::InvalidLocalePath(locale_path));
+        }
 
-        if !Self::check_locale_path(&locale_path) { return None; }
-        if !Self::check_crowdin_name(&crowdin_name) { return None; }
-
-        Some(Self::new(full_name, locale_path, Some(crowdin_name)))
+        Ok(Self::new(full_name, locale_path, Some(crowdin_name)))
     }
 
     fn new(
@@ -128,18 +304,44 @@ impl GithubModInfo {
             repo: repo.to_owned(),
             locale_path,
             crowdin_name,
+            removed_english_files: RemovedFilesPolicy::default(),
+            languages: LanguageFilter::default(),
+            min_translation_progress: MinTranslationProgress::default(),
+            export_only_approved: false,
+            source_language: DEFAULT_SOURCE_LANGUAGE.to_owned(),
+            additional_files: vec![],
         }
     }
 
+    /// Mod with default options, so tests don't need to list all fields
+    #[cfg(test)]
+    pub fn new_for_test(full_name: &str, locale_path: &str, crowdin_name: &str) -> Self {
+        Self::new(full_name, locale_path.to_owned(), Some(crowdin_name.to_owned()))
+    }
+
+    /// `info.json` is in root folder of mod, next to locale folder
+    pub fn info_json_path(&self) -> String {
+        match self.locale_path.rsplit_once('/') {
+            Some((mod_path, _)) => format!("{}/info.json", mod_path),
+            None => "info.json".to_owned(),
+        }
+    }
+
+    /// Full name of github repository in format "owner/repo"
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+
     fn check_locale_path(locale_path: &str) -> bool {
-        !locale_path.is_empty()
-            && !locale_path.contains(['.', ' ', '<', '>', ':', '"', '\\', '|', '?', '*'])
-            && !locale_path.split('/').any(|s| s.is_empty())
+        static LOCALE_PATH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(LOCALE_PATH_PATTERN).unwrap()
+        });
+        LOCALE_PATH_REGEX.is_match(locale_path)
     }
 
     fn check_crowdin_name(crowdin_name: &str) -> bool {
         static CROWDIN_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-            Regex::new(r"^[a-zA-Z0-9._-]+$").unwrap()
+            Regex::new(CROWDIN_NAME_PATTERN).unwrap()
         });
         CROWDIN_NAME_REGEX.is_match(crowdin_name)
     }
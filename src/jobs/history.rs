@@ -0,0 +1,223 @@
+//! Per-repository history of jobs, shown on status page.
+//! Stored as `{jobs directory}/history/{owner}/{repo}.json`, only last [MAX_ENTRIES] entries are kept.
+//!
+//! Code executed inside job reports progress using functions from this module
+//! (changed files, errors, link to commit), they do nothing when called outside of job.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::git_util::redact_credentials;
+use crate::jobs::{now, JobId};
+
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Running,
+    Success,
+    /// Job failed, but will be retried
+    Retrying,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub job_id: JobId,
+    pub kind: String,
+    /// Unix timestamp in seconds
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub outcome: Outcome,
+    pub attempts: u32,
+    pub errors: Vec<String>,
+    /// Problems which don't fail job, e.g. warnings for english files
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Files uploaded to Crowdin or changed in GitHub repository
+    pub changed_files: Vec<String>,
+    /// Commit or pull request created on GitHub
+    pub url: Option<String>,
+}
+
+/// Job which is executed by current tokio task
+#[derive(Clone)]
+pub struct CurrentJob {
+    pub id: JobId,
+    pub kind: &'static str,
+    pub attempts: u32,
+    /// Repositories for which history entry was created
+    pub repositories: Arc<Mutex<BTreeSet<String>>>,
+}
+
+tokio::task_local! {
+    pub static CURRENT_JOB: CurrentJob;
+}
+
+struct HistoryStore {
+    directory: PathBuf,
+    /// Protects read-modify-write of history files
+    lock: Mutex<()>,
+}
+
+static STORE: OnceLock<HistoryStore> = OnceLock::new();
+
+pub fn init(directory: PathBuf) {
+    fs::create_dir_all(&directory).unwrap();
+    let store = HistoryStore { directory, lock: Mutex::new(()) };
+    if STORE.set(store).is_err() {
+        panic!("Job history is already initialized");
+    }
+}
+
+/// `owner/repo` where both parts are valid GitHub names, so it is safe to use as path
+pub fn is_valid_full_name(full_name: &str) -> bool {
+    let Some((owner, repo)) = full_name.split_once('/') else { return false; };
+    let is_valid_name = |name: &str| {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    };
+    is_valid_name(owner) && is_valid_name(repo)
+}
+
+/// Newest entries first
+pub fn get(full_name: &str) -> Vec<HistoryEntry> {
+    let Some(store) = STORE.get() else { return vec![]; };
+    if !is_valid_full_name(full_name) { return vec![]; }
+    let _lock = store.lock.lock().unwrap();
+    let mut entries = store.load(full_name);
+    entries.reverse();
+    entries
+}
+
+/// Creates entry for current job if it doesn't exist yet
+pub fn start(full_name: &str) {
+    update_current_entry(full_name, |_entry| {});
+}
+
+pub fn add_changed_file(full_name: &str, file: String) {
+    update_current_entry(full_name, |entry| entry.changed_files.push(file));
+}
+
+pub fn add_error(full_name: &str, error: String) {
+    update_current_entry(full_name, |entry| entry.errors.push(error));
+}
+
+pub fn add_warning(full_name: &str, warning: String) {
+    update_current_entry(full_name, |entry| entry.warnings.push(warning));
+}
+
+pub fn set_url(full_name: &str, url: String) {
+    update_current_entry(full_name, |entry| entry.url = Some(url));
+}
+
+/// Called by job queue after each attempt, `error` is panic message.
+/// History is public, so credentials are removed from it (full message is only in server log)
+pub(crate) fn finish(job: &CurrentJob, outcome: Outcome, error: Option<&str>) {
+    let Some(store) = STORE.get() else { return; };
+    let error = error.map(redact_credentials);
+    let repositories = job.repositories.lock().unwrap().clone();
+    for full_name in repositories {
+        store.update(&full_name, job, |entry| {
+            entry.finished_at = Some(now());
+            if let Some(error) = &error {
+                entry.errors.push(error.clone());
+            }
+            // Errors reported by job itself (e.g. for single mod) don't cause retry
+            entry.outcome = if outcome == Outcome::Success && !entry.errors.is_empty() {
+                Outcome::Failed
+            } else {
+                outcome
+            };
+        });
+    }
+}
+
+fn update_current_entry(full_name: &str, f: impl FnOnce(&mut HistoryEntry)) {
+    let Some(store) = STORE.get() else { return; };
+    let Ok(job) = CURRENT_JOB.try_with(Clone::clone) else { return; };
+    if !is_valid_full_name(full_name) { return; }
+    job.repositories.lock().unwrap().insert(full_name.to_owned());
+    store.update(full_name, &job, f);
+}
+
+impl HistoryStore {
+    fn path(&self, full_name: &str) -> PathBuf {
+        self.directory.join(format!("{}.json", full_name))
+    }
+
+    /// Oldest entries first
+    fn load(&self, full_name: &str) -> Vec<HistoryEntry> {
+        let Ok(json) = fs::read_to_string(self.path(full_name)) else { return vec![]; };
+        serde_json::from_str(&json).unwrap_or_default()
+    }
+
+    fn save(&self, full_name: &str, entries: &[HistoryEntry]) {
+        let path = self.path(full_name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, serde_json::to_string_pretty(entries).unwrap()).unwrap();
+        fs::rename(temporary_path, path).unwrap();
+    }
+
+    /// Entry is created if needed, and is reset when job is retried
+    fn update(&self, full_name: &str, job: &CurrentJob, f: impl FnOnce(&mut HistoryEntry)) {
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.load(full_name);
+        let index = match entries.iter().position(|entry| entry.job_id == job.id) {
+            Some(index) => index,
+            None => {
+                entries.push(HistoryEntry::new(job));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index];
+        if entry.attempts != job.attempts {
+            *entry = HistoryEntry::new(job);
+        }
+        f(entry);
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+        self.save(full_name, &entries);
+    }
+}
+
+impl HistoryEntry {
+    fn new(job: &CurrentJob) -> Self {
+        Self {
+            job_id: job.id,
+            kind: job.kind.to_owned(),
+            started_at: now(),
+            finished_at: None,
+            outcome: Outcome::Running,
+            attempts: job.attempts,
+            errors: vec![],
+            warnings: vec![],
+            changed_files: vec![],
+            url: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_full_name() {
+        assert!(is_valid_full_name("owner/repo"));
+        assert!(is_valid_full_name("Some-Owner/repo_name.js"));
+        assert!(!is_valid_full_name("owner"));
+        assert!(!is_valid_full_name("owner/"));
+        assert!(!is_valid_full_name("owner/repo/subpath"));
+        assert!(!is_valid_full_name("../repo"));
+        assert!(!is_valid_full_name("owner/.."));
+    }
+}
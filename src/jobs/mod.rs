@@ -0,0 +1,376 @@
+//! Durable queue for long-running work (imports, push webhooks, updates from Crowdin).
+//! Jobs are persisted before they are started, so they survive deploys and crashes
+//! and are resumed by [init]. Jobs touching same repository are executed one at a time.
+//!
+//! Only panics are retried (e.g. GitHub API failure, failed git push). Crowdin errors are already
+//! retried by HTTP client, after that they are recorded in history and job is finished as usual,
+//! so problem of one mod doesn't repeat whole job for other mods.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use octocrab::models::InstallationId;
+use octocrab::models::webhook_events::payload::PushWebhookEventPayload;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedMutexGuard, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use tokio::task::JoinError;
+
+use crate::github_repo_info::GithubRepoInfo;
+use crate::jobs::history::{CURRENT_JOB, CurrentJob, Outcome};
+use crate::jobs::store::JobStore;
+use crate::myenv::{is_production, JOBS_DIRECTORY};
+use crate::sentry::sentry_report_error;
+use crate::server::trigger_update;
+use crate::{uninstall, webhooks};
+
+pub mod history;
+pub mod store;
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+pub type JobId = u64;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum JobKind {
+    /// GitHub app installed for public repositories
+    Install { installation_id: InstallationId, repositories: Vec<String> },
+    Push { installation_id: InstallationId, full_name: String, payload: Box<PushWebhookEventPayload> },
+    /// Readd repository to Crowdin (both english files and translations)
+    ImportRepository { full_name: String, subpath: Option<String> },
+    /// Overwrite english files on Crowdin based on GitHub
+    ImportEnglish { full_name: String, subpath: Option<String> },
+    /// Push translations from Crowdin to single repository
+    UpdateRepository { full_name: String, subpath: Option<String> },
+    /// Push translations from Crowdin to all repositories
+    UpdateAllRepositories,
+    /// GitHub app uninstalled for repositories, see [uninstall]
+    Uninstall { repositories: Vec<String> },
+    /// Delete archived Crowdin directories of uninstalled repository after grace period
+    DeleteRepository { full_name: String },
+    /// Repository renamed or transferred to another owner
+    RenameRepository { old_full_name: String, new_full_name: String },
+    /// User or organization renamed
+    RenameOwner { old_owner: String, new_owner: String },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub id: JobId,
+    #[serde(flatten)]
+    pub kind: JobKind,
+    /// Number of failed attempts
+    pub attempts: u32,
+    /// Unix timestamp in seconds
+    pub next_attempt_at: u64,
+}
+
+impl JobKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::Install { .. } => "install",
+            JobKind::Push { .. } => "push",
+            JobKind::ImportRepository { .. } => "import-repository",
+            JobKind::ImportEnglish { .. } => "import-english",
+            JobKind::UpdateRepository { .. } => "update-repository",
+            JobKind::UpdateAllRepositories => "update-all-repositories",
+            JobKind::Uninstall { .. } => "uninstall",
+            JobKind::DeleteRepository { .. } => "delete-repository",
+            JobKind::RenameRepository { .. } => "rename-repository",
+            JobKind::RenameOwner { .. } => "rename-owner",
+        }
+    }
+
+    /// Full names of repositories which job modifies, `None` means all repositories
+    pub fn repositories(&self) -> Option<Vec<&str>> {
+        match self {
+            JobKind::Install { repositories, .. }
+            | JobKind::Uninstall { repositories } => Some(repositories.iter().map(String::as_str).collect()),
+            JobKind::Push { full_name, .. }
+            | JobKind::ImportRepository { full_name, .. }
+            | JobKind::ImportEnglish { full_name, .. }
+            | JobKind::UpdateRepository { full_name, .. }
+            | JobKind::DeleteRepository { full_name } => Some(vec![full_name]),
+            JobKind::RenameRepository { old_full_name, new_full_name } => Some(vec![old_full_name, new_full_name]),
+            // Affects unknown number of repositories
+            JobKind::UpdateAllRepositories | JobKind::RenameOwner { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.repositories() {
+            Some(repositories) => write!(f, "{} {}", self.name(), repositories.join(", ")),
+            None => write!(f, "{} *", self.name()),
+        }
+    }
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.id, self.kind)
+    }
+}
+
+static STORE: OnceLock<JobStore> = OnceLock::new();
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+fn store() -> &'static JobStore {
+    STORE.get().expect("Job queue is not initialized")
+}
+
+/// Directory on persistent volume, also contains history of jobs and [crate::crowdin::directory_ids].
+/// Required in production (see `[mounts]` in `fly.toml`), otherwise all of it is lost on each deploy
+pub fn get_directory() -> PathBuf {
+    match JOBS_DIRECTORY.as_deref() {
+        Some(directory) => PathBuf::from(directory),
+        None if is_production() => panic!("JOBS_DIRECTORY must be set to directory on persistent volume"),
+        None => PathBuf::from("jobs"),
+    }
+}
+
+/// Resumes jobs which were not finished before restart
+pub fn init() {
+    init_with_directory(&get_directory());
+}
+
+pub fn init_with_directory(directory: &Path) {
+    if STORE.set(JobStore::new(directory)).is_err() {
+        panic!("Job queue is already initialized");
+    }
+    history::init(directory.join("history"));
+    let jobs = store().load_all();
+    let max_id = jobs.iter().map(|job| job.id).max().unwrap_or(0);
+    NEXT_JOB_ID.store(max_id + 1, Ordering::SeqCst);
+    if !jobs.is_empty() {
+        info!("[jobs] resuming {} unfinished jobs", jobs.len());
+    }
+    for job in jobs {
+        tokio::spawn(run_job(job));
+    }
+}
+
+/// Job is saved to disk before this function returns
+pub fn enqueue(kind: JobKind) -> JobId {
+    enqueue_delayed(kind, Duration::ZERO)
+}
+
+/// Job is started not earlier than after `delay`, even if there are restarts in between
+pub fn enqueue_delayed(kind: JobKind, delay: Duration) -> JobId {
+    let job = Job {
+        id: NEXT_JOB_ID.fetch_add(1, Ordering::SeqCst),
+        kind,
+        attempts: 0,
+        next_attempt_at: now() + delay.as_secs(),
+    };
+    store().save(&job);
+    info!("[jobs] [{}] enqueued", job);
+    let id = job.id;
+    tokio::spawn(run_job(job));
+    id
+}
+
+/// Jobs which are not finished yet, including waiting for retry
+pub fn pending_jobs() -> Vec<Job> {
+    store().load_all()
+}
+
+pub fn number_pending_jobs() -> usize {
+    pending_jobs().len()
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    let multiplier = 2u32.saturating_pow(attempts.saturating_sub(1));
+    INITIAL_RETRY_DELAY.saturating_mul(multiplier).min(MAX_RETRY_DELAY)
+}
+
+async fn run_job(mut job: Job) {
+    loop {
+        let delay = job.next_attempt_at.saturating_sub(now());
+        tokio::time::sleep(Duration::from_secs(delay)).await;
+
+        let current_job = CurrentJob {
+            id: job.id,
+            kind: job.kind.name(),
+            attempts: job.attempts,
+            repositories: Default::default(),
+        };
+        let result = {
+            let _locks = lock_repositories(&job.kind).await;
+            info!("[jobs] [{}] started", job);
+            let kind = job.kind.clone();
+            let task = CURRENT_JOB.scope(current_job.clone(), async move {
+                for repository in kind.repositories().unwrap_or_default() {
+                    history::start(repository);
+                }
+                execute(kind).await;
+            });
+            // Separate task so panic inside job is caught
+            tokio::spawn(task).await
+        };
+        match result {
+            Ok(()) => {
+                info!("[jobs] [{}] finished", job);
+                history::finish(&current_job, Outcome::Success, None);
+                store().remove(job.id);
+                return;
+            }
+            Err(error) => {
+                let error = get_panic_message(error);
+                job.attempts += 1;
+                if job.attempts >= MAX_ATTEMPTS {
+                    sentry_report_error(&format!("[jobs] [{}] failed {} times, giving up: {}", job, job.attempts, error));
+                    history::finish(&current_job, Outcome::Failed, Some(&error));
+                    store().remove(job.id);
+                    return;
+                }
+                history::finish(&current_job, Outcome::Retrying, Some(&error));
+                let delay = retry_delay(job.attempts);
+                warn!("[jobs] [{}] attempt {}/{} failed, retrying in {:?}: {}", job, job.attempts, MAX_ATTEMPTS, delay, error);
+                job.next_attempt_at = now() + delay.as_secs();
+                store().save(&job);
+            }
+        }
+    }
+}
+
+fn get_panic_message(error: JoinError) -> String {
+    if !error.is_panic() {
+        return error.to_string();
+    }
+    let payload = error.into_panic();
+    if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else {
+        "panic".to_owned()
+    }
+}
+
+async fn execute(kind: JobKind) {
+    match kind {
+        JobKind::Install { installation_id, repositories } => {
+            webhooks::on_repositories_added(repositories, installation_id).await;
+        }
+        JobKind::Push { installation_id, full_name, payload } => {
+            webhooks::on_push_event(&payload, installation_id, full_name).await;
+        }
+        JobKind::ImportRepository { full_name, subpath } => {
+            let Some((installation_id, repo_info)) = get_installation_id_and_repo_info(&full_name, subpath).await else { return; };
+            webhooks::on_repository_added(repo_info, installation_id).await;
+        }
+        JobKind::ImportEnglish { full_name, subpath } => {
+            let Some((installation_id, repo_info)) = get_installation_id_and_repo_info(&full_name, subpath).await else { return; };
+            webhooks::import_english(repo_info, installation_id).await;
+        }
+        JobKind::UpdateRepository { full_name, subpath } => {
+            let Some((installation_id, repo_info)) = get_installation_id_and_repo_info(&full_name, subpath).await else { return; };
+            trigger_update::trigger_update_single_repository(repo_info, installation_id).await;
+        }
+        JobKind::UpdateAllRepositories => {
+            trigger_update::trigger_update_all_repositories().await;
+        }
+        JobKind::Uninstall { repositories } => {
+            uninstall::on_repositories_removed(repositories).await;
+        }
+        JobKind::DeleteRepository { full_name } => {
+            uninstall::delete_archived_repository(&full_name).await;
+        }
+        JobKind::RenameRepository { old_full_name, new_full_name } => {
+            webhooks::on_repository_renamed(&old_full_name, &new_full_name).await;
+        }
+        JobKind::RenameOwner { old_owner, new_owner } => {
+            webhooks::on_owner_renamed(&old_owner, &new_owner).await;
+        }
+    }
+}
+
+/// Repository could be uninstalled or changed between enqueuing and executing job
+async fn get_installation_id_and_repo_info(
+    full_name: &str,
+    subpath: Option<String>,
+) -> Option<(InstallationId, GithubRepoInfo)> {
+    match trigger_update::get_installation_id_and_repo_info(full_name, subpath).await {
+        Ok(value) => Some(value),
+        Err(message) => {
+            info!("[jobs] [{}] skipping: {}", full_name, message);
+            None
+        }
+    }
+}
+
+/// Jobs for all repositories (weekly update) are exclusive,
+/// other jobs are executed in parallel if they don't share repositories.
+static ALL_REPOSITORIES_LOCK: LazyLock<Arc<RwLock<()>>> = LazyLock::new(Default::default);
+static REPOSITORY_LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = LazyLock::new(Default::default);
+
+enum RepositoriesLock {
+    All(#[allow(unused)] OwnedRwLockWriteGuard<()>),
+    Some(#[allow(unused)] OwnedRwLockReadGuard<()>, #[allow(unused)] Vec<OwnedMutexGuard<()>>),
+}
+
+async fn lock_repositories(kind: &JobKind) -> RepositoriesLock {
+    let Some(mut repositories) = kind.repositories() else {
+        return RepositoriesLock::All(ALL_REPOSITORIES_LOCK.clone().write_owned().await);
+    };
+    let all_lock = ALL_REPOSITORIES_LOCK.clone().read_owned().await;
+    // Sorted to avoid deadlocks
+    repositories.sort();
+    repositories.dedup();
+    let mut guards = Vec::new();
+    for repository in repositories {
+        let lock = REPOSITORY_LOCKS.lock().unwrap()
+            .entry(repository.to_owned())
+            .or_default()
+            .clone();
+        guards.push(lock.lock_owned().await);
+    }
+    RepositoriesLock::Some(all_lock, guards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_secs(60));
+        assert_eq!(retry_delay(2), Duration::from_secs(120));
+        assert_eq!(retry_delay(3), Duration::from_secs(240));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_job_serialization() {
+        let job = Job {
+            id: 7,
+            kind: JobKind::UpdateRepository { full_name: "owner/repo".to_owned(), subpath: None },
+            attempts: 1,
+            next_attempt_at: 100,
+        };
+        let json = serde_json::to_value(&job).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "id": 7,
+            "kind": "update-repository",
+            "full_name": "owner/repo",
+            "subpath": null,
+            "attempts": 1,
+            "next_attempt_at": 100,
+        }));
+        let job: Job = serde_json::from_value(json).unwrap();
+        assert_eq!(job.to_string(), "#7 update-repository owner/repo");
+    }
+}
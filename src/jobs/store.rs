@@ -0,0 +1,57 @@
+//! Each unfinished job is stored as `{directory}/{id}.json`.
+//! File is removed when job is finished, so after restart all remaining files are resumed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::error;
+
+use crate::jobs::{Job, JobId};
+use crate::util;
+
+pub struct JobStore {
+    directory: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(directory: &Path) -> Self {
+        fs::create_dir_all(directory).unwrap();
+        Self { directory: directory.to_owned() }
+    }
+
+    fn job_path(&self, id: JobId) -> PathBuf {
+        self.directory.join(format!("{}.json", id))
+    }
+
+    /// Writes to temporary file first, so job file is never partially written
+    pub fn save(&self, job: &Job) {
+        let json = serde_json::to_string_pretty(job).unwrap();
+        let path = self.job_path(job.id);
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, json).unwrap();
+        fs::rename(temporary_path, path).unwrap();
+    }
+
+    pub fn remove(&self, id: JobId) {
+        fs::remove_file(self.job_path(id)).ok();
+    }
+
+    /// Jobs sorted by id, that is in order they were created
+    pub fn load_all(&self) -> Vec<Job> {
+        let mut jobs = util::read_dir(&self.directory)
+            .filter(|(_path, name)| name.ends_with(".json"))
+            .filter_map(|(path, name)| {
+                let json = fs::read_to_string(&path).unwrap();
+                match serde_json::from_str::<Job>(&json) {
+                    Ok(job) => Some(job),
+                    Err(error) => {
+                        error!("[jobs] can't parse job file {}: {}", name, error);
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+}
@@ -1,15 +1,18 @@
 //! General overview of the process:
 //! 1. GitHub app installed - [webhooks::on_repositories_added]
 //! 2. English files updated on GitHub - [webhooks::on_push_event]
-//! 3. Weekly update from Crowdin to GitHub - [server::trigger_update::push_all_crowdin_changes_to_github]
+//! 3. Weekly update from Crowdin to GitHub - [server::trigger_update::trigger_update_all_repositories]
 
 pub mod crowdin;
 pub mod git_util;
 pub mod github;
+pub mod jobs;
+pub mod locale;
 pub mod mod_directory;
 pub mod myenv;
 pub mod sentry;
 pub mod server;
+pub mod uninstall;
 pub mod util;
 pub mod webhooks;
 pub mod github_repo_info;
@@ -28,5 +31,7 @@ pub async fn init_with_crowdin() {
 
 pub async fn main() {
     init_with_crowdin().await;
+    crowdin::directory_ids::init(jobs::get_directory().join("crowdin").join("directories.json"));
+    jobs::init();
     server::main().await;
 }
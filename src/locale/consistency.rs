@@ -0,0 +1,68 @@
+//! Translation which lost placeholder (`__1__`) or rich text tag (`[item=...]`) of english string
+//! will be broken in game, so such translations are not committed to repository.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::locale::LocaleFile;
+use crate::locale::markup::markup_matches;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Mismatch {
+    pub section: Option<String>,
+    pub key: String,
+    pub english: String,
+    pub translation: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(section) = &self.section {
+            write!(f, "[{}] ", section)?;
+        }
+        write!(f, "{}: `{}` translated as `{}`", self.key, self.english, self.translation)
+    }
+}
+
+/// Removes translations which placeholders/tags don't match english file, and returns them
+pub fn remove_mismatched_translations(english: &LocaleFile, translation: &mut LocaleFile) -> Vec<Mismatch> {
+    let english_values = english.entries()
+        .map(|entry| ((entry.section, entry.key), entry.value))
+        .collect::<HashMap<_, _>>();
+    let mut mismatches = Vec::new();
+    translation.retain_entries(|entry| {
+        let Some(english_value) = english_values.get(&(entry.section, entry.key)) else {
+            return true;
+        };
+        if markup_matches(english_value, entry.value) {
+            return true;
+        }
+        mismatches.push(Mismatch {
+            section: entry.section.map(ToOwned::to_owned),
+            key: entry.key.to_owned(),
+            english: english_value.to_string(),
+            translation: entry.value.to_owned(),
+        });
+        false
+    });
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_mismatched_translations() {
+        let english = LocaleFile::parse("[section]\nkey1=__1__ items\nkey2=[item=iron-plate]\nkey3=foo\n");
+        let mut translation = LocaleFile::parse("[section]\nkey1=__1__ предметов\nkey2=железная пластина\nkey3=бар\nkey4=__1_\n");
+        let mismatches = remove_mismatched_translations(&english, &mut translation);
+        assert_eq!(translation.to_string(), "[section]\nkey1=__1__ предметов\nkey3=бар\nkey4=__1_\n");
+        assert_eq!(mismatches, vec![Mismatch {
+            section: Some("section".to_owned()),
+            key: "key2".to_owned(),
+            english: "[item=iron-plate]".to_owned(),
+            translation: "железная пластина".to_owned(),
+        }]);
+    }
+}
@@ -0,0 +1,72 @@
+//! `info.json` of mod contains `title` and `description` shown in mod portal and in-game mod list.
+//! Factorio allows to localize them using `[mod-name]` and `[mod-description]` sections of locale files.
+//!
+//! If english files don't define these keys, synthetic english file [FILE_NAME] is generated from `info.json`
+//! and uploaded to Crowdin. It is never added to `locale/en`, but its translations are added to `locale/<lang>`.
+
+use serde::Deserialize;
+
+use crate::locale::LocaleFile;
+
+/// Name of synthetic english file inside language folder
+pub const FILE_NAME: &str = "info-json.cfg";
+
+#[derive(Deserialize)]
+struct InfoJson {
+    name: String,
+    title: Option<String>,
+    description: Option<String>,
+}
+
+/// `english_files` are contents of english files of mod.
+/// Returns `None` if `info.json` is invalid or all its strings are already localized by mod
+pub fn generate_english_file(info_json: &str, english_files: &[String]) -> Option<String> {
+    let info_json: InfoJson = serde_json::from_str(info_json).ok()?;
+    let name = info_json.name.as_str();
+    if name.is_empty() || name.contains(['=', '[', ']', '\n', '\r']) { return None; }
+
+    let english_files = english_files.iter()
+        .map(|content| LocaleFile::parse(content))
+        .collect::<Vec<_>>();
+    let is_defined = |section: &str| {
+        english_files.iter()
+            .flat_map(LocaleFile::entries)
+            .any(|entry| entry.section == Some(section) && entry.key == name)
+    };
+    let mut result = String::new();
+    for (section, value) in [("mod-name", &info_json.title), ("mod-description", &info_json.description)] {
+        let Some(value) = value else { continue; };
+        if value.trim().is_empty() || is_defined(section) { continue; }
+        result += &format!("[{}]\n{}={}\n", section, name, escape_value(value));
+    }
+    if result.is_empty() { None } else { Some(result) }
+}
+
+/// Locale values are single line, line breaks are written as `\n`
+fn escape_value(value: &str) -> String {
+    value.replace("\r\n", "\n").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_english_file() {
+        let info_json = r#"{"name": "my-mod", "version": "1.0.0", "title": "My mod", "description": "Adds\nthings"}"#;
+        assert_eq!(
+            generate_english_file(info_json, &[]).as_deref(),
+            Some("[mod-name]\nmy-mod=My mod\n[mod-description]\nmy-mod=Adds\\nthings\n"),
+        );
+        let english_files = ["[mod-name]\nmy-mod=My localized mod\n".to_owned()];
+        assert_eq!(
+            generate_english_file(info_json, &english_files).as_deref(),
+            Some("[mod-description]\nmy-mod=Adds\\nthings\n"),
+        );
+        let english_files = ["[mod-name]\nmy-mod=Mod\n[mod-description]\nmy-mod=Description\n".to_owned()];
+        assert_eq!(generate_english_file(info_json, &english_files), None);
+        assert_eq!(generate_english_file(r#"{"name": "my-mod"}"#, &[]), None);
+        assert_eq!(generate_english_file(r#"{"name": "a=b", "title": "Title"}"#, &[]), None);
+        assert_eq!(generate_english_file("{", &[]), None);
+    }
+}
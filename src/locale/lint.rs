@@ -0,0 +1,208 @@
+//! Checks of english locale files before uploading them to Crowdin.
+//! Errors mean that file most likely is broken in game too,
+//! so it is better to ask mod author to fix it instead of uploading it to translators.
+//! Warnings (including checks which may have false positives) don't prevent upload.
+//! Both are shown to mod author on status page, see [crate::mod_directory::ModDirectory::check_english_files].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::locale::{LineContent, LocaleFile};
+use crate::locale::markup::{check_markup, MarkupProblem};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    InvalidUtf8,
+    DuplicateKey { key: String, first_line: usize },
+    KeyOutsideSection { key: String },
+    LineWithoutEquals,
+    Markup { key: String, problem: MarkupProblem },
+    MixedLineEndings,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// File name relative to language directory, e.g. `locale.cfg`
+    pub file: String,
+    /// 1-based
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match &self.kind {
+            DiagnosticKind::InvalidUtf8 | DiagnosticKind::DuplicateKey { .. } => Severity::Error,
+            DiagnosticKind::Markup { problem, .. } if *problem != MarkupProblem::UnbalancedPlaceholder => Severity::Error,
+            DiagnosticKind::Markup { .. }
+            | DiagnosticKind::KeyOutsideSection { .. }
+            | DiagnosticKind::LineWithoutEquals
+            | DiagnosticKind::MixedLineEndings => Severity::Warning,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity() {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}:{}: {}: {}", self.file, self.line, severity, self.kind)
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::InvalidUtf8 => {
+                write!(f, "file is not valid UTF-8")
+            }
+            DiagnosticKind::DuplicateKey { key, first_line } => {
+                write!(f, "duplicate key `{}` (first defined at line {})", key, first_line)
+            }
+            DiagnosticKind::KeyOutsideSection { key } => {
+                write!(f, "key `{}` is outside of any [section]", key)
+            }
+            DiagnosticKind::LineWithoutEquals => {
+                write!(f, "line is not a section, comment or `key=value`")
+            }
+            DiagnosticKind::Markup { key, problem } => {
+                write!(f, "value of key `{}` has {}", key, problem)
+            }
+            DiagnosticKind::MixedLineEndings => {
+                write!(f, "line ending differs from previous lines (mixed CRLF and LF)")
+            }
+        }
+    }
+}
+
+pub fn lint_file(file_name: &str, content: &[u8]) -> Vec<Diagnostic> {
+    match std::str::from_utf8(content) {
+        Ok(content) => lint_content(file_name, content),
+        Err(error) => {
+            let valid = &content[..error.valid_up_to()];
+            let line = valid.iter().filter(|&&it| it == b'\n').count() + 1;
+            vec![Diagnostic { file: file_name.to_owned(), line, kind: DiagnosticKind::InvalidUtf8 }]
+        }
+    }
+}
+
+pub fn lint_content(file_name: &str, content: &str) -> Vec<Diagnostic> {
+    let file = LocaleFile::parse(content);
+    let mut diagnostics = Vec::new();
+    let mut push = |line: usize, kind: DiagnosticKind| {
+        diagnostics.push(Diagnostic { file: file_name.to_owned(), line, kind });
+    };
+
+    let mut first_line_ending = None;
+    for (index, line) in file.lines.iter().enumerate() {
+        if let LineContent::Unknown(_) = line.content {
+            push(index + 1, DiagnosticKind::LineWithoutEquals);
+        }
+        let Some(ending) = line.ending else { continue; };
+        match first_line_ending {
+            None => first_line_ending = Some(ending),
+            Some(first) if first != ending => push(index + 1, DiagnosticKind::MixedLineEndings),
+            Some(_) => {}
+        }
+    }
+
+    let mut keys: HashMap<(Option<&str>, &str), usize> = HashMap::new();
+    for entry in file.entries() {
+        let key = entry.key.to_owned();
+        if entry.section.is_none() {
+            push(entry.line_number, DiagnosticKind::KeyOutsideSection { key: key.clone() });
+        }
+        if let Some(&first_line) = keys.get(&(entry.section, entry.key)) {
+            push(entry.line_number, DiagnosticKind::DuplicateKey { key: key.clone(), first_line });
+        } else {
+            keys.insert((entry.section, entry.key), entry.line_number);
+        }
+        for problem in check_markup(entry.value) {
+            push(entry.line_number, DiagnosticKind::Markup { key: key.clone(), problem });
+        }
+    }
+
+    diagnostics.sort_by_key(|it| it.line);
+    diagnostics
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(Diagnostic::is_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::DiagnosticKind::*;
+
+    fn test(content: &str, expected: &[(usize, DiagnosticKind)]) {
+        let diagnostics = lint_content("locale.cfg", content)
+            .into_iter()
+            .map(|it| (it.line, it.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, diagnostics);
+    }
+
+    #[test]
+    fn test_correct() {
+        test("", &[]);
+        test("[section]\nkey1=value1\nkey2=__1__ [item=iron-plate]\n", &[]);
+        test("; comment\r\n[section]\r\nkey=value\r\n", &[]);
+        test("[section1]\nkey=value\n[section2]\nkey=value", &[]);
+    }
+
+    #[test]
+    fn test_duplicate_key() {
+        test("[section]\nkey=1\nkey=2", &[(3, DuplicateKey { key: "key".to_owned(), first_line: 2 })]);
+    }
+
+    #[test]
+    fn test_key_outside_section() {
+        test("key=1\n[section]\nkey=2", &[(1, KeyOutsideSection { key: "key".to_owned() })]);
+    }
+
+    #[test]
+    fn test_line_without_equals() {
+        test("[section]\nfoo\n[bar", &[(2, LineWithoutEquals), (3, LineWithoutEquals)]);
+    }
+
+    #[test]
+    fn test_markup() {
+        test("[section]\nkey=__1_", &[(2, Markup { key: "key".to_owned(), problem: MarkupProblem::UnbalancedPlaceholder })]);
+        test("[section]\nkey=[color=red]foo", &[(2, Markup { key: "key".to_owned(), problem: MarkupProblem::UnclosedTag("color".to_owned()) })]);
+        test("[section]\nkey=Type __foo__ in console", &[]);
+    }
+
+    #[test]
+    fn test_mixed_line_endings() {
+        test("[section]\r\nkey1=1\nkey2=2\r\nkey3=3\n", &[(2, MixedLineEndings), (4, MixedLineEndings)]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let diagnostics = lint_file("locale.cfg", b"[section]\nkey=\xFF\xFE\n");
+        assert_eq!(diagnostics, vec![Diagnostic { file: "locale.cfg".to_owned(), line: 2, kind: InvalidUtf8 }]);
+        assert!(has_errors(&diagnostics));
+    }
+
+    #[test]
+    fn test_severity() {
+        assert!(!has_errors(&lint_content("locale.cfg", "key=value\nfoo")));
+        // may be false positive, so it doesn't block upload
+        assert!(!has_errors(&lint_content("locale.cfg", "[section]\nkey=__1_")));
+        assert!(has_errors(&lint_content("locale.cfg", "[section]\nkey=1\nkey=2")));
+        assert!(has_errors(&lint_content("locale.cfg", "[section]\nkey=[color=red]foo")));
+    }
+}
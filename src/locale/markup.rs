@@ -0,0 +1,235 @@
+//! Placeholders and rich text tags inside locale values:
+//! * https://wiki.factorio.com/Tutorial:Localisation#Localising_with_parameters
+//! * https://wiki.factorio.com/Rich_text
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Rich text tags which should be closed, e.g. `[color=red]text[/color]`.
+/// All other tags (e.g. `[item=iron-plate]`) are self-contained.
+const PAIRED_TAGS: &[&str] = &["color", "font"];
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Token<'a> {
+    /// `__1__`, `__ENTITY__iron-plate__`, `__CONTROL_STYLE_BEGIN__`, `__plural_for_parameter__1__{1=item|rest=items}__`
+    Placeholder(&'a str),
+    /// `[item=iron-plate]`, `[color=red]`
+    Tag { name: &'a str, value: &'a str },
+    /// `[/color]` or `[.color]`
+    ClosingTag(&'a str),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MarkupProblem {
+    /// `__` which is not part of any placeholder, e.g. `__1_`
+    UnbalancedPlaceholder,
+    /// `[item=iron-plate` without `]`
+    UnterminatedTag,
+    /// `[color=red]` without `[/color]`
+    UnclosedTag(String),
+    /// `[/color]` without `[color=...]`
+    UnexpectedClosingTag(String),
+}
+
+impl fmt::Display for MarkupProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupProblem::UnbalancedPlaceholder => write!(f, "unbalanced `__` placeholder"),
+            MarkupProblem::UnterminatedTag => write!(f, "rich text tag without closing `]`"),
+            MarkupProblem::UnclosedTag(name) => write!(f, "`[{}=...]` without `[/{}]`", name, name),
+            MarkupProblem::UnexpectedClosingTag(name) => write!(f, "`[/{}]` without `[{}=...]`", name, name),
+        }
+    }
+}
+
+static PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    // name of entity/item/control, may contain single underscores
+    let name = r"[^\s_]+(?:_[^\s_]+)*";
+    let categories = "CONTROL|CONTROL_MODIFIER|ENTITY|ITEM|TILE|FLUID|RECIPE|TECHNOLOGY|EQUIPMENT|ITEM_GROUP|SPACE_LOCATION";
+    let regex = format!(
+        r"__(?:(?:{categories})__{name}|ALT_CONTROL__\d+__{name}|plural_for_parameter_{{1,2}}\d+_{{1,2}}\{{[^}}]*\}}|[A-Z0-9]+(?:_[A-Z0-9]+)*)__"
+    );
+    Regex::new(&regex).unwrap()
+});
+
+/// Text like `__foo__` is shown as is by the game, so it is not an unbalanced placeholder
+static LOWERCASE_TEXT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"__[a-z][^\s_]*(?:_[^\s_]+)*__").unwrap()
+});
+
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(?:[/.]([a-z][a-z-]*)|([a-z][a-z-]*)=([^\]\[]*))\]").unwrap()
+});
+
+static UNTERMINATED_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(?:[/.][a-z][a-z-]*|[a-z][a-z-]*=)").unwrap()
+});
+
+/// Returns placeholders and rich text tags in order of appearance
+pub fn tokenize(value: &str) -> Vec<Token<'_>> {
+    let placeholders = PLACEHOLDER_REGEX.find_iter(value)
+        .map(|it| (it.start(), Token::Placeholder(it.as_str())));
+    let tags = TAG_REGEX.captures_iter(value)
+        .map(|captures| {
+            let start = captures.get(0).unwrap().start();
+            let token = match captures.get(1) {
+                Some(name) => Token::ClosingTag(name.as_str()),
+                None => Token::Tag {
+                    name: captures.get(2).unwrap().as_str(),
+                    value: captures.get(3).unwrap().as_str(),
+                },
+            };
+            (start, token)
+        });
+    let mut tokens = placeholders.chain(tags).collect::<Vec<_>>();
+    tokens.sort_by_key(|(start, _)| *start);
+    tokens.into_iter().map(|(_, token)| token).collect()
+}
+
+pub fn check_markup(value: &str) -> Vec<MarkupProblem> {
+    let mut problems = Vec::new();
+
+    let without_placeholders = PLACEHOLDER_REGEX.replace_all(value, "");
+    let without_placeholders = LOWERCASE_TEXT_REGEX.replace_all(&without_placeholders, "");
+    if without_placeholders.contains("__") {
+        problems.push(MarkupProblem::UnbalancedPlaceholder);
+    }
+
+    let without_tags = TAG_REGEX.replace_all(value, "");
+    if UNTERMINATED_TAG_REGEX.is_match(&without_tags) {
+        problems.push(MarkupProblem::UnterminatedTag);
+    }
+
+    let mut open_tags = Vec::new();
+    for token in tokenize(value) {
+        match token {
+            Token::Tag { name, .. } if PAIRED_TAGS.contains(&name) => {
+                open_tags.push(name);
+            }
+            Token::ClosingTag(name) => {
+                if open_tags.last() == Some(&name) {
+                    open_tags.pop();
+                } else {
+                    problems.push(MarkupProblem::UnexpectedClosingTag(name.to_owned()));
+                }
+            }
+            _ => {}
+        }
+    }
+    for name in open_tags {
+        problems.push(MarkupProblem::UnclosedTag(name.to_owned()));
+    }
+    problems
+}
+
+/// Checks that translation contains same placeholders and rich text tags as english string (in any order).
+/// Values of `[color=...]` and `[font=...]` and plural forms are allowed to differ.
+pub fn markup_matches(english: &str, translation: &str) -> bool {
+    fn normalized_tokens(value: &str) -> Vec<Token<'_>> {
+        let mut tokens = tokenize(value)
+            .into_iter()
+            .map(|token| match token {
+                Token::Placeholder(placeholder) => {
+                    // `__plural_for_parameter__1__{1=item|rest=items}__` => `__plural_for_parameter__1__`
+                    let placeholder = placeholder.split_once('{')
+                        .map_or(placeholder, |(prefix, _)| prefix);
+                    Token::Placeholder(placeholder)
+                }
+                Token::Tag { name, .. } if PAIRED_TAGS.contains(&name) => Token::Tag { name, value: "" },
+                token => token,
+            })
+            .collect::<Vec<_>>();
+        tokens.sort();
+        tokens
+    }
+    normalized_tokens(english) == normalized_tokens(translation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::MarkupProblem::*;
+
+    fn test_placeholders(value: &str, expected: &[&str]) {
+        let placeholders = tokenize(value)
+            .into_iter()
+            .filter_map(|it| match it {
+                Token::Placeholder(placeholder) => Some(placeholder),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected, placeholders);
+    }
+
+    #[test]
+    fn test_tokenize_placeholders() {
+        test_placeholders("foo", &[]);
+        test_placeholders("__1__ and __2__", &["__1__", "__2__"]);
+        test_placeholders("Place __ENTITY__iron-chest__", &["__ENTITY__iron-chest__"]);
+        test_placeholders("__ITEM__my_mod_item__", &["__ITEM__my_mod_item__"]);
+        test_placeholders("Press __CONTROL__move-up__", &["__CONTROL__move-up__"]);
+        test_placeholders("__ALT_CONTROL__1__mine__", &["__ALT_CONTROL__1__mine__"]);
+        test_placeholders("__CONTROL_STYLE_BEGIN__Press__CONTROL_STYLE_END__", &["__CONTROL_STYLE_BEGIN__", "__CONTROL_STYLE_END__"]);
+        test_placeholders("__1__ __plural_for_parameter_1_{1=item|rest=items}__", &["__1__", "__plural_for_parameter_1_{1=item|rest=items}__"]);
+        test_placeholders("__plural_for_parameter__1__{1=item|rest=items}__", &["__plural_for_parameter__1__{1=item|rest=items}__"]);
+    }
+
+    #[test]
+    fn test_tokenize_tags() {
+        assert_eq!(
+            tokenize("[color=red]__1__[/color] [item=iron-plate] [.font]"),
+            vec![
+                Token::Tag { name: "color", value: "red" },
+                Token::Placeholder("__1__"),
+                Token::ClosingTag("color"),
+                Token::Tag { name: "item", value: "iron-plate" },
+                Token::ClosingTag("font"),
+            ]
+        );
+        assert_eq!(tokenize("[note] [1]"), vec![]);
+    }
+
+    #[test]
+    fn test_check_markup_correct() {
+        let no_problems: Vec<MarkupProblem> = vec![];
+        assert_eq!(check_markup("foo"), no_problems);
+        assert_eq!(check_markup("[note] text"), no_problems);
+        assert_eq!(check_markup("__1__ [item=iron-plate] __ENTITY__iron-chest__"), no_problems);
+        assert_eq!(check_markup("[color=red][font=default-bold]foo[/font][/color]"), no_problems);
+        assert_eq!(check_markup("[color=red]foo[.color]"), no_problems);
+        assert_eq!(check_markup("use __foo__ and __my_mod__ as is"), no_problems);
+    }
+
+    #[test]
+    fn test_check_markup_problems() {
+        assert_eq!(check_markup("__1_"), vec![UnbalancedPlaceholder]);
+        assert_eq!(check_markup("__1__ __2"), vec![UnbalancedPlaceholder]);
+        assert_eq!(check_markup("[item=iron-plate"), vec![UnterminatedTag]);
+        assert_eq!(check_markup("[color=red]foo"), vec![UnclosedTag("color".to_owned())]);
+        assert_eq!(check_markup("foo[/color]"), vec![UnexpectedClosingTag("color".to_owned())]);
+        assert_eq!(
+            check_markup("[color=red][font=default]foo[/color][/font]"),
+            vec![UnexpectedClosingTag("color".to_owned()), UnclosedTag("color".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_markup_matches() {
+        assert!(markup_matches("foo", "bar"));
+        assert!(markup_matches("__1__ of __2__", "__2__ из __1__"));
+        assert!(markup_matches("Place __ENTITY__iron-chest__", "Поставьте __ENTITY__iron-chest__"));
+        assert!(markup_matches("[item=iron-plate] [color=red]x[/color]", "[color=#ff0000]y[/color] [item=iron-plate]"));
+        assert!(markup_matches(
+            "__1__ __plural_for_parameter__1__{1=item|rest=items}__",
+            "__1__ __plural_for_parameter__1__{1=предмет|2=предмета|rest=предметов}__",
+        ));
+
+        assert!(!markup_matches("__1__", "__1_"));
+        assert!(!markup_matches("__1__ __2__", "__1__"));
+        assert!(!markup_matches("__ENTITY__iron-chest__", "__ENTITY__железный-сундук__"));
+        assert!(!markup_matches("[item=iron-plate]", "[item=iron-gear-wheel]"));
+        assert!(!markup_matches("[color=red]x[/color]", "[color=red]x"));
+    }
+}
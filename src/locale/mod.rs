@@ -0,0 +1,298 @@
+//! Factorio locale files (`.cfg`).
+//!
+//! General format is (https://wiki.factorio.com/Tutorial:Localisation):
+//! ```ini
+//! ; comment
+//! [section]
+//! key=value
+//! ```
+//!
+//! [LocaleFile] keeps everything which is needed to write file back without changes:
+//! BOM, blank lines, comments, line endings and lines which can't be parsed.
+
+use std::collections::HashSet;
+use std::fmt;
+
+pub mod consistency;
+pub mod info_json;
+pub mod lint;
+pub mod markup;
+
+const BOM: char = '\u{FEFF}';
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LocaleFile {
+    pub has_bom: bool,
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Line {
+    pub content: LineContent,
+    /// `None` for last line of file without trailing newline
+    pub ending: Option<LineEnding>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LineContent {
+    /// Empty line or line with only whitespaces
+    Blank(String),
+    /// `; comment` or `# comment`
+    Comment(String),
+    /// `[name]`, everything after `]` (e.g. comment) is stored in `trailing`
+    Section { indent: String, name: String, trailing: String },
+    /// `key=value`, value is everything after first `=`
+    Entry { key: String, value: String },
+    /// Line which doesn't match any of above (e.g. line without `=`)
+    Unknown(String),
+}
+
+/// Key-value pair with its position in file
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct LocaleEntry<'a> {
+    /// `None` if key is located before first section
+    pub section: Option<&'a str>,
+    pub key: &'a str,
+    pub value: &'a str,
+    /// 1-based
+    pub line_number: usize,
+}
+
+impl LocaleFile {
+    pub fn parse(content: &str) -> Self {
+        let (has_bom, content) = match content.strip_prefix(BOM) {
+            Some(content) => (true, content),
+            None => (false, content),
+        };
+
+        let mut lines = Vec::new();
+        let mut rest = content;
+        while !rest.is_empty() {
+            let (line, ending) = match rest.split_once('\n') {
+                Some((line, tail)) => {
+                    rest = tail;
+                    match line.strip_suffix('\r') {
+                        Some(line) => (line, Some(LineEnding::CrLf)),
+                        None => (line, Some(LineEnding::Lf)),
+                    }
+                }
+                None => {
+                    let line = rest;
+                    rest = "";
+                    (line, None)
+                }
+            };
+            lines.push(Line { content: LineContent::parse(line), ending });
+        }
+        Self { has_bom, lines }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item=LocaleEntry<'_>> {
+        let mut section = None;
+        self.lines.iter()
+            .enumerate()
+            .filter_map(move |(index, line)| match &line.content {
+                LineContent::Section { name, .. } => {
+                    section = Some(name.as_str());
+                    None
+                }
+                LineContent::Entry { key, value } => {
+                    Some(LocaleEntry { section, key, value, line_number: index + 1 })
+                }
+                _ => None,
+            })
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item=&mut String> {
+        self.lines.iter_mut()
+            .filter_map(|line| match &mut line.content {
+                LineContent::Entry { value, .. } => Some(value),
+                _ => None,
+            })
+    }
+
+    /// Removes `key=value` lines for which `predicate` returns false
+    pub fn retain_entries(&mut self, mut predicate: impl FnMut(&LocaleEntry) -> bool) {
+        let removed_lines = self.entries()
+            .filter(|entry| !predicate(entry))
+            .map(|entry| entry.line_number)
+            .collect::<HashSet<_>>();
+        let mut line_number = 0;
+        self.lines.retain(|_| {
+            line_number += 1;
+            !removed_lines.contains(&line_number)
+        });
+    }
+
+    /// File without any key-value pairs (only sections, comments, etc)
+    pub fn is_empty(&self) -> bool {
+        self.entries().next().is_none()
+    }
+}
+
+impl fmt::Display for LocaleFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.has_bom {
+            write!(f, "{}", BOM)?;
+        }
+        for line in &self.lines {
+            write!(f, "{}", line.content)?;
+            match line.ending {
+                None => {}
+                Some(LineEnding::Lf) => f.write_str("\n")?,
+                Some(LineEnding::CrLf) => f.write_str("\r\n")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LineContent {
+    fn parse(line: &str) -> Self {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            return LineContent::Blank(line.to_owned());
+        }
+        if trimmed.starts_with(';') || trimmed.starts_with('#') {
+            return LineContent::Comment(line.to_owned());
+        }
+        if let Some(section) = trimmed.strip_prefix('[') {
+            if let Some((name, trailing)) = section.split_once(']') {
+                let indent = &line[..line.len() - trimmed.len()];
+                return LineContent::Section {
+                    indent: indent.to_owned(),
+                    name: name.to_owned(),
+                    trailing: trailing.to_owned(),
+                };
+            }
+            return LineContent::Unknown(line.to_owned());
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            return LineContent::Entry { key: key.to_owned(), value: value.to_owned() };
+        }
+        LineContent::Unknown(line.to_owned())
+    }
+}
+
+impl fmt::Display for LineContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineContent::Blank(line) | LineContent::Comment(line) | LineContent::Unknown(line) => {
+                write!(f, "{}", line)
+            }
+            LineContent::Section { indent, name, trailing } => {
+                write!(f, "{}[{}]{}", indent, name, trailing)
+            }
+            LineContent::Entry { key, value } => {
+                write!(f, "{}={}", key, value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_roundtrip(content: &str) {
+        assert_eq!(content, LocaleFile::parse(content).to_string());
+    }
+
+    fn check_entries(content: &str, expected: &[(Option<&str>, &str, &str, usize)]) {
+        let file = LocaleFile::parse(content);
+        let entries = file.entries()
+            .map(|it| (it.section, it.key, it.value, it.line_number))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, entries);
+    }
+
+    #[test]
+    fn test_roundtrip_simple() {
+        test_roundtrip("");
+        test_roundtrip("\n");
+        test_roundtrip("[section]");
+        test_roundtrip("[section]\nkey=value\n");
+        test_roundtrip("[section]\nkey=value");
+        test_roundtrip("\n\n[section]\n\nkey1=value1\nkey2=value2\n\n");
+    }
+
+    #[test]
+    fn test_roundtrip_edge_cases() {
+        test_roundtrip("\u{FEFF}[section]\nkey=value\n");
+        test_roundtrip("[section]\r\nkey=value\r\n");
+        test_roundtrip("[section]\r\nkey1=value1\nkey2=value2\r\n");
+        test_roundtrip("[section] ; comment\nkey=value\n");
+        test_roundtrip("  [section]  \n  key = value  \n");
+        test_roundtrip("; comment\n# comment\nkey=value");
+        test_roundtrip("[section]\nline without equals sign\n[unclosed\n");
+        test_roundtrip("[section]\nkey=a=b;c\"d\"\n");
+        test_roundtrip("\t \n \r\n");
+    }
+
+    #[test]
+    fn test_bom() {
+        let file = LocaleFile::parse("\u{FEFF}[section]\nkey=value");
+        assert!(file.has_bom);
+        check_entries("\u{FEFF}[section]\nkey=value", &[(Some("section"), "key", "value", 2)]);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let file = LocaleFile::parse("a=1\r\nb=2\nc=3");
+        let endings = file.lines.iter().map(|it| it.ending).collect::<Vec<_>>();
+        assert_eq!(endings, vec![Some(LineEnding::CrLf), Some(LineEnding::Lf), None]);
+    }
+
+    #[test]
+    fn test_line_contents() {
+        let file = LocaleFile::parse("  \n; c\n[s] ; c\nk=v\n[x\nfoo");
+        let contents = file.lines.into_iter().map(|it| it.content).collect::<Vec<_>>();
+        assert_eq!(contents, vec![
+            LineContent::Blank("  ".to_owned()),
+            LineContent::Comment("; c".to_owned()),
+            LineContent::Section { indent: "".to_owned(), name: "s".to_owned(), trailing: " ; c".to_owned() },
+            LineContent::Entry { key: "k".to_owned(), value: "v".to_owned() },
+            LineContent::Unknown("[x".to_owned()),
+            LineContent::Unknown("foo".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_entries() {
+        check_entries("key0=value0\n[section1]\nkey1=value1\n; key=comment\n[section2] ; comment\nkey2=\nkey2=value2", &[
+            (None, "key0", "value0", 1),
+            (Some("section1"), "key1", "value1", 3),
+            (Some("section2"), "key2", "", 6),
+            (Some("section2"), "key2", "value2", 7),
+        ]);
+    }
+
+    #[test]
+    fn test_is_empty() {
+        assert!(LocaleFile::parse("").is_empty());
+        assert!(LocaleFile::parse("\n[section]\n; comment\n\n").is_empty());
+        assert!(!LocaleFile::parse("[section]\nkey=").is_empty());
+    }
+
+    #[test]
+    fn test_values_mut() {
+        let mut file = LocaleFile::parse("[section]\r\nkey1=value1\r\nkey2=value2");
+        for value in file.values_mut() {
+            value.push('!');
+        }
+        assert_eq!(file.to_string(), "[section]\r\nkey1=value1!\r\nkey2=value2!");
+    }
+
+    #[test]
+    fn test_retain_entries() {
+        let mut file = LocaleFile::parse("[section1]\nkey1=value1\nkey2=value2\n[section2]\nkey1=value3\n");
+        file.retain_entries(|entry| entry.section != Some("section1") || entry.key != "key1");
+        assert_eq!(file.to_string(), "[section1]\nkey2=value2\n[section2]\nkey1=value3\n");
+    }
+}
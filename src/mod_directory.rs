@@ -1,10 +1,14 @@
+use std::fs;
 use std::path::{Path, PathBuf};
 
 use log::{error, warn};
 use tempfile::TempDir;
 
 use crate::{crowdin, util};
-use crate::github_repo_info::GithubModInfo;
+use crate::github_repo_info::{AdditionalFile, GithubModInfo};
+use crate::jobs::history;
+use crate::locale::{info_json, lint};
+use crate::locale::lint::Diagnostic;
 use crate::sentry::sentry_report_error;
 
 pub type LanguageCode = String;
@@ -25,47 +29,54 @@ impl RepositoryDirectory {
     }
 }
 
-/// Represents local directory containing factorio mod
+/// Represents local directory containing factorio mod.
+/// "English files" are files in source language of mod, which is english by default
 pub struct ModDirectory {
+    repository_root: PathBuf,
     pub locale_path: PathBuf,
     pub mod_info: GithubModInfo,
 }
 
 impl ModDirectory {
     pub fn new(repository_directory: &RepositoryDirectory, mod_info: GithubModInfo) -> Self {
-        let repository_root = repository_directory.root.path();
-        let locale_path = repository_root.join(&mod_info.locale_path).to_owned();
-        Self { locale_path, mod_info }
+        let repository_root = repository_directory.root.path().to_owned();
+        let locale_path = repository_root.join(&mod_info.locale_path);
+        Self { repository_root, locale_path, mod_info }
     }
 
     pub fn locale_path(&self) -> &Path {
         &self.locale_path
     }
 
-    pub fn locale_en_path(&self) -> PathBuf {
-        self.locale_path.join("en")
+    /// `locale/en` for english source language
+    pub fn locale_source_path(&self) -> PathBuf {
+        self.locale_path.join(&self.mod_info.source_language)
     }
 
     pub fn check_structure(&self) -> bool {
         if !self.check_for_locale_folder() {
-            warn!("[add-repository] [{}] Missing `locale/en`", &self.mod_info);
+            warn!("[add-repository] [{}] Missing `locale/{}`", &self.mod_info, &self.mod_info.source_language);
             return false;
         }
-        
+        if !self.check_english_files() {
+            return false;
+        }
+
         self.check_translation_files_match_english_files(true)
     }
 
     pub fn check_for_locale_folder(&self) -> bool {
-        self.locale_en_path().exists()
+        self.locale_source_path().exists()
     }
 
     pub fn check_translation_files_match_english_files(&self, report_sentry: bool) -> bool {
         let localizations = self.get_localizations();
         for (language_code, localized_files) in localizations {
             for localized_file in localized_files {
-                let file_name = util::file_name(&localized_file);
-                let english_file = self.locale_en_path().join(file_name);
-                if !english_file.exists() {
+                let file_name = self.get_file_name(&localized_file);
+                let english_file = self.locale_source_path().join(&file_name);
+                let is_info_json_file = file_name == info_json::FILE_NAME && self.get_info_json_english_file().is_some();
+                if !english_file.exists() && !is_info_json_file {
                     let message = format!(
                         "[add-repository] [{}] matched english file not found for '{}/{}'",
                         self.mod_info,
@@ -74,6 +85,7 @@ impl ModDirectory {
                     );
                     error!("{}", &message);
                     if report_sentry {
+                        history::add_error(&self.mod_info.full_name(), message.clone());
                         sentry_report_error(&message);
                     }
                     return false;
@@ -83,14 +95,94 @@ impl ModDirectory {
         true
     }
 
+    /// Returns false if english files have errors which mod author should fix before uploading to crowdin
+    pub fn check_english_files(&self) -> bool {
+        let diagnostics = self.lint_english_files();
+        if diagnostics.is_empty() { return true; }
+
+        let has_errors = lint::has_errors(&diagnostics);
+        let diagnostics = diagnostics
+            .iter()
+            .map(|it| format!("    {}", it))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let message = format!("[add-repository] [{}] problems in english files:\n{}", self.mod_info, diagnostics);
+        if has_errors {
+            history::add_error(&self.mod_info.full_name(), message.clone());
+            sentry_report_error(&message);
+        } else {
+            history::add_warning(&self.mod_info.full_name(), message.clone());
+            warn!("{}", message);
+        }
+        !has_errors
+    }
+
+    pub fn lint_english_files(&self) -> Vec<Diagnostic> {
+        self.get_english_files()
+            .iter()
+            .flat_map(|path| {
+                let content = fs::read(path).unwrap();
+                lint::lint_file(&self.get_file_name(path), &content)
+            })
+            .collect()
+    }
+
+    /// Path of file inside its language directory,
+    /// e.g. `subfolder/locale.cfg` for `{locale_path}/ru/subfolder/locale.cfg`.
+    /// English and translated files match if they have same name
+    pub fn get_file_name(&self, file: &Path) -> String {
+        let mut components = file.strip_prefix(&self.locale_path).unwrap().components();
+        components.next();
+        components.as_path().to_str().unwrap().to_owned()
+    }
+
+    /// Path of file relative to repository root, e.g. `locale/ru/subfolder/locale.cfg`
+    pub fn get_repository_path(&self, file: &Path) -> String {
+        util::relative_path(file, &self.repository_root)
+    }
+
+    /// Source files of `additional_files` option which exist in repository.
+    /// Symlinks are skipped, so files outside of repository are never uploaded
+    pub fn get_additional_files(&self) -> Vec<(PathBuf, &AdditionalFile)> {
+        self.mod_info.additional_files.iter()
+            .map(|file| (self.repository_root.join(&file.source), file))
+            .filter(|(path, _file)| path.is_file() && !path.is_symlink())
+            .collect()
+    }
+
+    /// Path where translation of additional file is stored in repository, e.g. `changelogs/de.txt`.
+    /// `None` if path points outside of repository through symlinked directory
+    pub fn get_additional_file_translation_path(&self, file: &AdditionalFile, language: &str) -> Option<PathBuf> {
+        let path = self.repository_root.join(file.translation_path(language));
+        let existing_ancestor = path.ancestors().skip(1).find(|it| it.exists()).unwrap();
+        let repository_root = self.repository_root.canonicalize().unwrap();
+        if !existing_ancestor.canonicalize().unwrap().starts_with(repository_root) { return None; }
+        if path.is_symlink() { return None; }
+        Some(path)
+    }
+
+    /// Includes files in subdirectories, e.g. `locale/en/subfolder/locale.cfg`
     pub fn get_english_files(&self) -> Vec<PathBuf> {
-        util::get_directory_cfg_files_paths(&self.locale_en_path())
+        util::get_directory_cfg_files_paths(&self.locale_source_path())
+    }
+
+    /// Synthetic english file with title and description from `info.json`, see [info_json]
+    pub fn get_info_json_english_file(&self) -> Option<String> {
+        let path = self.repository_root.join(self.mod_info.info_json_path());
+        if !path.is_file() || path.is_symlink() { return None; }
+        let info_json = fs::read_to_string(path).ok()?;
+        let english_files = self.get_english_files()
+            .iter()
+            .map(|path| String::from_utf8_lossy(&fs::read(path).unwrap()).into_owned())
+            .collect::<Vec<_>>();
+        info_json::generate_english_file(&info_json, &english_files)
     }
 
     pub fn get_localizations(&self) -> Vec<(LanguageCode, Vec<PathBuf>)> {
+        let source_language = crowdin::normalize_language_code(&self.mod_info.source_language);
         self.get_language_directories()
             .into_iter()
-            .filter(|(code, _path)| code != "en")
+            .filter(|(code, _path)| *code != source_language)
             .map(|(code, path)| {
                 let files = util::get_directory_cfg_files_paths(&path);
                 (code, files)
@@ -25,6 +25,10 @@ pub fn is_development() -> bool {
     dotenv::var("IS_DEVELOPMENT").ok() == Some("true".to_owned())
 }
 
+pub fn is_production() -> bool {
+    current_environment() == Environment::Production
+}
+
 macro_rules! gen {
     ($($ci:literal $name:ident),* $(,)?) => {
         $(
@@ -60,3 +64,22 @@ gen!(
     0 WEBSERVER_SECRET,
     0 WEBSERVER_SECRET_PUBLIC,
 );
+
+macro_rules! gen_optional {
+    ($($name:ident),* $(,)?) => {
+        $(
+            pub static $name: LazyLock<Option<String>> = LazyLock::new(|| dotenv::var(stringify!($name)).ok());
+        )*
+    }
+}
+
+// Not checked in `init`, each usage has fallback
+gen_optional!(
+    CROWDIN_API_BASE_URL,
+    CROWDIN_ORGANIZATION,
+    CROWDIN_STORAGE_URL,
+    CROWDIN_ADDITIONAL_PROJECT_IDS,
+    JOBS_DIRECTORY,
+    UNINSTALLED_REPOSITORIES_POLICY,
+    UNINSTALLED_REPOSITORIES_GRACE_PERIOD_DAYS,
+);
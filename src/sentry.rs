@@ -1,4 +1,4 @@
-use log::error;
+use log::{error, warn};
 use sentry::Level;
 use sentry_log::LogFilter;
 
@@ -37,3 +37,8 @@ pub fn sentry_report_error(message: &str) {
     error!("{}", message);
     sentry::capture_message(message, Level::Error);
 }
+
+pub fn sentry_report_warning(message: &str) {
+    warn!("{}", message);
+    sentry::capture_message(message, Level::Warning);
+}
@@ -0,0 +1,18 @@
+//! `/schema/factorio-mods-localization.json` - JSON Schema of config file, see [crate::github_config::config_schema].
+//! Url is stable, so it can be referenced from `$schema` field of config for autocompletion in editors.
+
+use rocket::get;
+use rocket::response::content::RawJson;
+
+use crate::github_config;
+use crate::server::SERVER_URL;
+
+pub fn get_config_schema_url() -> String {
+    format!("{}/schema/factorio-mods-localization.json", SERVER_URL)
+}
+
+#[get("/schema/factorio-mods-localization.json")]
+pub fn config_schema() -> RawJson<String> {
+    let schema = github_config::config_schema();
+    RawJson(serde_json::to_string_pretty(&schema).unwrap())
+}
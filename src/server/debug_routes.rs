@@ -1,15 +1,16 @@
 use rocket::get;
 use serde::Serialize;
 
-use crate::{github, webhooks};
+use crate::{github, jobs, uninstall};
+use crate::jobs::JobKind;
 use crate::server::check_secret;
-use crate::server::trigger_update::{get_installation_id_and_repo_info, get_trigger_update_mutex};
+use crate::server::status::get_status_page_url;
+use crate::server::trigger_update::get_installation_id_and_repo_info;
 
 #[get("/listRepos?<secret>")]
 pub async fn list_repositories(secret: Option<String>) -> String {
     if !check_secret(secret) { return "Missing secret".to_owned(); }
-    let api = github::as_app();
-    let repositories = github::get_all_repositories(&api).await
+    let repositories = github::get_all_repositories().await
         .into_iter()
         .map(|(repo_info, _)| repo_info.full_name)
         .collect::<Vec<_>>();
@@ -19,10 +20,10 @@ pub async fn list_repositories(secret: Option<String>) -> String {
 #[get("/listReposForUser?<user>&<secret>")]
 pub async fn list_repositories_for_user(user: String, secret: Option<String>) -> String {
     if !check_secret(secret) { return "Missing secret".to_owned(); }
-    let Some(api) = github::as_installation_for_user(&user).await else {
+    let Some(installation_id) = github::get_installation_id_for_user(&user).await else {
         return format!("App is not installed for {}", user);
     };
-    let repositories = github::get_all_repositories_of_installation(&api).await;
+    let repositories = github::get_all_repositories_of_installation(installation_id).await;
     serde_json::to_string_pretty(&repositories).unwrap()
 }
 
@@ -35,32 +36,40 @@ pub async fn list_users(secret: Option<String>) -> String {
     }
 
     if !check_secret(secret) { return "Missing secret".to_owned(); }
-    let api = github::as_app();
-    let repositories = github::get_all_installations(&api).await
+    let repositories = github::get_all_installations().await
         .into_iter()
         .map(|installation| User {
-            login: installation.account.login,
+            login: installation.login,
             repository_selection: installation.repository_selection,
         })
         .collect::<Vec<_>>();
     serde_json::to_string_pretty(&repositories).unwrap()
 }
 
+/// Crowdin directories of repositories for which app was uninstalled (or config is broken)
+#[get("/listOrphanedDirectories?<secret>")]
+pub async fn list_orphaned_directories(secret: Option<String>) -> String {
+    if !check_secret(secret) { return "Missing secret".to_owned(); }
+    match uninstall::get_orphaned_directories().await {
+        Ok(directories) => serde_json::to_string_pretty(&directories).unwrap(),
+        Err(error) => format!("Crowdin error: {}", error),
+    }
+}
+
 /// For cases when repository was not imported correctly for some reason and manual intervention is needed
 #[get("/importRepository?<repo>&<subpath>&<secret>")]
 pub async fn import_repository(
     repo: String,
     subpath: Option<String>,
     secret: Option<String>,
-) -> &'static str {
-    if !check_secret(secret) { return "Missing secret"; }
-    let _lock = get_trigger_update_mutex().await;
-    let (installation_id, repo_info) = match get_installation_id_and_repo_info(&repo, subpath).await {
-        Ok(value) => value,
-        Err(value) => return value,
-    };
-    webhooks::on_repository_added(repo_info, installation_id).await;
-    "Ok."
+) -> String {
+    if !check_secret(secret) { return "Missing secret".to_owned(); }
+    if let Err(message) = get_installation_id_and_repo_info(&repo, subpath.clone()).await {
+        return message.to_owned();
+    }
+    let status_page_url = get_status_page_url(Some(&repo));
+    jobs::enqueue(JobKind::ImportRepository { full_name: repo, subpath });
+    format!("Triggered. See {} for details.", status_page_url)
 }
 
 /// Overwrites all english file on crowdin based on github
@@ -69,21 +78,19 @@ pub async fn import_english(
     repo: String,
     subpath: Option<String>,
     secret: Option<String>,
-) -> &'static str {
-    if !check_secret(secret) { return "Missing secret"; }
-    let _lock = get_trigger_update_mutex().await;
-    let (installation_id, repo_info) = match get_installation_id_and_repo_info(&repo, subpath).await {
-        Ok(value) => value,
-        Err(value) => return value,
-    };
-    webhooks::import_english(repo_info, installation_id).await;
-    "Ok."
+) -> String {
+    if !check_secret(secret) { return "Missing secret".to_owned(); }
+    if let Err(message) = get_installation_id_and_repo_info(&repo, subpath.clone()).await {
+        return message.to_owned();
+    }
+    let status_page_url = get_status_page_url(Some(&repo));
+    jobs::enqueue(JobKind::ImportEnglish { full_name: repo, subpath });
+    format!("Triggered. See {} for details.", status_page_url)
 }
 
 #[get("/triggerOOM?<secret>")]
 pub async fn trigger_oom(secret: Option<String>) -> &'static str {
     if !check_secret(secret) { return "Missing secret"; }
-    let _lock = get_trigger_update_mutex().await;
 
     eprintln!("\nTrying to trigger OOM...");
     let mut v = Vec::new();
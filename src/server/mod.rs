@@ -8,23 +8,26 @@ use crate::myenv::WEBSERVER_SECRET;
 use crate::server::webhook_util::GithubEvent;
 use crate::webhooks;
 
+pub mod config_schema;
 mod debug_routes;
 mod example_error_routes;
-mod trigger_update;
+pub mod status;
+pub mod trigger_update;
 mod trigger_update_public;
 mod trigger_update_public_with_secret;
 pub mod webhook_util;
 
+pub const SERVER_URL: &str = "https://factorio-mods-localization.fly.dev";
+
 #[get("/")]
 fn index() -> RawHtml<&'static str> {
-    RawHtml("<p>Factorio mods localization</p><p>See <a href='https://github.com/dima74/factorio-mods-localization'>GitHub repository</a> for documentation</p>")
+    RawHtml("<p>Factorio mods localization</p><p>See <a href='https://github.com/dima74/factorio-mods-localization'>GitHub repository</a> for documentation</p><p><a href='/status'>Status of jobs</a></p>")
 }
 
 #[post("/webhook", format = "json", data = "<event>")]
 fn webhook(event: GithubEvent) {
-    let task = webhooks::webhook_impl(event.0);
-    // execute task in another thread, because it may be long
-    tokio::spawn(task);
+    // long work is executed in background by job queue
+    webhooks::webhook_impl(event.event, event.changes);
 }
 
 #[get("/version")]
@@ -46,11 +49,15 @@ pub async fn main() {
         trigger_update_public::trigger_update2,
         trigger_update_public_with_secret::trigger_update,
         version,
+        config_schema::config_schema,
+        status::status_api,
+        status::status_page,
         debug_routes::import_repository,
         debug_routes::import_english,
         debug_routes::list_repositories,
         debug_routes::list_repositories_for_user,
         debug_routes::list_users,
+        debug_routes::list_orphaned_directories,
         debug_routes::trigger_oom,
         example_error_routes::error1,
         example_error_routes::error2,
@@ -0,0 +1,174 @@
+//! `/status?repo=owner/repo` - page for mod authors with pending jobs and history of last jobs
+//! `/api/status?repo=owner/repo` - same in JSON
+
+use rocket::get;
+use rocket::response::content::{RawHtml, RawJson};
+use serde::Serialize;
+use url::Url;
+
+use crate::jobs;
+use crate::jobs::history;
+use crate::jobs::history::HistoryEntry;
+use crate::server::SERVER_URL;
+
+#[derive(Serialize)]
+struct Status {
+    repository: Option<String>,
+    pending_jobs: Vec<PendingJob>,
+    history: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize)]
+struct PendingJob {
+    id: jobs::JobId,
+    kind: &'static str,
+    /// `None` means all repositories
+    repositories: Option<Vec<String>>,
+    attempts: u32,
+    next_attempt_at: u64,
+}
+
+pub fn get_status_page_url(repo: Option<&str>) -> String {
+    let mut url = Url::parse(SERVER_URL).unwrap().join("/status").unwrap();
+    if let Some(repo) = repo {
+        url.query_pairs_mut().append_pair("repo", repo);
+    }
+    url.to_string()
+}
+
+fn get_status(repo: Option<String>) -> Result<Status, &'static str> {
+    if let Some(repo) = &repo {
+        if !history::is_valid_full_name(repo) {
+            return Err("`repo` parameter should be in format `owner/repo`");
+        }
+    }
+    let pending_jobs = jobs::pending_jobs()
+        .into_iter()
+        .filter_map(|job| {
+            let repositories = job.kind.repositories()
+                .map(|it| it.into_iter().map(ToOwned::to_owned).collect::<Vec<_>>());
+            if let (Some(repo), Some(repositories)) = (&repo, &repositories) {
+                if !repositories.contains(repo) { return None; }
+            }
+            Some(PendingJob {
+                id: job.id,
+                kind: job.kind.name(),
+                repositories,
+                attempts: job.attempts,
+                next_attempt_at: job.next_attempt_at,
+            })
+        })
+        .collect();
+    let history = repo.as_deref().map(history::get).unwrap_or_default();
+    Ok(Status { repository: repo, pending_jobs, history })
+}
+
+#[get("/api/status?<repo>")]
+pub fn status_api(repo: Option<String>) -> Result<RawJson<String>, &'static str> {
+    let status = get_status(repo)?;
+    Ok(RawJson(serde_json::to_string_pretty(&status).unwrap()))
+}
+
+#[get("/status?<repo>")]
+pub fn status_page(repo: Option<String>) -> Result<RawHtml<String>, &'static str> {
+    let status = get_status(repo)?;
+    Ok(RawHtml(render_status_page(&status)))
+}
+
+fn render_status_page(status: &Status) -> String {
+    let mut html = String::new();
+    html += "<!DOCTYPE html><html><head><meta charset='utf-8'><title>Factorio mods localization - status</title>";
+    html += "<style>body { font-family: sans-serif; } table { border-collapse: collapse; } td, th { border: 1px solid #ccc; padding: 4px 8px; vertical-align: top; }</style>";
+    html += "</head><body>";
+    html += "<form action='/status'><input name='repo' placeholder='owner/repo'> <button>Show</button></form>";
+
+    match &status.repository {
+        Some(repository) => html += &format!("<h1>{}</h1>", escape_html(repository)),
+        None => html += "<h1>All repositories</h1>",
+    }
+
+    html += "<h2>Pending jobs</h2>";
+    if status.pending_jobs.is_empty() {
+        html += "<p>No pending jobs</p>";
+    } else {
+        html += "<table><tr><th>Job</th><th>Repositories</th><th>Failed attempts</th><th>Next attempt</th></tr>";
+        for job in &status.pending_jobs {
+            let repositories = job.repositories.as_ref()
+                .map_or_else(|| "all".to_owned(), |it| it.join(", "));
+            html += &format!(
+                "<tr><td>#{} {}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                job.id, job.kind, escape_html(&repositories), job.attempts, render_time(job.next_attempt_at),
+            );
+        }
+        html += "</table>";
+    }
+
+    if status.repository.is_some() {
+        html += "<h2>History</h2>";
+        if status.history.is_empty() {
+            html += "<p>No jobs yet</p>";
+        } else {
+            html += "<table><tr><th>Job</th><th>Started</th><th>Finished</th><th>Outcome</th><th>Changed files</th><th>Link</th><th>Errors</th><th>Warnings</th></tr>";
+            for entry in &status.history {
+                let finished_at = entry.finished_at.map(render_time).unwrap_or_default();
+                let outcome = serde_json::to_value(entry.outcome).unwrap();
+                let changed_files = entry.changed_files.iter()
+                    .map(|it| escape_html(it))
+                    .collect::<Vec<_>>()
+                    .join("<br>");
+                let link = entry.url.as_ref()
+                    .map(|url| format!("<a href='{0}'>{0}</a>", escape_html(url)))
+                    .unwrap_or_default();
+                let render_messages = |messages: &[String]| {
+                    messages.iter()
+                        .map(|it| format!("<pre>{}</pre>", escape_html(it)))
+                        .collect::<Vec<_>>()
+                        .join("")
+                };
+                html += &format!(
+                    "<tr><td>#{} {}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    entry.job_id, escape_html(&entry.kind), render_time(entry.started_at), finished_at,
+                    outcome.as_str().unwrap(), changed_files, link,
+                    render_messages(&entry.errors), render_messages(&entry.warnings),
+                );
+            }
+            html += "</table>";
+        }
+    }
+
+    // Show timestamps in local time of user
+    html += "<script>for (const time of document.querySelectorAll('[data-timestamp]')) time.textContent = new Date(time.dataset.timestamp * 1000).toLocaleString();</script>";
+    html += "</body></html>";
+    html
+}
+
+fn render_time(timestamp: u64) -> String {
+    format!("<span data-timestamp='{0}'>{0}</span>", timestamp)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_status_page_url() {
+        assert_eq!(get_status_page_url(None), "https://factorio-mods-localization.fly.dev/status");
+        assert_eq!(
+            get_status_page_url(Some("owner/repo")),
+            "https://factorio-mods-localization.fly.dev/status?repo=owner%2Frepo",
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<a href='x'>&</a>"), "&lt;a href=&#39;x&#39;&gt;&amp;&lt;/a&gt;");
+    }
+}
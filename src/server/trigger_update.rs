@@ -1,8 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::ops::Deref;
 use std::path::Path;
-use std::sync::LazyLock;
 use std::time::Duration;
 
 use log::info;
@@ -11,70 +9,56 @@ use rocket::get;
 use tempfile::TempDir;
 use tokio::time::sleep;
 
-use crate::{crowdin, git_util, github, util};
-use crate::crowdin::{get_crowdin_directory_name, normalize_language_code, replace_ini_to_cfg};
-use crate::github::{as_personal_account, get_repo_info};
-use crate::github_repo_info::{GithubModInfo, GithubRepoInfo};
-use crate::mod_directory::ModDirectory;
+use crate::{crowdin, git_util, github, jobs, util};
+use crate::crowdin::{directory_ids, normalize_language_code, replace_ini_to_cfg, TranslationsBuild};
+use crate::github::{get_repo_info, GITHUB_BRANCH_NAME};
+use crate::github_repo_info::{AdditionalFile, GithubModInfo, GithubRepoInfo};
+use crate::jobs::{history, JobKind};
+use crate::locale::consistency::{Mismatch, remove_mismatched_translations};
+use crate::locale::{info_json, LocaleFile};
+use crate::mod_directory::{LanguageCode, ModDirectory};
+use crate::sentry::{sentry_report_error, sentry_report_warning};
 use crate::server::check_secret;
-
-const TRIGGER_UPDATE_IGNORED_REPOSITORIES: &[&str] = &[
-    // TODO:
-    //  Large repository, causes OOM for some reason
-    //  https://github.com/dima74/factorio-mods-localization/issues/25
-    "robot256/cargo_ships",
-];
+use crate::server::status::get_status_page_url;
 
 #[get("/triggerUpdate?<repo>&<subpath>&<secret>")]
 pub async fn trigger_update(
     repo: Option<String>,
     subpath: Option<String>,
     secret: Option<String>,
-) -> &'static str {
-    if !check_secret(secret) { return "Missing secret"; }
+) -> String {
+    if !check_secret(secret) { return "Missing secret".to_owned(); }
     match repo {
         Some(repo) => {
-            trigger_update_single_repository_part1(repo, subpath).await
-                .unwrap_or_else(|it| it)
+            enqueue_update_single_repository(repo, subpath).await
+                .unwrap_or_else(ToOwned::to_owned)
         }
         None => {
-            let task = trigger_update_all_repositories();
-            tokio::spawn(task);
-            // TODO link to logs
-            "Triggered. See logs for details."
+            jobs::enqueue(JobKind::UpdateAllRepositories);
+            format!("Triggered. See {} for details.", get_status_page_url(None))
         }
     }
 }
 
-pub async fn get_trigger_update_mutex() -> impl Drop {
-    // Note that tokio Mutex doesn't poisoning in contrast to stdlib Mutex.
-    // This means that it will work correctly if thread panicked.
-    use tokio::sync::Mutex;
-    static MUTEX: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
-    MUTEX.lock().await
-}
-
-pub async fn trigger_update_single_repository_part1(
+/// Checks repository before enqueuing, so user gets error message immediately
+pub async fn enqueue_update_single_repository(
     full_name: String,
     subpath: Option<String>,
-) -> Result<&'static str, &'static str> {
-    info!("\n[update-github-from-crowdin] [{}] starting...", full_name);
-    let (installation_id, repo_info) = match get_installation_id_and_repo_info(&full_name, subpath).await {
-        Ok(value) => value,
-        Err(message) => return Err(message),
-    };
-    let repositories = vec![(repo_info, installation_id)];
-    let task = trigger_update_single_repository_part2(repositories, full_name);
-    tokio::spawn(task);
-    Ok("Triggered. See logs for details.")
+) -> Result<String, &'static str> {
+    get_installation_id_and_repo_info(&full_name, subpath.clone()).await?;
+    let status_page_url = get_status_page_url(Some(&full_name));
+    jobs::enqueue(JobKind::UpdateRepository { full_name, subpath });
+    Ok(format!(
+        "Triggered. Repository will be updated if there are changes on Crowdin. See {} for details.",
+        status_page_url,
+    ))
 }
 
-async fn trigger_update_single_repository_part2(
-    repositories: Vec<(GithubRepoInfo, InstallationId)>,
-    full_name: String,
-) {
-    let _lock = get_trigger_update_mutex().await;
-    push_crowdin_changes_to_repositories(repositories).await;
+pub async fn trigger_update_single_repository(repo_info: GithubRepoInfo, installation_id: InstallationId) {
+    let full_name = repo_info.full_name.clone();
+    info!("\n[update-github-from-crowdin] [{}] starting...", full_name);
+    let repositories = vec![(repo_info, installation_id)];
+    push_crowdin_changes_to_repositories(repositories, TranslationsBuild::Directories).await;
     info!("[update-github-from-crowdin] [{}] success", full_name);
 }
 
@@ -87,8 +71,7 @@ pub async fn get_installation_id_and_repo_info(
         None => return Err("Can't find installation for repository"),
     };
 
-    let api = github::as_installation(installation_id);
-    let mut repo_info = match get_repo_info(&api, full_name).await {
+    let mut repo_info = match get_repo_info(installation_id, full_name).await {
         Err(_) => return Err("No mods."),
         Ok(repo_info) => repo_info,
     };
@@ -101,13 +84,11 @@ pub async fn get_installation_id_and_repo_info(
     Ok((installation_id, repo_info))
 }
 
-async fn trigger_update_all_repositories() {
-    let _lock = get_trigger_update_mutex().await;
+pub async fn trigger_update_all_repositories() {
     info!("\n[update-github-from-crowdin] [*] starting...");
-    let api = github::as_app();
-    let repositories = github::get_all_repositories(&api).await;
+    let repositories = github::get_all_repositories().await;
     let repositories = filter_repositories_for_update_all(repositories);
-    push_crowdin_changes_to_repositories(repositories).await;
+    push_crowdin_changes_to_repositories(repositories, TranslationsBuild::Project).await;
     info!("[update-github-from-crowdin] [*] success");
 }
 
@@ -116,14 +97,6 @@ fn filter_repositories_for_update_all(
 ) -> Vec<(GithubRepoInfo, InstallationId)> {
     repositories
         .retain(|(repo_info, _)| {
-            if TRIGGER_UPDATE_IGNORED_REPOSITORIES.contains(&repo_info.full_name.deref()) {
-                info!(
-                    "[update-github-from-crowdin] [{}] skipping update (ignored)",
-                    repo_info.full_name
-                );
-                return false;
-            };
-
             let weekly_update_from_crowdin = repo_info.weekly_update_from_crowdin;
             if !weekly_update_from_crowdin {
                 info!(
@@ -136,41 +109,82 @@ fn filter_repositories_for_update_all(
     repositories
 }
 
-async fn push_crowdin_changes_to_repositories(repositories: Vec<(GithubRepoInfo, InstallationId)>) {
-    let repositories = crowdin::filter_repositories(repositories).await;
+async fn push_crowdin_changes_to_repositories(
+    repositories: Vec<(GithubRepoInfo, InstallationId)>,
+    build: TranslationsBuild,
+) {
+    let repositories_names = repositories.iter()
+        .map(|(repo_info, _)| repo_info.full_name.clone())
+        .collect::<Vec<_>>();
+    let repositories = match crowdin::filter_repositories(repositories).await {
+        Ok(repositories) => repositories,
+        Err(error) => {
+            let message = format!("[update-github-from-crowdin] can't list crowdin directories: {}", error);
+            report_error_for_repositories(&repositories_names, message);
+            return;
+        }
+    };
     if repositories.is_empty() { return; }
-    let translations_directory = crowdin::download_all_translations().await;
-    for (repo_info, installation_id) in repositories {
+    let mods = repositories.iter()
+        .flat_map(|(repo_info, _)| &repo_info.mods)
+        .collect::<Vec<_>>();
+    let translations_directory = match crowdin::download_translations_for_mods(&mods, build).await {
+        Ok(translations_directory) => translations_directory,
+        Err(error) => {
+            let message = format!("[update-github-from-crowdin] can't download translations: {}", error);
+            report_error_for_repositories(&repositories_names, message);
+            return;
+        }
+    };
+    for (index, (repo_info, installation_id)) in repositories.into_iter().enumerate() {
+        if index > 0 {
+            // TODO: https://github.com/dima74/factorio-mods-localization/issues/25
+            sleep(Duration::from_secs(30)).await;
+        }
         push_crowdin_changes_to_repository(repo_info, installation_id, &translations_directory).await;
-        // TODO: https://github.com/dima74/factorio-mods-localization/issues/25
-        sleep(Duration::from_secs(30)).await;
     }
 }
 
+fn report_error_for_repositories(repositories: &[String], message: String) {
+    for full_name in repositories {
+        history::add_error(full_name, message.clone());
+    }
+    sentry_report_error(&message);
+}
+
 async fn push_crowdin_changes_to_repository(
     repo_info: GithubRepoInfo,
     installation_id: InstallationId,
     translations_directory: &TempDir,
 ) {
     let full_name = &repo_info.full_name;
+    history::start(full_name);
     let repository_directory = github::clone_repository(&repo_info, installation_id).await;
     for mod_ in repo_info.mods {
         let mod_directory = ModDirectory::new(&repository_directory, mod_);
-        move_translated_files_to_mod_directory(&mod_directory, translations_directory.path()).await;
+        let source_language = mod_directory.mod_info.source_language.clone();
+        crowdin::with_source_language(&source_language, async {
+            let progress = get_translation_progress(&mod_directory.mod_info).await;
+            move_translated_files_to_mod_directory(&mod_directory, translations_directory.path(), &progress).await;
+        }).await;
     }
     let path = repository_directory.root.path();
-    let are_changes_exists = git_util::add_all_and_check_has_changes(path);
-    if are_changes_exists {
+    let changed_files = git_util::add_all_and_get_changed_files(path);
+    if !changed_files.is_empty() {
         info!("[update-github-from-crowdin] [{}] found changes", full_name);
+        for file in changed_files {
+            history::add_changed_file(full_name, file);
+        }
         git_util::commit(path);
-        let installation_api = github::as_installation(installation_id);
-        let default_branch = github::get_default_branch(&installation_api, full_name).await;
+        let default_branch = github::get_default_branch(installation_id, full_name).await;
         let base_branch = repo_info.branch.unwrap_or(default_branch);
-        let is_protected = github::is_branch_protected(&installation_api, full_name, &base_branch).await;
+        let is_protected = github::is_branch_protected(installation_id, full_name, &base_branch).await;
         if is_protected {
             push_changes_using_pull_request(path, full_name, &base_branch).await;
         } else {
             git_util::push(path);
+            let commit = git_util::get_head_commit(path);
+            history::set_url(full_name, format!("https://github.com/{}/commit/{}", full_name, commit));
             info!("[update-github-from-crowdin] [{}] pushed", full_name);
         }
     } else {
@@ -179,44 +193,158 @@ async fn push_crowdin_changes_to_repository(
 }
 
 async fn push_changes_using_pull_request(path: &Path, full_name: &str, base_branch: &str) {
-    let personal_api = as_personal_account();
-    if !github::fork_repository(&personal_api, full_name).await {
+    if !github::fork_repository(full_name).await {
         return;
     }
     let (_owner, repo) = full_name.split_once('/').unwrap();
-    let pushed = git_util::push_to_my_fork(path, repo);
+    let pushed = git_util::push_to_my_fork(path, &github::get_fork_push_url(repo));
     if pushed {
-        sleep(Duration::from_secs(30)).await;
-        github::create_pull_request(&personal_api, full_name, base_branch).await;
+        github::create_pull_request(full_name, base_branch).await;
+        let pulls_url = format!("https://github.com/{}/pulls?q=is%3Apr+head%3A{}", full_name, GITHUB_BRANCH_NAME);
+        history::set_url(full_name, pulls_url);
         info!("[update-github-from-crowdin] [{}] pushed to crowdin-fml branch and created PR", full_name);
     } else {
         info!("[update-github-from-crowdin] [{}] existing crowdin-fml branch has same content", full_name);
     }
 }
 
-async fn move_translated_files_to_mod_directory(mod_directory: &ModDirectory, translation_directory: &Path) {
+/// Empty if `min_translation_progress` is not specified, or if progress can't be requested,
+/// in which case new languages are not added to repository
+async fn get_translation_progress(mod_info: &GithubModInfo) -> HashMap<LanguageCode, u8> {
+    if mod_info.min_translation_progress.is_empty() { return HashMap::new(); }
+    match crowdin::get_translation_progress(mod_info).await {
+        Ok(progress) => progress,
+        Err(error) => {
+            let message = format!("[update-github-from-crowdin] [{}] can't get translation progress: {}", mod_info, error);
+            history::add_error(&mod_info.full_name(), message.clone());
+            sentry_report_error(&message);
+            HashMap::new()
+        }
+    }
+}
+
+async fn move_translated_files_to_mod_directory(
+    mod_directory: &ModDirectory,
+    translation_directory: &Path,
+    progress: &HashMap<LanguageCode, u8>,
+) {
     delete_unmatched_localization_files(mod_directory);
     for (language_path, language) in util::read_dir(translation_directory) {
-        if !language_is_enabled_for_mod_on_crowdin(&mod_directory.mod_info, &language) { continue; }
+        if !mod_directory.mod_info.languages.is_enabled(&language) { continue; }
+        if !has_enough_translation_progress(mod_directory, &language, progress) { continue; }
 
-        let language_path_crowdin = language_path.join(get_crowdin_directory_name(&mod_directory.mod_info));
-        assert!(language_path_crowdin.exists());
-        let files = util::read_dir(&language_path_crowdin).collect::<Vec<_>>();
+        // Translations directory contains languages of all Crowdin projects
+        let language_path_crowdin = language_path.join(directory_ids::get_name(&mod_directory.mod_info));
+        if !language_path_crowdin.exists() { continue; }
+        // Nested Crowdin directories correspond to subfolders of language folder
+        let files = util::get_directory_files_paths(&language_path_crowdin);
         if files.is_empty() { continue; }
 
         let language_original = util::read_dir(mod_directory.locale_path())
             .map(|(_path, name)| name)
             .find(|it| normalize_language_code(it) == language)
-            .unwrap_or(language);
+            .unwrap_or_else(|| language.clone());
         let language_path_repository = mod_directory.locale_path().join(language_original);
         fs::create_dir(&language_path_repository).ok();
-        for (old_path, name) in files {
-            assert!(name.ends_with(".ini"), "file {} from crowdin must ends with .ini`", name);
+        let mut mismatches = Vec::new();
+        for old_path in files {
+            let name = util::relative_path(&old_path, &language_path_crowdin);
+            let additional_file = mod_directory.mod_info.additional_files.iter()
+                .find(|it| it.crowdin_name() == name);
+            if let Some(additional_file) = additional_file {
+                move_additional_file_translation(mod_directory, additional_file, &old_path, &language);
+                continue;
+            }
+            if !name.ends_with(".ini") {
+                // E.g. additional file which was removed from config
+                let message = format!(
+                    "[update-github-from-crowdin] [{}] skipping unexpected file {} from crowdin",
+                    mod_directory.mod_info, name,
+                );
+                sentry_report_warning(&message);
+                continue;
+            }
             let file_renamed = replace_ini_to_cfg(&name);
             let new_path = language_path_repository.join(&file_renamed);
-            fs::rename(old_path, new_path).unwrap();
+            fs::create_dir_all(new_path.parent().unwrap()).unwrap();
+            let english_path = mod_directory.locale_source_path().join(&file_renamed);
+            for mismatch in move_translated_file(&old_path, &new_path, &english_path) {
+                mismatches.push(format!("{}: {}", file_renamed, mismatch));
+            }
+        }
+        report_mismatched_translations(&mod_directory.mod_info, &language, &mismatches);
+    }
+}
+
+/// Translations of `additional_files` are stored by path from config, e.g. `changelogs/de.txt`
+fn move_additional_file_translation(mod_directory: &ModDirectory, file: &AdditionalFile, old_path: &Path, language: &str) {
+    let Some(new_path) = mod_directory.get_additional_file_translation_path(file, language) else {
+        let message = format!(
+            "[update-github-from-crowdin] [{}] translation path of {} points outside of repository",
+            mod_directory.mod_info, file.source,
+        );
+        sentry_report_warning(&message);
+        return;
+    };
+    fs::create_dir_all(new_path.parent().unwrap()).unwrap();
+    fs::rename(old_path, new_path).unwrap();
+}
+
+/// Only new languages are checked. Languages already present in repository are always updated,
+/// so their translations are not frozen when progress drops because of added english strings
+fn has_enough_translation_progress(
+    mod_directory: &ModDirectory,
+    language: &LanguageCode,
+    progress: &HashMap<LanguageCode, u8>,
+) -> bool {
+    let min_progress = mod_directory.mod_info.min_translation_progress.get(language);
+    if min_progress == 0 { return true; }
+    let is_present_in_repository = util::read_dir(mod_directory.locale_path())
+        .any(|(_path, name)| normalize_language_code(&name) == *language);
+    if is_present_in_repository { return true; }
+    let progress = progress.get(language).copied().unwrap_or(0);
+    if progress < min_progress {
+        info!(
+            "[update-github-from-crowdin] [{}] skip language {} with translation progress {}% < {}%",
+            mod_directory.mod_info, language, progress, min_progress,
+        );
+        return false;
+    }
+    true
+}
+
+/// Translations with placeholders/tags not matching english file are not moved.
+/// If no translations are left, existing file is deleted, so it doesn't keep outdated translations
+fn move_translated_file(old_path: &Path, new_path: &Path, english_path: &Path) -> Vec<Mismatch> {
+    if !english_path.exists() {
+        fs::rename(old_path, new_path).unwrap();
+        return vec![];
+    }
+    let english = String::from_utf8_lossy(&fs::read(english_path).unwrap()).into_owned();
+    let english = LocaleFile::parse(&english);
+    let translation = fs::read_to_string(old_path).unwrap();
+    let mut translation = LocaleFile::parse(&translation);
+    let mismatches = remove_mismatched_translations(&english, &mut translation);
+    if translation.is_empty() {
+        if new_path.exists() {
+            fs::remove_file(new_path).unwrap();
         }
+    } else {
+        fs::write(new_path, translation.to_string()).unwrap();
     }
+    mismatches
+}
+
+fn report_mismatched_translations(mod_info: &GithubModInfo, language: &str, mismatches: &[String]) {
+    if mismatches.is_empty() { return; }
+    let message = format!(
+        "[update-github-from-crowdin] [{}] [{}] skipped {} translations with mismatched placeholders or rich text tags:\n{}",
+        mod_info,
+        language,
+        mismatches.len(),
+        mismatches.iter().map(|it| format!("    {}", it)).collect::<Vec<_>>().join("\n"),
+    );
+    sentry_report_warning(&message);
 }
 
 /// Consider:
@@ -227,25 +355,19 @@ async fn move_translated_files_to_mod_directory(mod_directory: &ModDirectory, tr
 /// locale/en: ["locale1.cfg"]
 /// locale/ru: ["locale1.cfg"]
 fn delete_unmatched_localization_files(mod_directory: &ModDirectory) {
-    let english_files = mod_directory.get_english_files()
+    let mut english_files = mod_directory.get_english_files()
         .into_iter()
-        .map(|it| util::file_name(&it).to_owned())
+        .map(|it| mod_directory.get_file_name(&it))
         .collect::<HashSet<_>>();
+    if mod_directory.get_info_json_english_file().is_some() {
+        english_files.insert(info_json::FILE_NAME.to_owned());
+    }
     for (_, localized_files) in mod_directory.get_localizations() {
         for localized_file in localized_files {
-            let name = util::file_name(&localized_file);
-            if !english_files.contains(name) {
+            let name = mod_directory.get_file_name(&localized_file);
+            if !english_files.contains(&name) {
                 fs::remove_file(&localized_file).unwrap();
             }
         }
     }
 }
-
-fn language_is_enabled_for_mod_on_crowdin(mod_info: &GithubModInfo, language: &str) -> bool {
-    // This is a hack for this specific use case. See #19
-    if mod_info.owner == "PennyJim" && mod_info.repo == "pirate-locale" {
-        return language == "fr";
-    }
-
-    true
-}
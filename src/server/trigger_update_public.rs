@@ -10,7 +10,8 @@ use url::Url;
 
 use crate::github;
 use crate::myenv::GITHUB_OAUTH_CLIENT_ID;
-use crate::server::trigger_update::trigger_update_single_repository_part1;
+use crate::server::SERVER_URL;
+use crate::server::trigger_update::enqueue_update_single_repository;
 
 #[get("/api/triggerUpdate?<repo>")]
 pub async fn trigger_update(repo: Option<String>) -> Result<Redirect, &'static str> {
@@ -23,7 +24,7 @@ pub async fn trigger_update(repo: Option<String>) -> Result<Redirect, &'static s
     }
     let owner = repo.split_once('/').unwrap().0;
 
-    let mut redirect_url = Url::parse("https://factorio-mods-localization.fly.dev/api/triggerUpdate2").unwrap();
+    let mut redirect_url = Url::parse(&format!("{}/api/triggerUpdate2", SERVER_URL)).unwrap();
     redirect_url.query_pairs_mut()
         .append_pair("repo", &repo);
 
@@ -47,8 +48,8 @@ pub async fn trigger_update2(repo: String, code: String) -> String {
         return format!("Authentication failed, expected `{}` user, found `{}`", owner, authenticated_user);
     }
 
-    match trigger_update_single_repository_part1(repo, None).await {
-        Ok(_) => "Triggered. Repository will be updated if there are changes on Crowdin.".to_owned(),
+    match enqueue_update_single_repository(repo, None).await {
+        Ok(message) => message,
         Err(e) => e.to_owned(),
     }
 }
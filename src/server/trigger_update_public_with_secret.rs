@@ -7,7 +7,7 @@ use std::ops::Deref;
 use rocket::get;
 
 use crate::myenv::WEBSERVER_SECRET_PUBLIC;
-use crate::server::trigger_update::trigger_update_single_repository_part1;
+use crate::server::trigger_update::enqueue_update_single_repository;
 
 #[get("/api/triggerUpdateWithSecret?<repo>&<secret>")]
 pub async fn trigger_update(repo: Option<String>, secret: Option<String>) -> String {
@@ -33,8 +33,8 @@ pub async fn trigger_update(repo: Option<String>, secret: Option<String>) -> Str
         return "Invalid secret".to_owned();
     }
 
-    match trigger_update_single_repository_part1(repo, None).await {
-        Ok(_) => "Triggered. Repository will be updated if there are changes on Crowdin.".to_owned(),
+    match enqueue_update_single_repository(repo, None).await {
+        Ok(message) => message,
         Err(e) => e.to_owned(),
     }
 }
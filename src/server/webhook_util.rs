@@ -6,11 +6,22 @@ use octocrab::models::webhook_events::WebhookEvent;
 use rocket::{Data, Request};
 use rocket::data::{FromData, Outcome, ToByteUnit};
 use rocket::http::Status;
+use serde::Deserialize;
 use sha2::Sha256;
 
 use crate::myenv::GITHUB_APP_WEBHOOKS_SECRET;
 
-pub struct GithubEvent(pub WebhookEvent);
+pub struct GithubEvent {
+    pub event: WebhookEvent,
+    /// `changes` field of payload, octocrab doesn't keep it for some events (e.g. repository renamed)
+    pub changes: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct PayloadChanges {
+    #[serde(default)]
+    changes: serde_json::Value,
+}
 
 #[rocket::async_trait]
 impl<'r> FromData<'r> for GithubEvent {
@@ -51,7 +62,8 @@ impl GithubEvent {
         verify_signature(&signature, &content)?;
 
         let event = WebhookEvent::try_from_header_and_body(event_type, &content)?;
-        Ok(GithubEvent(event))
+        let changes = serde_json::from_slice::<PayloadChanges>(&content)?.changes;
+        Ok(GithubEvent { event, changes })
     }
 }
 
@@ -0,0 +1,168 @@
+//! Cleanup of Crowdin directories of repositories for which GitHub app was uninstalled.
+//! Config of such repositories is not available anymore,
+//! so directories are found by ids recorded in [crowdin::directory_ids].
+//! Name matching (see [crowdin::is_crowdin_directory_of_repository]) is used only
+//! for repositories without recorded ids, e.g. installed before mapping existed.
+//!
+//! Behaviour is configured by `UNINSTALLED_REPOSITORIES_POLICY` env:
+//! - `keep` - directories are left as is
+//! - `archive` (default) - directories are moved to `_archived`, so they are hidden from translators.
+//!   When repository is installed again, directories are restored together with translations
+//! - `delete` - same as `archive`, and after grace period (`UNINSTALLED_REPOSITORIES_GRACE_PERIOD_DAYS`)
+//!   archived directories are deleted, unless repository was installed again
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use log::info;
+use serde::Serialize;
+
+use crate::{crowdin, github, jobs};
+use crate::crowdin::{directory_ids, get_crowdin_directory_name, is_crowdin_directory_of_repository, CrowdinError, DirectoryId, CrowdinResult, ARCHIVED_DIRECTORY_NAME, ARCHIVED_FILES_DIRECTORY_NAME};
+use crate::jobs::{history, JobKind};
+use crate::myenv::{UNINSTALLED_REPOSITORIES_GRACE_PERIOD_DAYS, UNINSTALLED_REPOSITORIES_POLICY};
+use crate::sentry::sentry_report_error;
+
+const DEFAULT_GRACE_PERIOD_DAYS: u64 = 30;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UninstalledRepositoriesPolicy {
+    Keep,
+    Archive,
+    Delete,
+}
+
+pub fn get_policy() -> UninstalledRepositoriesPolicy {
+    match UNINSTALLED_REPOSITORIES_POLICY.as_deref() {
+        None | Some("archive") => UninstalledRepositoriesPolicy::Archive,
+        Some("keep") => UninstalledRepositoriesPolicy::Keep,
+        Some("delete") => UninstalledRepositoriesPolicy::Delete,
+        Some(value) => {
+            sentry_report_error(&format!("Unknown UNINSTALLED_REPOSITORIES_POLICY `{}`, keeping directories", value));
+            UninstalledRepositoriesPolicy::Keep
+        }
+    }
+}
+
+fn get_grace_period() -> Duration {
+    let days = UNINSTALLED_REPOSITORIES_GRACE_PERIOD_DAYS.as_deref()
+        .and_then(|it| it.parse().ok())
+        .unwrap_or(DEFAULT_GRACE_PERIOD_DAYS);
+    Duration::from_secs(days * 24 * 60 * 60)
+}
+
+pub async fn on_repositories_removed(repositories: Vec<String>) {
+    let policy = get_policy();
+    if policy == UninstalledRepositoriesPolicy::Keep { return; }
+    for full_name in repositories {
+        // Webhook could be processed after repository was installed again
+        if github::get_installation_id_for_repo(&full_name).await.is_some() {
+            info!("[uninstall] [{}] repository is installed again, skipping", full_name);
+            continue;
+        }
+        if let Err(error) = archive_repository(&full_name).await {
+            report_crowdin_error(&full_name, &error);
+            continue;
+        }
+        if policy == UninstalledRepositoriesPolicy::Delete {
+            jobs::enqueue_delayed(JobKind::DeleteRepository { full_name }, get_grace_period());
+        }
+    }
+}
+
+/// Source language of repository is unknown, so directories are searched in all Crowdin projects
+async fn archive_repository(full_name: &str) -> CrowdinResult<()> {
+    let is_directory_of_repository = directories_of_repository_filter(full_name);
+    for source_language in crowdin::source_languages() {
+        crowdin::with_source_language(&source_language, async {
+            let directories = crowdin::list_directories().await?
+                .filter(&is_directory_of_repository)
+                .collect::<Vec<_>>();
+            for (name, directory_id) in directories {
+                info!("[uninstall] [{}] archive crowdin directory {}", full_name, name);
+                crowdin::archive_directory(directory_id, &name).await?;
+                history::add_changed_file(full_name, format!("{} (archived on Crowdin)", name));
+            }
+            Ok(())
+        }).await?;
+    }
+    Ok(())
+}
+
+/// Ids are read once, because mapping is updated while directories are archived or deleted
+fn directories_of_repository_filter(full_name: &str) -> impl Fn(&(String, DirectoryId)) -> bool + '_ {
+    let ids = directory_ids::get_repository_ids(full_name);
+    move |(name, id)| {
+        if ids.is_empty() {
+            is_crowdin_directory_of_repository(name, full_name)
+        } else {
+            ids.contains(id)
+        }
+    }
+}
+
+pub async fn delete_archived_repository(full_name: &str) {
+    if github::get_installation_id_for_repo(full_name).await.is_some() {
+        info!("[uninstall] [{}] repository is installed again, skipping deletion", full_name);
+        return;
+    }
+    let is_directory_of_repository = directories_of_repository_filter(full_name);
+    let result = async {
+        for source_language in crowdin::source_languages() {
+            crowdin::with_source_language(&source_language, async {
+                let directories = crowdin::list_archived_directories().await?
+                    .into_iter()
+                    .filter(&is_directory_of_repository);
+                for (name, directory_id) in directories {
+                    info!("[uninstall] [{}] delete archived crowdin directory {}", full_name, name);
+                    crowdin::delete_directory(directory_id).await?;
+                    history::add_changed_file(full_name, format!("{} (deleted on Crowdin)", name));
+                }
+                Ok(())
+            }).await?;
+        }
+        Ok(())
+    }.await;
+    if let Err(error) = result {
+        report_crowdin_error(full_name, &error);
+    }
+}
+
+fn report_crowdin_error(full_name: &str, error: &crowdin::CrowdinError) {
+    let message = format!("[uninstall] [{}] crowdin error: {}", full_name, error);
+    history::add_error(full_name, message.clone());
+    sentry_report_error(&message);
+}
+
+#[derive(Serialize)]
+pub struct OrphanedDirectories {
+    /// Root directories which don't correspond to any mod of installed repositories.
+    /// Note that repositories with invalid config are not considered installed
+    pub orphaned: Vec<String>,
+    /// Directories hidden in `_archived`
+    pub archived: Vec<String>,
+}
+
+pub async fn get_orphaned_directories() -> CrowdinResult<OrphanedDirectories> {
+    let installed = github::get_all_repositories().await
+        .iter()
+        .flat_map(|(repo_info, _)| repo_info.mods.iter().map(get_crowdin_directory_name))
+        .collect::<HashSet<_>>();
+    let mut orphaned = Vec::new();
+    let mut archived = Vec::new();
+    for source_language in crowdin::source_languages() {
+        crowdin::with_source_language(&source_language, async {
+            let directories = crowdin::list_directories().await?
+                .map(|(name, _id)| name)
+                .filter(|name| name != ARCHIVED_DIRECTORY_NAME && name != ARCHIVED_FILES_DIRECTORY_NAME)
+                .filter(|name| !installed.contains(name));
+            orphaned.extend(directories);
+            let directories = crowdin::list_archived_directories().await?
+                .into_iter()
+                .map(|(name, _id)| name);
+            archived.extend(directories);
+            Ok::<_, CrowdinError>(())
+        }).await?;
+    }
+    Ok(OrphanedDirectories { orphaned, archived })
+}
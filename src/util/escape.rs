@@ -1,6 +1,4 @@
-use std::sync::LazyLock;
-
-use regex::{Captures, Regex};
+use crate::locale::LocaleFile;
 
 /// .ini file looks like this:
 /// ```ini
@@ -12,18 +10,27 @@ use regex::{Captures, Regex};
 /// - semicolon is used for commenting, so by default everything after semicolon is ignored
 /// - quotes are used for escaping, so by default something strange happens
 pub fn escape_strings_in_ini_file(ini_content: &str) -> String {
-    static REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?mR)^([^\[=\n]*)=([^\n]*)$").unwrap());
-    REGEX.replace_all(ini_content, |captures: &Captures| {
-        let key = &captures[1];
-        let value = &captures[2];
-        let should_escape = value.contains('"') || value.contains(';');
-        let already_escaped = value.starts_with('"') && value.ends_with('"');
-        if should_escape && !already_escaped {
-            format!("{}=\"{}\"", key, value)
-        } else {
-            captures[0].to_owned()
+    let mut file = LocaleFile::parse(ini_content);
+    escape_strings_in_locale_file(&mut file);
+    file.to_string()
+}
+
+pub fn escape_strings_in_locale_file(file: &mut LocaleFile) {
+    for value in file.values_mut() {
+        if let Some(escaped) = escape_value(value) {
+            *value = escaped;
         }
-    }).into_owned()
+    }
+}
+
+fn escape_value(value: &str) -> Option<String> {
+    let should_escape = value.contains('"') || value.contains(';');
+    let already_escaped = value.starts_with('"') && value.ends_with('"');
+    if should_escape && !already_escaped {
+        Some(format!("\"{}\"", value))
+    } else {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -74,6 +81,13 @@ key2="v;a"l;u"e;3"
         "#);
     }
 
+    #[test]
+    fn test_escape_comments_and_bom() {
+        test("; comment=foo;bar\nkey=foo;bar", "; comment=foo;bar\nkey=\"foo;bar\"");
+        test("[section] ; comment\nkey=foo;bar", "[section] ; comment\nkey=\"foo;bar\"");
+        test("\u{FEFF}key=foo;bar", "\u{FEFF}key=\"foo;bar\"");
+    }
+
     #[test]
     fn test_escape_crlf() {
         test(
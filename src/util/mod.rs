@@ -4,6 +4,10 @@ use std::path::{Path, PathBuf};
 
 use tempfile::TempDir;
 
+use crate::crowdin::CrowdinError;
+use crate::crowdin::http::{check_status, CrowdinResult};
+use crate::locale::LocaleFile;
+
 pub mod case;
 pub mod escape;
 
@@ -20,31 +24,73 @@ pub fn file_name(path: &Path) -> &str {
     path.file_name().unwrap().to_str().unwrap()
 }
 
+/// Files of directory and its subdirectories.
+/// Symlinked directories are skipped, so symlink loop in cloned repository can't cause infinite recursion
+pub fn get_directory_files_paths(path: &Path) -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    for (path, _name) in read_dir(path) {
+        if path.is_dir() {
+            if !path.is_symlink() {
+                result.extend(get_directory_files_paths(&path));
+            }
+        } else if path.is_file() {
+            result.push(path);
+        }
+    }
+    result
+}
+
+/// `.cfg` files of directory and its subdirectories, e.g. `locale/en/subfolder/locale.cfg`
 pub fn get_directory_cfg_files_paths(path: &Path) -> Vec<PathBuf> {
-    read_dir(path)
-        .filter(|(path, name)| path.is_file() && name.ends_with(".cfg"))
-        .map(|(path, _name)| path)
+    get_directory_files_paths(path)
+        .into_iter()
+        .filter(|path| file_name(path).ends_with(".cfg"))
         .collect()
 }
 
-pub async fn download_and_extract_zip_file(url: &str) -> TempDir {
+/// `root/subfolder/locale.cfg` => `subfolder/locale.cfg`
+pub fn relative_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap().to_str().unwrap().to_owned()
+}
+
+/// Extracts only entries for which `include` returns true (path is relative to archive root)
+pub async fn download_and_extract_zip_file(url: &str, include: impl Fn(&Path) -> bool) -> CrowdinResult<TempDir> {
     use zip::ZipArchive;
 
-    let file = download_file(url).await;
-    let mut zip = ZipArchive::new(file).unwrap();
+    let to_archive_error = |source| CrowdinError::InvalidArchive { url: url.to_owned(), source };
+    let file = download_file(url).await?;
+    let mut zip = ZipArchive::new(file).map_err(to_archive_error)?;
     let directory = create_temporary_directory();
-    zip.extract(&directory).unwrap();
-    directory
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(to_archive_error)?;
+        let Some(relative_path) = entry.enclosed_name() else { continue; };
+        if !include(relative_path) { continue; }
+        let path = directory.path().join(relative_path);
+        if entry.is_dir() {
+            fs::create_dir_all(&path).unwrap();
+        } else {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let mut file = File::create(&path).unwrap();
+            std::io::copy(&mut entry, &mut file).map_err(|it| to_archive_error(it.into()))?;
+        }
+    }
+    Ok(directory)
 }
 
-pub async fn download_file(url: &str) -> File {
+/// Response is written to disk chunk by chunk, so large files are not kept in memory
+pub async fn download_file(url: &str) -> CrowdinResult<File> {
+    use tokio::io::AsyncWriteExt;
+
+    let to_request_error = |source| CrowdinError::Request { url: url.to_owned(), source };
     let file = create_temporary_file();
     let mut file = tokio::fs::File::from_std(file);
-    let response = reqwest::get(url)
-        .await.unwrap()
-        .bytes().await.unwrap();
-    tokio::io::copy(&mut &*response, &mut file).await.unwrap();
-    file.into_std().await
+    let response = reqwest::get(url).await.map_err(to_request_error)?;
+    let mut response = check_status(url, response).await?;
+    while let Some(chunk) = response.chunk().await.map_err(to_request_error)? {
+        file.write_all(&chunk).await.unwrap();
+    }
+    file.flush().await.unwrap();
+    Ok(file.into_std().await)
 }
 
 // See [create_temporary_directory]
@@ -58,27 +104,23 @@ pub fn create_temporary_directory() -> TempDir {
     TempDir::with_prefix_in("FML.", "./").unwrap()
 }
 
-pub fn remove_empty_ini_files(root: &Path) {
-    // `ru/Factorio Mod Example (dima74)/test.ini`
-    for (language_path, _) in read_dir(root) {
-        for (repository_path, _) in read_dir(&language_path) {
-            for (file_path, _) in read_dir(&repository_path) {
-                let content = fs::read_to_string(&file_path).unwrap();
-                if is_empty_ini_file(content) {
-                    fs::remove_file(file_path).unwrap();
-                }
-            }
+/// Removes translated files without strings, so untranslated files are not added to repository.
+/// Files which are not `.ini` (see [crate::crowdin::file_type]) are removed if they are blank
+pub fn remove_empty_translation_files(root: &Path) {
+    // `ru/Factorio Mod Example (dima74)/test.ini` or `ru/Factorio Mod Example (dima74)/subfolder/test.ini`
+    for file_path in get_directory_files_paths(root) {
+        let content = String::from_utf8_lossy(&fs::read(&file_path).unwrap()).into_owned();
+        let is_empty = if file_name(&file_path).ends_with(".ini") {
+            LocaleFile::parse(&content).is_empty()
+        } else {
+            content.trim().is_empty()
+        };
+        if is_empty {
+            fs::remove_file(file_path).unwrap();
         }
     }
 }
 
-fn is_empty_ini_file(content: String) -> bool {
-    content.lines().all(|line| {
-        let line = line.trim();
-        line.is_empty() || line.starts_with('[') || line.starts_with(';')
-    })
-}
-
 #[derive(Debug)]
 pub struct EmptyBody;
 
@@ -1,36 +1,55 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ops::Deref;
 
 use InstallationWebhookEventAction::{Created, Deleted};
 use log::info;
 use octocrab::models::InstallationId;
-use octocrab::models::webhook_events::{EventInstallation, InstallationEventRepository, WebhookEvent, WebhookEventPayload};
-use octocrab::models::webhook_events::payload::{InstallationWebhookEventAction, PushWebhookEventPayload};
-use WebhookEventPayload::{Installation, InstallationRepositories, Push};
+use octocrab::models::webhook_events::{EventInstallation, WebhookEvent, WebhookEventPayload};
+use octocrab::models::webhook_events::payload::{InstallationTargetWebhookEventPayload, InstallationWebhookEventAction, PushWebhookEventPayload, RepositoryWebhookEventAction};
+use WebhookEventPayload::{Installation, InstallationRepositories, InstallationTarget, Push, Repository};
 
-use crate::crowdin::CrowdinDirectory;
-use crate::github;
-use crate::github::GITHUB_CONFIG_FILE_NAME;
-use crate::github_repo_info::GithubRepoInfo;
-use crate::mod_directory::ModDirectory;
+use crate::crowdin::{directory_ids, get_renamed_owner_directory_name, get_renamed_repository_directory_name, CrowdinDirectory, CrowdinError, CrowdinResult};
+use crate::{crowdin, github, jobs};
+use crate::github::{GetRepoInfoError, GITHUB_CONFIG_FILE_NAMES};
+use crate::github_config::ConfigError;
+use crate::github_repo_info::{GithubModInfo, GithubRepoInfo};
+use crate::jobs::{history, JobKind};
+use crate::mod_directory::{ModDirectory, RepositoryDirectory};
+use crate::sentry::{sentry_report_error, sentry_report_warning};
 
-pub async fn webhook_impl(event: WebhookEvent) {
+/// Only enqueues jobs, actual work is done by [jobs].
+/// `changes` is raw `changes` field of payload, needed for repository renamed event.
+pub fn webhook_impl(event: WebhookEvent, changes: serde_json::Value) {
     match event.specific {
         Installation(_) | InstallationRepositories(_) => {
-            handle_installation_event(event).await;
+            handle_installation_event(event);
         }
         Push(payload) => {
             let EventInstallation::Minimal(installation) = event.installation.as_ref().unwrap() else {
                 panic!("Unexpected installation data");
             };
             let full_name = event.repository.unwrap().full_name.unwrap();
-            on_push_event(&payload, installation.id, full_name).await;
+            jobs::enqueue(JobKind::Push { installation_id: installation.id, full_name, payload });
+        }
+        InstallationTarget(payload) => {
+            handle_installation_target_event(&payload);
+        }
+        Repository(payload) => {
+            let repository = event.repository.unwrap();
+            if repository.private == Some(true) { return; }
+            let new_full_name = repository.full_name.unwrap();
+            let Some(old_full_name) = get_old_full_name(&payload.action, &new_full_name, &changes) else {
+                info!("[repository-webhook] [{}] ignoring action {:?}", new_full_name, payload.action);
+                return;
+            };
+            info!("[repository-webhook] [{}] renamed to {}", old_full_name, new_full_name);
+            jobs::enqueue(JobKind::RenameRepository { old_full_name, new_full_name });
         }
-        // TODO InstallationTarget event (user changed login)
         _ => info!("[webhook] unknown event: {:?}", event.kind),
     };
 }
 
-async fn handle_installation_event(event: WebhookEvent) {
+fn handle_installation_event(event: WebhookEvent) {
     let user = event.sender.unwrap().login;
     let EventInstallation::Full(installation) = event.installation.as_ref().unwrap() else {
         panic!("Unexpected installation data");
@@ -43,6 +62,13 @@ async fn handle_installation_event(event: WebhookEvent) {
                 }
                 Deleted => {
                     info!("[email] app uninstalled for user {}", user);
+                    let repositories = payload.repositories.unwrap_or_default()
+                        .into_iter()
+                        .map(|it| it.full_name)
+                        .collect::<Vec<_>>();
+                    if !repositories.is_empty() {
+                        jobs::enqueue(JobKind::Uninstall { repositories });
+                    }
                     return;
                 }
                 _ => {
@@ -52,30 +78,107 @@ async fn handle_installation_event(event: WebhookEvent) {
             }
         }
         InstallationRepositories(payload) => {
-            for repository_removed in payload.repositories_removed {
-                info!("[email] app uninstalled for repository {}", repository_removed.full_name);
+            let repositories_removed = payload.repositories_removed
+                .into_iter()
+                .map(|it| it.full_name)
+                .collect::<Vec<_>>();
+            for repository_removed in &repositories_removed {
+                info!("[email] app uninstalled for repository {}", repository_removed);
+            }
+            if !repositories_removed.is_empty() {
+                jobs::enqueue(JobKind::Uninstall { repositories: repositories_removed });
             }
             payload.repositories_added
         }
         _ => panic!("Unexpected event type"),
     };
 
-    info!("\n[installation-webhook] [{}] starting for {} repositories...", user, repositories.len());
-    let installation_id = installation.id;
-    on_repositories_added(repositories, installation_id).await;
-    info!("[installation-webhook] [{}] success", user);
-}
-
-async fn on_repositories_added(repositories: Vec<InstallationEventRepository>, installation_id: InstallationId) {
     let repositories = repositories
         .into_iter()
         .filter(|it| !it.private)
         .map(|it| it.full_name)
         .collect::<Vec<_>>();
-    let api = github::as_installation(installation_id);
+    info!("\n[installation-webhook] [{}] enqueued {} repositories", user, repositories.len());
+    jobs::enqueue(JobKind::Install { installation_id: installation.id, repositories });
+}
+
+/// User or organization changed login
+fn handle_installation_target_event(payload: &InstallationTargetWebhookEventPayload) {
+    if payload.action != "renamed" {
+        info!("[installation-target-webhook] unknown action: {}", payload.action);
+        return;
+    }
+    let old_owner = payload.changes["login"]["from"].as_str();
+    let new_owner = payload.account["login"].as_str();
+    let (Some(old_owner), Some(new_owner)) = (old_owner, new_owner) else {
+        sentry_report_error(&format!("[installation-target-webhook] unexpected payload: {:?}", payload));
+        return;
+    };
+    info!("[installation-target-webhook] [{}] renamed to {}", old_owner, new_owner);
+    jobs::enqueue(JobKind::RenameOwner { old_owner: old_owner.to_owned(), new_owner: new_owner.to_owned() });
+}
+
+/// For renamed or transferred repository, `changes` contains old name or old owner
+fn get_old_full_name(action: &RepositoryWebhookEventAction, full_name: &str, changes: &serde_json::Value) -> Option<String> {
+    let (owner, repo) = full_name.split_once('/')?;
+    match action {
+        RepositoryWebhookEventAction::Renamed => {
+            let old_repo = changes["repository"]["name"]["from"].as_str()?;
+            Some(format!("{}/{}", owner, old_repo))
+        }
+        RepositoryWebhookEventAction::Transferred => {
+            let from = &changes["owner"]["from"];
+            let old_owner = from["user"]["login"].as_str()
+                .or_else(|| from["organization"]["login"].as_str())?;
+            Some(format!("{}/{}", old_owner, repo))
+        }
+        _ => None,
+    }
+}
+
+/// Crowdin directories are renamed, so translations are kept.
+/// Directories are found by mapped ids, by name only if repository has no mapping yet
+pub async fn on_repository_renamed(old_full_name: &str, new_full_name: &str) {
+    let ids = directory_ids::get_repository_ids(old_full_name);
+    directory_ids::rename_repository(old_full_name, new_full_name);
+    let result = crowdin::rename_directories(|name, id| {
+        if !ids.is_empty() && !ids.contains(&id) { return None; }
+        get_renamed_repository_directory_name(name, old_full_name, new_full_name)
+    }).await;
+    match result {
+        Ok(renamed) => {
+            for (old_name, new_name) in renamed {
+                history::add_changed_file(new_full_name, format!("{} => {} (renamed on Crowdin)", old_name, new_name));
+            }
+            info!("[rename-repository] [{}] renamed to {}", old_full_name, new_full_name);
+        }
+        Err(error) => report_crowdin_error("rename-repository", new_full_name, old_full_name, &error),
+    }
+}
+
+/// Same as [on_repository_renamed], for all repositories of owner
+pub async fn on_owner_renamed(old_owner: &str, new_owner: &str) {
+    let ids = directory_ids::get_owner_ids(old_owner);
+    directory_ids::rename_owner(old_owner, new_owner);
+    let result = crowdin::rename_directories(|name, id| {
+        if !ids.is_empty() && !ids.contains(&id) { return None; }
+        get_renamed_owner_directory_name(name, old_owner, new_owner)
+    }).await;
+    match result {
+        Ok(renamed) => info!("[rename-owner] [{}] renamed to {}, {} crowdin directories renamed", old_owner, new_owner, renamed.len()),
+        Err(error) => sentry_report_error(&format!("[rename-owner] [{}] crowdin error: {}", old_owner, error)),
+    }
+}
+
+pub async fn on_repositories_added(repositories: Vec<String>, installation_id: InstallationId) {
     for repository in repositories {
-        let Ok(repo_info) = github::get_repo_info(&api, &repository).await else {
-            continue;
+        let repo_info = match github::get_repo_info(installation_id, &repository).await {
+            Ok(repo_info) => repo_info,
+            Err(GetRepoInfoError::InvalidConfig(error)) => {
+                report_invalid_config("add-repository", &repository, &error);
+                continue;
+            }
+            Err(_) => continue,
         };
         on_repository_added(repo_info, installation_id).await;
         star_and_fork_repository(&repository).await;
@@ -85,28 +188,73 @@ async fn on_repositories_added(repositories: Vec<InstallationEventRepository>, i
 pub async fn on_repository_added(repo_info: GithubRepoInfo, installation_id: InstallationId) {
     info!("[email] app installed for repository {}", repo_info.full_name);
     let repository_directory = github::clone_repository(&repo_info, installation_id).await;
-    for mod_ in repo_info.mods {
-        let mod_directory = ModDirectory::new(&repository_directory, mod_);
-        if !mod_directory.check_structure() { continue; }
+    add_repository_mods_to_crowdin(&repository_directory, repo_info.mods).await;
+    info!("[add-repository] [{}] success", repo_info.full_name);
+}
 
-        let (crowdin_directory, _) = CrowdinDirectory::get_or_create(mod_directory).await;
-        crowdin_directory.add_english_and_localization_files().await;
+/// Uploads english files and existing translations of cloned repository
+pub async fn add_repository_mods_to_crowdin(repository_directory: &RepositoryDirectory, mods: Vec<GithubModInfo>) {
+    for mod_ in mods {
+        if !check_source_language("add-repository", &mod_) { continue; }
+        let source_language = mod_.source_language.clone();
+        let mod_directory = ModDirectory::new(repository_directory, mod_);
+        crowdin::with_source_language(&source_language, add_mod_to_crowdin(mod_directory)).await;
+    }
+}
+
+async fn add_mod_to_crowdin(mod_directory: ModDirectory) {
+    if !mod_directory.check_structure() { return; }
+
+    let full_name = mod_directory.mod_info.full_name();
+    let mod_name = mod_directory.mod_info.to_string();
+    let result = async {
+        let (crowdin_directory, _) = CrowdinDirectory::get_or_create(mod_directory).await?;
+        crowdin_directory.add_english_and_localization_files().await
+    }.await;
+    if let Err(error) = result {
+        report_crowdin_error("add-repository", &full_name, &mod_name, &error);
     }
-    info!("[add-repository] [{}] success", repo_info.full_name);
 }
 
 pub async fn import_english(repo_info: GithubRepoInfo, installation_id: InstallationId) {
     let repository_directory = github::clone_repository(&repo_info, installation_id).await;
     for mod_ in repo_info.mods {
+        if !check_source_language("import-english", &mod_) { continue; }
+        let source_language = mod_.source_language.clone();
         let mod_directory = ModDirectory::new(&repository_directory, mod_);
-        if !mod_directory.check_for_locale_folder() { continue; }
+        crowdin::with_source_language(&source_language, import_mod_english(mod_directory)).await;
+    }
+}
+
+async fn import_mod_english(mod_directory: ModDirectory) {
+    if !mod_directory.check_for_locale_folder() { return; }
+    if !mod_directory.check_english_files() { return; }
 
-        if !CrowdinDirectory::has_existing(&mod_directory).await { continue; }
-        let (crowdin_directory, _) = CrowdinDirectory::get_or_create(mod_directory).await;
-        crowdin_directory.add_english_files().await;
+    let full_name = mod_directory.mod_info.full_name();
+    let mod_name = mod_directory.mod_info.to_string();
+    let result = async {
+        if !CrowdinDirectory::has_existing(&mod_directory).await? { return Ok(()); }
+        let (crowdin_directory, _) = CrowdinDirectory::get_or_create(mod_directory).await?;
+        crowdin_directory.add_english_files().await?;
+        Ok(())
+    }.await;
+    if let Err(error) = result {
+        report_crowdin_error("import-english", &full_name, &mod_name, &error);
     }
 }
 
+/// Mods with source language for which there is no Crowdin project are skipped
+fn check_source_language(task: &str, mod_info: &GithubModInfo) -> bool {
+    if crowdin::has_project_with_source_language(&mod_info.source_language) { return true; }
+    let message = format!(
+        "[{}] [{}] skipping mod because there is no Crowdin project with source language `{}`",
+        task, mod_info, mod_info.source_language,
+    );
+    history::add_error(&mod_info.full_name(), message.clone());
+    sentry_report_warning(&message);
+    false
+}
+
 pub async fn on_push_event(
     event: &PushWebhookEventPayload,
     installation_id: InstallationId,
@@ -114,24 +262,35 @@ pub async fn on_push_event(
 ) {
     info!("\n[push-webhook] [{}] starting...", full_name);
 
-    if !has_interesting_changes(event) {
-        info!("[push-webhook] [{}] no modified/added english files found", full_name);
-        return;
+    // Config is needed to know locale paths of mods, so it is read for every push,
+    // but commit status is set only for pushes which change config
+    let config_changed = get_all_changed_files(event).any(|file| GITHUB_CONFIG_FILE_NAMES.contains(&file));
+    let repo_info = match github::get_repo_info(installation_id, &full_name).await {
+        Ok(repo_info) => repo_info,
+        Err(GetRepoInfoError::InvalidConfig(error)) => {
+            report_invalid_config("push-webhook", &full_name, &error);
+            if config_changed {
+                set_config_status(event, installation_id, &full_name, Some(&error)).await;
+            }
+            return;
+        }
+        Err(_) => {
+            info!("[push-webhook] [{}] no mods found", full_name);
+            return;
+        }
     };
+    if config_changed {
+        set_config_status(event, installation_id, &full_name, None).await;
+    }
 
-    let api = github::as_installation(installation_id);
-    let Ok(repo_info) = github::get_repo_info(&api, &full_name).await else {
-        info!("[push-webhook] [{}] no mods found", full_name);
+    if !has_interesting_changes(event, &repo_info) {
+        info!("[push-webhook] [{}] no modified/added english files found", full_name);
         return;
     };
 
     let repository_directory = github::clone_repository(&repo_info, installation_id).await;
-    let mut created = false;
-    for mod_ in repo_info.mods {
-        let mod_directory = ModDirectory::new(&repository_directory, mod_);
-        if !mod_directory.check_for_locale_folder() { continue; }
-        created |= handle_push_event_for_mod(mod_directory).await;
-    }
+    let renamed_files = get_renamed_files(event);
+    let created = push_repository_mods_to_crowdin(&repository_directory, repo_info.mods, &renamed_files).await;
     info!("[push-webhook] [{}] success", full_name);
 
     if created {
@@ -139,26 +298,104 @@ pub async fn on_push_event(
     }
 }
 
-async fn handle_push_event_for_mod(mod_directory: ModDirectory) -> bool {
-    let exists = CrowdinDirectory::has_existing(&mod_directory).await;
+/// Updates english files of cloned repository on crowdin.
+/// `renamed_files` are paths relative to repository root, see [get_renamed_files].
+/// Returns true if directory for some mod was created (that is full import was performed).
+pub async fn push_repository_mods_to_crowdin(
+    repository_directory: &RepositoryDirectory,
+    mods: Vec<GithubModInfo>,
+    renamed_files: &[(String, String)],
+) -> bool {
+    let mut created = false;
+    for mod_ in mods {
+        if !check_source_language("push-webhook", &mod_) { continue; }
+        let mod_directory = ModDirectory::new(repository_directory, mod_);
+        if !mod_directory.check_for_locale_folder() { continue; }
+        let full_name = mod_directory.mod_info.full_name();
+        let mod_name = mod_directory.mod_info.to_string();
+        let source_language = mod_directory.mod_info.source_language.clone();
+        let locale_source_path = get_locale_source_path(&mod_directory.mod_info);
+        let renamed_files = renamed_files.iter()
+            .filter_map(|(old_path, new_path)| {
+                let old_name = old_path.strip_prefix(&locale_source_path)?;
+                let new_name = new_path.strip_prefix(&locale_source_path)?;
+                Some((old_name.to_owned(), new_name.to_owned()))
+            })
+            .collect::<Vec<_>>();
+        let result = handle_push_event_for_mod(mod_directory, &renamed_files);
+        match crowdin::with_source_language(&source_language, result).await {
+            Ok(mod_created) => created |= mod_created,
+            Err(error) => report_crowdin_error("push-webhook", &full_name, &mod_name, &error),
+        }
+    }
+    created
+}
+
+async fn handle_push_event_for_mod(mod_directory: ModDirectory, renamed_files: &[(String, String)]) -> CrowdinResult<bool> {
+    if !mod_directory.check_english_files() {
+        return Ok(false);
+    }
+
+    let exists = CrowdinDirectory::has_existing(&mod_directory).await?;
     if !exists && !mod_directory.check_translation_files_match_english_files(true) {
-        return false;
+        return Ok(false);
     }
 
-    let (crowdin_directory, created) = CrowdinDirectory::get_or_create(mod_directory).await;
+    let (crowdin_directory, created) = CrowdinDirectory::get_or_create(mod_directory).await?;
     if created {
         info!("[push-webhook] [{}] created directory on crowdin - performing full import", crowdin_directory.mod_directory.mod_info);
-        crowdin_directory.add_english_and_localization_files().await;
+        crowdin_directory.add_english_and_localization_files().await?;
     } else {
-        crowdin_directory.add_english_files().await;
+        crowdin_directory.sync_english_files(renamed_files).await?;
     }
-    created
+    Ok(created)
+}
+
+fn report_invalid_config(task: &str, full_name: &str, error: &ConfigError) {
+    let message = format!("[{}] [{}] skipping repository because of invalid config: {}", task, full_name, error);
+    history::add_error(full_name, message.clone());
+    info!("{}", message);
+}
+
+/// Config is read from default branch, so status is set only for pushes to it
+async fn set_config_status(
+    event: &PushWebhookEventPayload,
+    installation_id: InstallationId,
+    full_name: &str,
+    error: Option<&ConfigError>,
+) {
+    if event.deleted { return; }
+    let default_branch = github::get_default_branch(installation_id, full_name).await;
+    if event.r#ref != format!("refs/heads/{}", default_branch) { return; }
+    github::set_config_status(installation_id, full_name, &event.after, error).await;
 }
 
-fn has_interesting_changes(event: &PushWebhookEventPayload) -> bool {
+fn report_crowdin_error(task: &str, full_name: &str, mod_name: &str, error: &CrowdinError) {
+    let message = format!("[{}] [{}] skipping mod because of crowdin error: {}", task, mod_name, error);
+    history::add_error(full_name, message.clone());
+    sentry_report_error(&message);
+}
+
+/// Path relative to repository root with trailing slash, e.g. `locale/en/`
+fn get_locale_source_path(mod_info: &GithubModInfo) -> String {
+    format!("{}/{}/", mod_info.locale_path, mod_info.source_language)
+}
+
+fn has_interesting_changes(event: &PushWebhookEventPayload, repo_info: &GithubRepoInfo) -> bool {
+    let locale_source_paths = repo_info.mods.iter()
+        .map(get_locale_source_path)
+        .collect::<Vec<_>>();
+    // `additional_files` and `info.json`
+    let other_source_files = repo_info.mods.iter()
+        .flat_map(|it| &it.additional_files)
+        .map(|it| it.source.clone())
+        .chain(repo_info.mods.iter().map(GithubModInfo::info_json_path))
+        .collect::<HashSet<_>>();
     let mut changed_files = get_all_changed_files(event);
     changed_files.any(|file| {
-        file == GITHUB_CONFIG_FILE_NAME || file.contains("locale/en/")
+        GITHUB_CONFIG_FILE_NAMES.contains(&file)
+            || locale_source_paths.iter().any(|it| file.starts_with(it))
+            || other_source_files.contains(file)
     })
 }
 
@@ -172,6 +409,44 @@ fn get_all_changed_files(event: &PushWebhookEventPayload) -> impl Iterator<Item=
         })
 }
 
+/// Git doesn't store renames, so in push payload renamed file is listed as removed and added.
+/// Pairs are detected when exactly one `.cfg` file was removed and one was added in same directory.
+/// Returns `(old path, new path)` relative to repository root.
+fn get_renamed_files(event: &PushWebhookEventPayload) -> Vec<(String, String)> {
+    // Files added and then removed in later commit (and vice versa) are ignored
+    let mut added = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+    for commit in &event.commits {
+        for file in &commit.removed {
+            if !added.remove(file) { removed.insert(file); }
+        }
+        for file in &commit.added {
+            if !removed.remove(file) { added.insert(file); }
+        }
+    }
+
+    fn group_by_directory(files: BTreeSet<&String>) -> BTreeMap<&str, Vec<&str>> {
+        let mut result: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for file in files {
+            if !file.ends_with(".cfg") { continue; }
+            let (directory, _name) = file.rsplit_once('/').unwrap_or(("", file));
+            result.entry(directory).or_default().push(file);
+        }
+        result
+    }
+    let added = group_by_directory(added);
+    let removed = group_by_directory(removed);
+    removed.into_iter()
+        .filter_map(|(directory, removed)| {
+            let added = added.get(directory)?;
+            match (removed.as_slice(), added.as_slice()) {
+                ([old_path], [new_path]) => Some((old_path.to_string(), new_path.to_string())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
 // This is needed for correct counting of contributions,
 // so they will be displayed at https://github.com/factorio-mods-helper.
 // Previously it was enough to star repository, but it was changed somewhere in 2023-2024.
@@ -179,7 +454,30 @@ fn get_all_changed_files(event: &PushWebhookEventPayload) -> impl Iterator<Item=
 //
 // Note that it will not work (commits will not be shown), if mod repository is a fork.
 async fn star_and_fork_repository(repository: &str) {
-    let api_personal = github::as_personal_account();
-    github::star_repository(&api_personal, repository).await;
-    github::fork_repository(&api_personal, repository).await;
+    github::star_repository(repository).await;
+    github::fork_repository(repository).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_get_old_full_name() {
+        let changes = json!({"repository": {"name": {"from": "old-repo"}}});
+        let old_full_name = get_old_full_name(&RepositoryWebhookEventAction::Renamed, "owner/repo", &changes);
+        assert_eq!(old_full_name.as_deref(), Some("owner/old-repo"));
+
+        let changes = json!({"owner": {"from": {"organization": {"login": "old-owner"}}}});
+        let old_full_name = get_old_full_name(&RepositoryWebhookEventAction::Transferred, "owner/repo", &changes);
+        assert_eq!(old_full_name.as_deref(), Some("old-owner/repo"));
+
+        let changes = json!({"owner": {"from": {"user": {"login": "old-owner"}}}});
+        let old_full_name = get_old_full_name(&RepositoryWebhookEventAction::Transferred, "owner/repo", &changes);
+        assert_eq!(old_full_name.as_deref(), Some("old-owner/repo"));
+
+        assert_eq!(get_old_full_name(&RepositoryWebhookEventAction::Archived, "owner/repo", &json!({})), None);
+    }
 }
@@ -0,0 +1,32 @@
+//! Setup of offline tests, which use [FakeGithubApi] (local bare git repositories)
+//! and [FakeCrowdinApi] instead of real services.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use fml::{crowdin, github};
+use fml::crowdin::fake::FakeCrowdinApi;
+use fml::github::fake::FakeGithubApi;
+
+pub async fn setup_fakes() -> (Arc<FakeGithubApi>, Arc<FakeCrowdinApi>) {
+    let github = setup_github_fake();
+    let crowdin = setup_crowdin_fake().await;
+    (github, crowdin)
+}
+
+/// Also sets env needed to commit translations
+pub fn setup_github_fake() -> Arc<FakeGithubApi> {
+    std::env::set_var("GIT_COMMIT_USER_NAME", "Factorio Mods Helper");
+    std::env::set_var("GIT_COMMIT_USER_EMAIL", "helper@example.com");
+    std::env::set_var("GIT_COMMIT_MESSAGE", "Update translations from Crowdin");
+    let github = Arc::new(FakeGithubApi::new());
+    github::init_with_api(github.clone());
+    github
+}
+
+pub async fn setup_crowdin_fake() -> Arc<FakeCrowdinApi> {
+    let crowdin = Arc::new(FakeCrowdinApi::new());
+    crowdin::init_with_api(crowdin.clone()).await;
+    crowdin
+}
@@ -0,0 +1,71 @@
+//! Checks JSON Schema of `factorio-mods-localization.json`:
+//! committed copy is up to date, and parser accepts same configs as schema.
+//! Run with `UPDATE_CONFIG_SCHEMA=1` to update committed copy after adding new option.
+
+use std::fs;
+
+use fml::github_config::{config_schema, parse_github_repo_info_json, CONFIG_SCHEMA_VERSION};
+
+const SCHEMA_PATH: &str = "factorio-mods-localization.schema.json";
+
+const VALID_CONFIGS: &[&str] = &[
+    "{}",
+    r#"{"$schema": "https://factorio-mods-localization.fly.dev/schema/factorio-mods-localization.json"}"#,
+    r#"{"mods": ["mod1", "mod2"], "weekly_update_from_crowdin": false, "branch": "dev"}"#,
+    r#"{"mods": [{"localePath": "custom/path", "crowdinName": "Foo", "languages": ["fr"], "additionalFiles": [{"source": "a.md", "translation": "a.{language}.md"}]}]}"#,
+    r#"{"removed_english_files": "archive", "languages": ["de", "fr"], "exclude_languages": ["ru"]}"#,
+    r#"{"min_translation_progress": 50, "export_only_approved": true, "source_language": "pt-BR"}"#,
+    r#"{"min_translation_progress": {"de": 80, "fr": 30}}"#,
+    r#"{"additional_files": [{"source": "changelog.txt", "translation": "changelogs/{language}.txt", "type": "plain_text"}]}"#,
+];
+
+const INVALID_CONFIGS: &[&str] = &[
+    r#"{"weekly_update": false}"#,
+    r#"{"branch": 1}"#,
+    r#"{"removed_english_files": "keep"}"#,
+    r#"{"min_translation_progress": 101}"#,
+    r#"{"source_language": "../en"}"#,
+    r#"{"mods": [{"localePath": "custom/path"}]}"#,
+    r#"{"mods": [{"localePath": "../path", "crowdinName": "Foo"}]}"#,
+    r#"{"mods": [{"localePath": "custom/path", "crowdinName": "Foo Bar"}]}"#,
+    r#"{"mods": [{"localePath": "custom/path", "crowdinName": "Foo", "crowdin_name": "Foo"}]}"#,
+    r#"{"additional_files": [{"source": "changelog.txt", "translation": "{language}.txt", "kind": "txt"}]}"#,
+];
+
+#[test]
+fn main() {
+    let schema = config_schema();
+    check_committed_schema(&schema);
+
+    let validator = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for config in VALID_CONFIGS {
+        let json = serde_json::from_str(config).unwrap();
+        assert!(validator.is_valid(&json), "{}", config);
+        assert!(parse_github_repo_info_json("owner/repo", config).is_ok(), "{}", config);
+    }
+    for config in INVALID_CONFIGS {
+        let json = serde_json::from_str(config).unwrap();
+        assert!(!validator.is_valid(&json), "{}", config);
+        assert!(parse_github_repo_info_json("owner/repo", config).is_err(), "{}", config);
+    }
+}
+
+fn check_committed_schema(schema: &serde_json::Value) {
+    let content = serde_json::to_string_pretty(schema).unwrap() + "\n";
+    if std::env::var("UPDATE_CONFIG_SCHEMA").is_ok() {
+        fs::write(SCHEMA_PATH, content).unwrap();
+        return;
+    }
+    let committed: serde_json::Value = serde_json::from_str(&fs::read_to_string(SCHEMA_PATH).unwrap()).unwrap();
+    if &committed == schema { return; }
+
+    let version = |schema: &serde_json::Value| schema["$comment"].as_str().unwrap().to_owned();
+    assert_ne!(
+        version(&committed), version(schema),
+        "Config schema is changed, please increase CONFIG_SCHEMA_VERSION",
+    );
+    panic!(
+        "{} is outdated, please run tests with UPDATE_CONFIG_SCHEMA=1 (current version is {})",
+        SCHEMA_PATH, CONFIG_SCHEMA_VERSION,
+    );
+}
@@ -0,0 +1,50 @@
+//! Offline check that mods are mapped to Crowdin directories by id,
+//! so directory is still found after its name stops matching `get_crowdin_directory_name`.
+
+use std::fs;
+
+use fml::{crowdin, util};
+use fml::crowdin::api::CrowdinApi;
+use fml::crowdin::directory_ids;
+use fml::crowdin::file_type::FileType;
+use fml::crowdin::TranslationsBuild;
+use fml::github_repo_info::GithubRepoInfo;
+use octocrab::models::InstallationId;
+
+mod common;
+
+#[tokio::test]
+async fn main() {
+    let crowdin = common::setup_crowdin_fake().await;
+    let data_directory = util::create_temporary_directory();
+    let mapping_path = data_directory.path().join("crowdin").join("directories.json");
+    directory_ids::init(mapping_path.clone());
+
+    // directory created before mapping existed is found by name
+    let directory_id = crowdin.create_directory("Repo (owner)", None).await.unwrap();
+    let storage_id = crowdin.upload_file_to_storage("[section]\nkey=value\n".to_owned(), "locale.ini").await.unwrap();
+    crowdin.add_english_file(directory_id, storage_id, "locale.ini", FileType::Ini, &[]).await.unwrap();
+    crowdin.set_translation("Repo (owner)", "locale.ini", "de", "[section]\nkey=Wert\n");
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    assert_eq!(crowdin::find_directory_id(&repo_info.mods[0]).await.unwrap(), Some(directory_id));
+    let mapping = fs::read_to_string(&mapping_path).unwrap();
+    assert!(mapping.contains("\"owner/repo/locale\""), "{}", mapping);
+
+    // directory name doesn't match anymore, mapping is used
+    crowdin.rename_directory(directory_id, "Old Name (owner)").await.unwrap();
+    directory_ids::init(mapping_path.clone());
+    let repositories = crowdin::filter_repositories(vec![(repo_info, InstallationId(1))]).await.unwrap();
+    assert_eq!(repositories.len(), 1);
+    let mod_info = &repositories[0].0.mods[0];
+    assert_eq!(directory_ids::get_name(mod_info), "Old Name (owner)");
+    for build in [TranslationsBuild::Project, TranslationsBuild::Directories] {
+        let translations = crowdin::download_translations_for_mods(&[mod_info], build).await.unwrap();
+        let german = translations.path().join("de").join(directory_ids::get_name(mod_info)).join("locale.ini");
+        assert_eq!(fs::read_to_string(german).unwrap(), "[section]\nkey=Wert\n");
+    }
+
+    // deleted directory is removed from mapping
+    crowdin::delete_directory(directory_id).await.unwrap();
+    assert_eq!(directory_ids::get(mod_info), None);
+    assert_eq!(crowdin::find_directory_id(mod_info).await.unwrap(), None);
+}
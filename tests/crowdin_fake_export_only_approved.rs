@@ -0,0 +1,51 @@
+//! Offline check of `export_only_approved` option:
+//! mods with and without the option are built separately, and only approved strings are exported for former.
+
+use std::fs;
+
+use fml::{crowdin, util, webhooks};
+use fml::crowdin::TranslationsBuild;
+use fml::github_repo_info::GithubRepoInfo;
+use fml::mod_directory::RepositoryDirectory;
+
+mod common;
+
+async fn import_repository(full_name: &str) -> GithubRepoInfo {
+    let root = util::create_temporary_directory();
+    fs::create_dir_all(root.path().join("locale/en")).unwrap();
+    fs::write(root.path().join("locale/en/locale.cfg"), "[section]\nkey1=value1\nkey2=value2\n").unwrap();
+    let repository = RepositoryDirectory::new(full_name, root);
+    let repo_info = GithubRepoInfo::new_single_mod(full_name);
+    webhooks::add_repository_mods_to_crowdin(&repository, repo_info.mods).await;
+    GithubRepoInfo::new_single_mod(full_name)
+}
+
+#[tokio::test]
+async fn main() {
+    let crowdin = common::setup_crowdin_fake().await;
+
+    let repo_info_all = import_repository("owner/all").await;
+    let mut repo_info_approved = import_repository("owner/approved").await;
+    repo_info_approved.mods[0].export_only_approved = true;
+    for directory in ["All (owner)", "Approved (owner)"] {
+        crowdin.set_translation(directory, "locale.ini", "de", "[section]\nkey1=Wert1\nkey2=Wert2\n");
+        crowdin.set_approved_translation(directory, "locale.ini", "de", "[section]\nkey1=Wert1\n");
+    }
+
+    let mods = [&repo_info_all.mods[0], &repo_info_approved.mods[0]];
+    for build in [TranslationsBuild::Project, TranslationsBuild::Directories] {
+        let translations = crowdin::download_translations_for_mods(&mods, build).await.unwrap();
+        let de = translations.path().join("de");
+        let all = fs::read_to_string(de.join("All (owner)/locale.ini")).unwrap();
+        assert_eq!(all, "[section]\nkey1=Wert1\nkey2=Wert2\n");
+        let approved = fs::read_to_string(de.join("Approved (owner)/locale.ini")).unwrap();
+        assert_eq!(approved, "[section]\nkey1=Wert1\n");
+    }
+    assert_eq!(crowdin.number_builds(), 2);
+    assert_eq!(crowdin.number_directory_builds(), 2);
+
+    // single build if all mods have same options
+    let translations = crowdin::download_translations_for_mods(&mods[..1], TranslationsBuild::Project).await.unwrap();
+    assert!(!translations.path().join("de/Approved (owner)").exists());
+    assert_eq!(crowdin.number_builds(), 3);
+}
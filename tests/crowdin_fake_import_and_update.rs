@@ -0,0 +1,133 @@
+//! Offline check of import and push flows, without GitHub (repository is local directory).
+
+use std::fs;
+use std::path::Path;
+
+use fml::{crowdin, util, webhooks};
+use fml::crowdin::TranslationsBuild;
+use fml::github_repo_info::{GithubRepoInfo, RemovedFilesPolicy};
+use fml::mod_directory::RepositoryDirectory;
+
+mod common;
+
+const DIRECTORY: &str = "Repo (owner)";
+
+fn write_file(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn create_repository(files: &[(&str, &str)]) -> RepositoryDirectory {
+    let root = util::create_temporary_directory();
+    for (path, content) in files {
+        write_file(root.path(), path, content);
+    }
+    RepositoryDirectory::new("owner/repo", root)
+}
+
+#[tokio::test]
+async fn main() {
+    let crowdin = common::setup_crowdin_fake().await;
+
+    // import
+    let repository = create_repository(&[
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\nkey2=foo;bar\n"),
+        ("locale/ru/locale.cfg", "[section]\nkey1=значение1\n"),
+    ]);
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    webhooks::add_repository_mods_to_crowdin(&repository, repo_info.mods).await;
+    assert_eq!(crowdin.directory_names(), vec![DIRECTORY]);
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+    assert_eq!(
+        crowdin.file_content(DIRECTORY, "locale.ini").unwrap(),
+        "[section]\nkey1=value1\nkey2=\"foo;bar\"\n",
+    );
+    assert_eq!(
+        crowdin.translation(DIRECTORY, "locale.ini", "ru").unwrap(),
+        "[section]\nkey1=значение1\n",
+    );
+
+    // push with changed and added english files
+    write_file(repository.root.path(), "locale/en/locale.cfg", "[section]\nkey1=value1 changed\n");
+    write_file(repository.root.path(), "locale/en/new.cfg", "[section]\nkey=value\n");
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    let created = webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    assert!(!created);
+    let mut files = crowdin.file_names(DIRECTORY);
+    files.sort();
+    assert_eq!(files, vec!["locale.ini", "new.ini"]);
+    assert_eq!(crowdin.file_content(DIRECTORY, "locale.ini").unwrap(), "[section]\nkey1=value1 changed\n");
+
+    // push with broken english file is refused
+    write_file(repository.root.path(), "locale/en/new.cfg", "[section]\nkey=1\nkey=2\n");
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    assert_eq!(crowdin.file_content(DIRECTORY, "new.ini").unwrap(), "[section]\nkey=value\n");
+
+    // download translations
+    crowdin.set_translation(DIRECTORY, "new.ini", "de", "[section]\nkey=Wert\n");
+    let translations = crowdin::download_all_translations().await.unwrap();
+    let ru = translations.path().join("ru").join(DIRECTORY);
+    let de = translations.path().join("de").join(DIRECTORY);
+    assert_eq!(fs::read_to_string(ru.join("locale.ini")).unwrap(), "[section]\nkey1=значение1\n");
+    assert!(!ru.join("new.ini").exists());
+    assert_eq!(fs::read_to_string(de.join("new.ini")).unwrap(), "[section]\nkey=Wert\n");
+    assert!(!translations.path().join("fr").join(DIRECTORY).join("locale.ini").exists());
+    assert_eq!(crowdin.number_builds(), 1);
+
+    // download translations only for directories of given mods
+    crowdin::create_directory("Other (owner)").await.unwrap();
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    let mods = repo_info.mods.iter().collect::<Vec<_>>();
+    let translations = crowdin::download_translations_for_mods(&mods, TranslationsBuild::Directories).await.unwrap();
+    let de = translations.path().join("de");
+    assert_eq!(fs::read_to_string(de.join(DIRECTORY).join("new.ini")).unwrap(), "[section]\nkey=Wert\n");
+    assert!(!de.join("Other (owner)").exists());
+    assert_eq!(crowdin.number_builds(), 1);
+    assert_eq!(crowdin.number_directory_builds(), 1);
+
+    // build whole project, but keep only directories of given mods
+    let translations = crowdin::download_translations_for_mods(&mods, TranslationsBuild::Project).await.unwrap();
+    let de = translations.path().join("de");
+    assert!(de.join(DIRECTORY).join("new.ini").exists());
+    assert!(!de.join("Other (owner)").exists());
+    assert_eq!(crowdin.number_builds(), 2);
+
+    // renamed english file keeps translations
+    write_file(repository.root.path(), "locale/en/new.cfg", "[section]\nkey=value\n");
+    let english = repository.root.path().join("locale/en");
+    fs::rename(english.join("new.cfg"), english.join("renamed.cfg")).unwrap();
+    let renamed_files = [("locale/en/new.cfg".to_owned(), "locale/en/renamed.cfg".to_owned())];
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &renamed_files).await;
+    let mut files = crowdin.file_names(DIRECTORY);
+    files.sort();
+    assert_eq!(files, vec!["locale.ini", "renamed.ini"]);
+    assert_eq!(crowdin.translation(DIRECTORY, "renamed.ini", "de").unwrap(), "[section]\nkey=Wert\n");
+
+    // removed english file is archived by default
+    fs::remove_file(english.join("renamed.cfg")).unwrap();
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    let archived = format!("_archived_files/{}", DIRECTORY);
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+    assert_eq!(crowdin.file_names(&archived), vec!["renamed.ini"]);
+    assert_eq!(crowdin.translation(&archived, "renamed.ini", "de").unwrap(), "[section]\nkey=Wert\n");
+    let translations = crowdin::download_all_translations().await.unwrap();
+    assert!(!translations.path().join("de").join("_archived_files").exists());
+    // installed mod is not listed as archived repository
+    assert!(crowdin::list_archived_directories().await.unwrap().is_empty());
+
+    // removed english file is deleted if configured
+    write_file(repository.root.path(), "locale/en/extra.cfg", "[section]\nkey=value\n");
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    assert_eq!(crowdin.file_names(DIRECTORY).len(), 2);
+    fs::remove_file(english.join("extra.cfg")).unwrap();
+    let mut repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    repo_info.mods[0].removed_english_files = RemovedFilesPolicy::Delete;
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+    assert_eq!(crowdin.file_names(&archived), vec!["renamed.ini"]);
+}
@@ -0,0 +1,58 @@
+//! Offline check of `languages` and `exclude_languages` options:
+//! disabled languages are excluded for Crowdin files and their existing translations are not uploaded.
+
+use std::fs;
+use std::path::Path;
+
+use fml::{crowdin, util, webhooks};
+use fml::github_repo_info::{GithubRepoInfo, LanguageFilter};
+use fml::mod_directory::RepositoryDirectory;
+
+mod common;
+
+const DIRECTORY: &str = "Repo (owner)";
+
+fn write_file(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn create_repo_info(languages: LanguageFilter) -> GithubRepoInfo {
+    let mut repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    repo_info.mods[0].languages = languages;
+    repo_info
+}
+
+#[tokio::test]
+async fn main() {
+    let crowdin = common::setup_crowdin_fake().await;
+
+    let root = util::create_temporary_directory();
+    write_file(root.path(), "locale/en/locale.cfg", "[section]\nkey1=value1\n");
+    write_file(root.path(), "locale/fr/locale.cfg", "[section]\nkey1=valeur1\n");
+    write_file(root.path(), "locale/ru/locale.cfg", "[section]\nkey1=значение1\n");
+    let repository = RepositoryDirectory::new("owner/repo", root);
+
+    // import, only french is enabled
+    let repo_info = create_repo_info(LanguageFilter { languages: Some(vec!["fr".to_owned()]), exclude_languages: None });
+    webhooks::add_repository_mods_to_crowdin(&repository, repo_info.mods).await;
+    let excluded_languages = crowdin.excluded_languages(DIRECTORY, "locale.ini");
+    assert!(excluded_languages.contains(&"ru".to_owned()));
+    assert!(!excluded_languages.contains(&"fr".to_owned()));
+    assert_eq!(crowdin.translation(DIRECTORY, "locale.ini", "fr").unwrap(), "[section]\nkey1=valeur1\n");
+    assert!(crowdin.translation(DIRECTORY, "locale.ini", "ru").is_none());
+    let translations = crowdin::download_all_translations().await.unwrap();
+    assert!(translations.path().join("fr").join(DIRECTORY).join("locale.ini").exists());
+    assert!(!translations.path().join("de").join(DIRECTORY).join("locale.ini").exists());
+
+    // push after config is changed, excluded languages are updated
+    let repo_info = create_repo_info(LanguageFilter { languages: None, exclude_languages: Some(vec!["de".to_owned()]) });
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    assert_eq!(crowdin.excluded_languages(DIRECTORY, "locale.ini"), vec!["de"]);
+
+    // no options, all languages are enabled
+    let repo_info = create_repo_info(LanguageFilter::default());
+    webhooks::push_repository_mods_to_crowdin(&repository, repo_info.mods, &[]).await;
+    assert!(crowdin.excluded_languages(DIRECTORY, "locale.ini").is_empty());
+}
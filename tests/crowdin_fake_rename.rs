@@ -0,0 +1,62 @@
+//! Offline check that Crowdin directories are renamed (with translations)
+//! when repository is renamed or transferred, or when owner changes login.
+
+use fml::{crowdin, webhooks};
+use fml::crowdin::api::CrowdinApi;
+use fml::crowdin::fake::FakeCrowdinApi;
+use fml::crowdin::directory_ids;
+use fml::crowdin::file_type::FileType;
+use fml::github_repo_info::GithubRepoInfo;
+
+mod common;
+
+async fn add_directory(crowdin: &FakeCrowdinApi, name: &str) -> crowdin::DirectoryId {
+    let directory_id = crowdin.create_directory(name, None).await.unwrap();
+    let storage_id = crowdin.upload_file_to_storage("[section]\nkey=value\n".to_owned(), "locale.ini").await.unwrap();
+    crowdin.add_english_file(directory_id, storage_id, "locale.ini", FileType::Ini, &[]).await.unwrap();
+    crowdin.set_translation(name, "locale.ini", "de", "[section]\nkey=Wert\n");
+    directory_id
+}
+
+#[tokio::test]
+async fn main() {
+    let crowdin = common::setup_crowdin_fake().await;
+    add_directory(&crowdin, "Repo (owner)").await;
+    add_directory(&crowdin, "Repo - Mod1 (owner)").await;
+    add_directory(&crowdin, "Other (owner)").await;
+    let archived_id = add_directory(&crowdin, "Repo (someone)").await;
+    crowdin::archive_directory(archived_id, "Repo (someone)").await.unwrap();
+    let archived_files_id = crowdin.create_directory("_archived_files", None).await.unwrap();
+    crowdin.create_directory("Repo (owner)", Some(archived_files_id)).await.unwrap();
+
+    // repository renamed
+    webhooks::on_repository_renamed("owner/repo", "owner/new-repo").await;
+    assert_eq!(crowdin.directory_names(), vec!["New Repo (owner)", "New Repo - Mod1 (owner)", "Other (owner)", "_archived", "_archived_files"]);
+    assert_eq!(crowdin.translation("New Repo - Mod1 (owner)", "locale.ini", "de").unwrap(), "[section]\nkey=Wert\n");
+    let archived_files = crowdin::list_archived_files_directories().await.unwrap();
+    assert_eq!(archived_files[0].0, "New Repo (owner)");
+
+    // repository transferred
+    webhooks::on_repository_renamed("owner/other", "owner2/other").await;
+    assert_eq!(crowdin.directory_names(), vec!["New Repo (owner)", "New Repo - Mod1 (owner)", "Other (owner2)", "_archived", "_archived_files"]);
+
+    // owner renamed, archived directories are renamed too
+    webhooks::on_owner_renamed("owner", "owner3").await;
+    assert_eq!(crowdin.directory_names(), vec!["New Repo (owner3)", "New Repo - Mod1 (owner3)", "Other (owner2)", "_archived", "_archived_files"]);
+    webhooks::on_owner_renamed("someone", "someone2").await;
+    assert_eq!(crowdin.translation("_archived/Repo (someone2)", "locale.ini", "de").unwrap(), "[section]\nkey=Wert\n");
+
+    // mapped directory is renamed, other directory with matching name is kept
+    let directory_id = add_directory(&crowdin, "Repo (owner4)").await;
+    add_directory(&crowdin, "Repo - Stray (owner4)").await;
+    let repo_info = GithubRepoInfo::new_single_mod("owner4/repo");
+    directory_ids::set(&repo_info.mods[0], directory_id, "Repo (owner4)");
+    webhooks::on_repository_renamed("owner4/repo", "owner4/renamed").await;
+    assert!(crowdin.directory_names().contains(&"Renamed (owner4)".to_owned()));
+    assert!(crowdin.directory_names().contains(&"Repo - Stray (owner4)".to_owned()));
+    webhooks::on_owner_renamed("owner4", "owner5").await;
+    assert!(crowdin.directory_names().contains(&"Renamed (owner5)".to_owned()));
+    assert!(crowdin.directory_names().contains(&"Repo - Stray (owner4)".to_owned()));
+    let repo_info = GithubRepoInfo::new_single_mod("owner5/renamed");
+    assert_eq!(directory_ids::get_name(&repo_info.mods[0]), "Renamed (owner5)");
+}
@@ -3,7 +3,7 @@
 
 use std::collections::{HashMap, HashSet};
 
-use octocrab::Octocrab;
+use octocrab::models::InstallationId;
 
 use fml::{crowdin, github};
 use fml::crowdin::get_crowdin_directory_name;
@@ -36,9 +36,9 @@ async fn main() {
 
 async fn get_crowdin_data() -> HashMap<String, HashSet<String>> {
     let mut result = HashMap::new();
-    let directories = crowdin::list_directories().await;
+    let directories = crowdin::list_directories().await.unwrap();
     for (crowdin_name, directory_id) in directories {
-        let files = crowdin::list_files(directory_id).await;
+        let files = crowdin::list_files(directory_id).await.unwrap();
         let files = files
             .map(|(name, _)| crowdin::replace_ini_to_cfg(&name))
             .collect();
@@ -48,13 +48,11 @@ async fn get_crowdin_data() -> HashMap<String, HashSet<String>> {
 }
 
 async fn get_github_data() -> HashMap<String, HashSet<String>> {
-    let api = github::as_app();
-    let repositories = github::get_all_repositories(&api).await;
+    let repositories = github::get_all_repositories().await;
     let mut result = HashMap::new();
     for (repo_info, installation_id) in repositories {
         for mod_ in repo_info.mods {
-            let installation_api = api.installation(installation_id);
-            let files = list_locale_en_files_for_mod(&repo_info.full_name, &mod_, &installation_api).await;
+            let files = list_locale_en_files_for_mod(&repo_info.full_name, &mod_, installation_id).await;
             let files = match files {
                 Some(value) => value,
                 None => continue,
@@ -69,10 +67,10 @@ async fn get_github_data() -> HashMap<String, HashSet<String>> {
 async fn list_locale_en_files_for_mod(
     full_name: &str,
     mod_info: &GithubModInfo,
-    installation_api: &Octocrab,
+    installation_id: InstallationId,
 ) -> Option<HashSet<String>> {
     let path = format!("{}/en", mod_info.locale_path);
-    let files = github::list_files_in_directory(installation_api, full_name, &path).await.ok()?;
+    let files = github::list_files_in_directory(installation_id, full_name, &path).await?;
     let files = files
         .into_iter()
         .filter(|name| name.ends_with(".cfg"))
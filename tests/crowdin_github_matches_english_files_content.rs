@@ -29,12 +29,12 @@ async fn download_crowdin_files() {
     }
     fs::create_dir_all(root).unwrap();
 
-    let directories = crowdin::list_directories().await;
+    let directories = crowdin::list_directories().await.unwrap();
     for (directory_name, directory_id) in directories {
         let directory_path = root.join(&directory_name);
         fs::create_dir(&directory_path).unwrap();
-        for (file_name, file_id) in crowdin::list_files(directory_id).await {
-            let mut file = crowdin::download_file(file_id).await;
+        for (file_name, file_id) in crowdin::list_files(directory_id).await.unwrap() {
+            let mut file = crowdin::download_file(file_id).await.unwrap();
             file.seek(SeekFrom::Start(0)).unwrap();
             let target = directory_path.join(file_name);
             let mut target = File::create(target).unwrap();
@@ -18,13 +18,12 @@ const IGNORED_CROWDIN: &[&str] = &[
 async fn main() {
     fml::init_with_crowdin().await;
 
-    let crowdin_names = crowdin::list_directories().await
+    let crowdin_names = crowdin::list_directories().await.unwrap()
         .map(|(name, _id)| name)
         .filter(|name| !IGNORED_CROWDIN.contains(&name.as_str()))
         .collect::<HashSet<String>>();
 
-    let api = github::as_app();
-    let github_names = github::get_all_repositories(&api).await
+    let github_names = github::get_all_repositories().await
         .into_iter()
         .flat_map(|(repo_info, _id)| repo_info.mods)
         .map(|it| get_crowdin_directory_name(&it))
@@ -0,0 +1,198 @@
+//! Runs real HTTP Crowdin client against local scripted server.
+//! Server expects exact sequence of requests which `crowdin::mod` sends when adding repository
+//! and downloading translations, and replies with recorded Crowdin responses.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fml::{crowdin, util, webhooks};
+use fml::github_repo_info::GithubRepoInfo;
+use fml::mod_directory::RepositoryDirectory;
+use rocket::{Data, Request, Route};
+use rocket::config::LogLevel;
+use rocket::data::ToByteUnit;
+use rocket::http::{Header, Method, Status};
+use rocket::response::Response;
+use rocket::route::{Handler, Outcome};
+use serde_json::{json, Value};
+
+const PROJECT: &str = "/api/v2/projects/1";
+
+struct Exchange {
+    method: Method,
+    uri: String,
+    status: Status,
+    retry_after: Option<u64>,
+    body: Vec<u8>,
+}
+
+fn exchange(method: Method, uri: &str, response: Value) -> Exchange {
+    let body = json!({ "data": response }).to_string().into_bytes();
+    Exchange { method, uri: uri.to_owned(), status: Status::Ok, retry_after: None, body }
+}
+
+#[derive(Default)]
+struct MockState {
+    script: VecDeque<Exchange>,
+    /// (uri, body) of received requests
+    requests: Vec<(String, String)>,
+    errors: Vec<String>,
+}
+
+#[derive(Clone)]
+struct MockCrowdin(Arc<Mutex<MockState>>);
+
+#[rocket::async_trait]
+impl Handler for MockCrowdin {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        let uri = request.uri().to_string();
+        let body = data.open(1.mebibytes()).into_bytes().await.unwrap().into_inner();
+        let mut state = self.0.lock().unwrap();
+        state.requests.push((uri.clone(), String::from_utf8_lossy(&body).into_owned()));
+
+        // Build download url is presigned and doesn't need api key
+        let has_api_key = request.headers().get_one("Authorization") == Some("Bearer test-api-key");
+        let expected = state.script.pop_front();
+        let error = match &expected {
+            _ if !has_api_key && !uri.starts_with("/download/") => Some("missing api key".to_owned()),
+            None => Some("no more requests expected".to_owned()),
+            Some(expected) if expected.method != request.method() || expected.uri != uri => {
+                Some(format!("expected {} {}", expected.method, expected.uri))
+            }
+            Some(_) => None,
+        };
+        if let Some(error) = error {
+            // 400 is not retried by client
+            state.errors.push(format!("{} {}: {}", request.method(), uri, error));
+            return Outcome::Success(Response::build().status(Status::BadRequest).finalize());
+        }
+
+        let expected = expected.unwrap();
+        let mut response = Response::build();
+        response.status(expected.status);
+        if let Some(retry_after) = expected.retry_after {
+            response.header(Header::new("Retry-After", retry_after.to_string()));
+        }
+        response.sized_body(expected.body.len(), Cursor::new(expected.body));
+        Outcome::Success(response.finalize())
+    }
+}
+
+impl MockCrowdin {
+    async fn start(port: u16, script: Vec<Exchange>) -> Self {
+        let mock = MockCrowdin(Arc::new(Mutex::new(MockState {
+            script: script.into(),
+            ..MockState::default()
+        })));
+        let routes = [Method::Get, Method::Post, Method::Put, Method::Patch, Method::Delete]
+            .into_iter()
+            .map(|method| Route::new(method, "/<path..>", mock.clone()))
+            .collect::<Vec<_>>();
+        let config = rocket::Config {
+            address: Ipv4Addr::LOCALHOST.into(),
+            port,
+            log_level: LogLevel::Off,
+            ..rocket::Config::debug_default()
+        };
+        let rocket = rocket::custom(config).mount("/", routes);
+        tokio::spawn(rocket.launch());
+        while TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        mock
+    }
+
+    fn take_requests(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.0.lock().unwrap().requests)
+    }
+
+    fn assert_finished(&self) {
+        let state = self.0.lock().unwrap();
+        assert_eq!(state.errors, Vec::<String>::new());
+        let remaining = state.script.iter()
+            .map(|it| format!("{} {}", it.method, it.uri))
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, Vec::<String>::new());
+    }
+}
+
+fn create_translations_zip() -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    zip.start_file("ru/Repo (owner)/locale.ini", options).unwrap();
+    zip.write_all("[section]\nkey=значение\n".as_bytes()).unwrap();
+    zip.start_file("de/Repo (owner)/locale.ini", options).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn main() {
+    let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port();
+    let base_url = format!("http://127.0.0.1:{}", port);
+    std::env::set_var("CROWDIN_PROJECT_ID", "1");
+    std::env::set_var("CROWDIN_API_KEY", "test-api-key");
+    std::env::set_var("CROWDIN_API_BASE_URL", format!("{}/api/v2", base_url));
+
+    let languages = ["ar", "be", "bg", "ca", "cs", "da", "de", "el", "es-ES", "fi", "fr", "hu", "it", "ja", "ko", "nl", "no", "pl", "pt-BR", "ru", "uk"];
+    let project_info = json!({ "name": "Factorio mods localization", "sourceLanguageId": "en", "targetLanguageIds": languages });
+    let rate_limited = Exchange {
+        method: Method::Post,
+        uri: "/api/v2/storages".to_owned(),
+        status: Status::TooManyRequests,
+        retry_after: Some(0),
+        body: vec![],
+    };
+    let mock = MockCrowdin::start(port, vec![
+        exchange(Method::Get, PROJECT, project_info),
+        exchange(Method::Get, &format!("{PROJECT}/directories?offset=0&limit=500"), json!([])),
+        // No archived directory to restore
+        exchange(Method::Get, &format!("{PROJECT}/directories?offset=0&limit=500"), json!([])),
+        exchange(Method::Post, &format!("{PROJECT}/directories"), json!({ "id": 10 })),
+        exchange(Method::Get, &format!("{PROJECT}/files?directoryId=10&offset=0&limit=500"), json!([])),
+        exchange(Method::Get, &format!("{PROJECT}/directories?directoryId=10&offset=0&limit=500"), json!([])),
+        rate_limited,
+        exchange(Method::Post, "/api/v2/storages", json!({ "id": 100 })),
+        exchange(Method::Post, &format!("{PROJECT}/files"), json!({ "id": 20 })),
+        exchange(Method::Post, "/api/v2/storages", json!({ "id": 101 })),
+        exchange(Method::Post, &format!("{PROJECT}/translations/ru"), json!({})),
+        exchange(Method::Post, &format!("{PROJECT}/translations/builds"), json!({ "id": 5 })),
+        exchange(Method::Get, &format!("{PROJECT}/translations/builds/5"), json!({ "status": "inProgress" })),
+        exchange(Method::Get, &format!("{PROJECT}/translations/builds/5"), json!({ "status": "finished" })),
+        exchange(Method::Get, &format!("{PROJECT}/translations/builds/5/download"), json!({ "url": format!("{}/download/5.zip", base_url) })),
+        Exchange {
+            method: Method::Get,
+            uri: "/download/5.zip".to_owned(),
+            status: Status::Ok,
+            retry_after: None,
+            body: create_translations_zip(),
+        },
+    ]).await;
+
+    crowdin::init().await;
+
+    let root = util::create_temporary_directory();
+    for (path, content) in [("locale/en/locale.cfg", "[section]\nkey=foo;bar\n"), ("locale/ru/locale.cfg", "[section]\nkey=фу\n")] {
+        let path = root.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    let repository = RepositoryDirectory::new("owner/repo", root);
+    let repo_info = GithubRepoInfo::new_single_mod("owner/repo");
+    webhooks::add_repository_mods_to_crowdin(&repository, repo_info.mods).await;
+
+    let requests = mock.take_requests();
+    let body = |uri: &str| requests.iter().find(|(it, _)| it == uri).map(|(_, body)| body.as_str());
+    assert_eq!(body(&format!("{PROJECT}/directories")), Some(r#"{"name":"Repo (owner)"}"#));
+    assert_eq!(body("/api/v2/storages"), Some("[section]\nkey=\"foo;bar\"\n"));
+
+    let translations = crowdin::download_all_translations().await.unwrap();
+    let ru = translations.path().join("ru/Repo (owner)/locale.ini");
+    assert_eq!(fs::read_to_string(ru).unwrap(), "[section]\nkey=значение\n");
+    assert!(!translations.path().join("de/Repo (owner)/locale.ini").exists());
+
+    mock.assert_finished();
+}
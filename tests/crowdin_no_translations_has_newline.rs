@@ -14,7 +14,7 @@ const IGNORED: &[(&str, &str, &str, &str)] = &[
 async fn main() {
     fml::init_with_crowdin().await;
 
-    let translations_directory = crowdin::download_all_translations().await;
+    let translations_directory = crowdin::download_all_translations().await.unwrap();
     let mut has_newlines = false;
     // `ru/Factorio Mod Example (dima74)/locale.ini`
     for (language_path, language) in read_dir(translations_directory.path()) {
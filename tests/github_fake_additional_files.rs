@@ -0,0 +1,76 @@
+//! Offline check of `additional_files` option: files outside of locale folder
+//! are uploaded to Crowdin with matching file type and their translations are exported to configured paths.
+
+use fml::{github, webhooks};
+use fml::crowdin::file_type::FileType;
+use fml::server::trigger_update::trigger_update_single_repository;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+const CONFIG: &str = r#"{
+    "additional_files": [
+        {"source": "changelog.txt", "translation": "changelogs/{language}.txt"},
+        {"source": "portal/description.md", "translation": "portal/description.{language}.md"}
+    ]
+}"#;
+const CHANGELOG: &str = "Version: 1.0.0\n  Features:\n    - Added pistol; and tank\n";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    // import, additional files are uploaded as is together with existing translations
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("factorio-mods-localization.json", CONFIG),
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("changelog.txt", CHANGELOG),
+        ("changelogs/ru.txt", "Версия: 1.0.0\n"),
+        ("portal/description.md", "# Mod\nAdds pistol\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+    let mut files = crowdin.file_names(DIRECTORY);
+    files.sort();
+    assert_eq!(files, vec!["changelog.txt", "description.md", "locale.ini"]);
+    assert_eq!(crowdin.file_type(DIRECTORY, "changelog.txt"), Some(FileType::PlainText));
+    assert_eq!(crowdin.file_type(DIRECTORY, "description.md"), Some(FileType::Markdown));
+    assert_eq!(crowdin.file_type(DIRECTORY, "locale.ini"), Some(FileType::Ini));
+    assert_eq!(crowdin.file_content(DIRECTORY, "changelog.txt").unwrap(), CHANGELOG);
+    assert_eq!(crowdin.translation(DIRECTORY, "changelog.txt", "ru").unwrap(), "Версия: 1.0.0\n");
+
+    // push of additional file updates it on Crowdin
+    let payload = github.commit_files(REPOSITORY, &[
+        ("portal/description.md", Some("# Mod\nAdds pistol and tank\n")),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let description = crowdin.file_content(DIRECTORY, "description.md").unwrap();
+    assert_eq!(description, "# Mod\nAdds pistol and tank\n");
+
+    // translations are exported to configured paths, untranslated files are skipped
+    crowdin.set_translation(DIRECTORY, "changelog.txt", "de", "Version: 1.0.0\n  Funktionen:\n");
+    crowdin.set_translation(DIRECTORY, "description.md", "de", "# Mod\nFügt Pistole hinzu\n");
+    crowdin.set_translation(DIRECTORY, "locale.ini", "fr", "[section]\nkey1=valeur1\n");
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    trigger_update_single_repository(repo_info, installation_id).await;
+    let german = github.file_content(REPOSITORY, "main", "changelogs/de.txt");
+    assert_eq!(german.as_deref(), Some("Version: 1.0.0\n  Funktionen:\n"));
+    let german = github.file_content(REPOSITORY, "main", "portal/description.de.md");
+    assert_eq!(german.as_deref(), Some("# Mod\nFügt Pistole hinzu\n"));
+    let russian = github.file_content(REPOSITORY, "main", "changelogs/ru.txt");
+    assert_eq!(russian.as_deref(), Some("Версия: 1.0.0\n"));
+    assert_eq!(github.file_content(REPOSITORY, "main", "changelogs/fr.txt"), None);
+    let french = github.file_content(REPOSITORY, "main", "locale/fr/locale.cfg");
+    assert_eq!(french.as_deref(), Some("[section]\nkey1=valeur1\n"));
+
+    // translation of file which is no longer in config is skipped
+    crowdin.set_translation(DIRECTORY, "description.md", "fr", "# Mod\nAjoute un pistolet\n");
+    crowdin.set_translation(DIRECTORY, "locale.ini", "fr", "[section]\nkey1=valeur2\n");
+    let mut repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    repo_info.mods[0].additional_files.retain(|it| it.source == "changelog.txt");
+    trigger_update_single_repository(repo_info, installation_id).await;
+    assert_eq!(github.file_content(REPOSITORY, "main", "portal/description.fr.md"), None);
+    let french = github.file_content(REPOSITORY, "main", "locale/fr/locale.cfg");
+    assert_eq!(french.as_deref(), Some("[section]\nkey1=valeur2\n"));
+}
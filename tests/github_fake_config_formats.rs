@@ -0,0 +1,64 @@
+//! Offline check of config in YAML and TOML formats:
+//! push of such config is handled same as of JSON one, several config files are not allowed.
+
+use fml::{github, webhooks};
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const YAML_CONFIG: &str = "factorio-mods-localization.yaml";
+const TOML_CONFIG: &str = "factorio-mods-localization.toml";
+const YML_CONFIG: &str = "factorio-mods-localization.yml";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    // import with YAML config
+    let installation_id = github.add_repository(REPOSITORY, &[
+        (YAML_CONFIG, "mods:\n  - mod1\n"),
+        ("mod1/locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("mod2/locale/en/locale.cfg", "[section]\nkey2=value2\n"),
+    ]);
+    webhooks::on_repositories_added(vec![REPOSITORY.to_owned()], installation_id).await;
+    assert_eq!(crowdin.directory_names(), vec!["Repo - Mod1 (owner)"]);
+
+    // push which changes only YAML config is handled
+    let payload = github.commit_files(REPOSITORY, &[(YAML_CONFIG, Some("mods: [mod1, mod2]\n"))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(crowdin.directory_names(), vec!["Repo - Mod1 (owner)", "Repo - Mod2 (owner)"]);
+    let statuses = github.commit_statuses(REPOSITORY);
+    assert_eq!(statuses.len(), 1);
+    assert!(statuses[0].1.success);
+
+    // second config file is error
+    let payload = github.commit_files(REPOSITORY, &[(TOML_CONFIG, Some("mods = [\"mod1\"]\n"))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let statuses = github.commit_statuses(REPOSITORY);
+    assert_eq!(statuses.len(), 2);
+    let (sha, status) = &statuses[1];
+    assert_eq!(sha, &payload.after);
+    assert!(!status.success);
+    assert_eq!(
+        status.description,
+        "Invalid config: only one config file is allowed, found factorio-mods-localization.toml, factorio-mods-localization.yaml",
+    );
+
+    // TOML config alone is fine
+    let payload = github.commit_files(REPOSITORY, &[(YAML_CONFIG, None)]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    assert_eq!(repo_info.mods.len(), 1);
+    let statuses = github.commit_statuses(REPOSITORY);
+    assert_eq!(statuses.len(), 3);
+    assert!(statuses[2].1.success);
+
+    // `.yml` extension is also supported
+    let payload = github.commit_files(REPOSITORY, &[(TOML_CONFIG, None), (YML_CONFIG, Some("mods: [mod1, mod2]\n"))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    assert_eq!(repo_info.mods.len(), 2);
+    let statuses = github.commit_statuses(REPOSITORY);
+    assert_eq!(statuses.len(), 4);
+    assert!(statuses[3].1.success);
+}
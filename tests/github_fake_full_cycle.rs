@@ -0,0 +1,59 @@
+//! Offline check of full cycle: import → push webhook (including renamed file) → weekly update.
+
+use fml::{github, webhooks};
+use fml::server::trigger_update::trigger_update_all_repositories;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    // app installed
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("locale/ru/locale.cfg", "[section]\nkey1=значение1\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+    assert_eq!(crowdin.translation(DIRECTORY, "locale.ini", "ru").unwrap(), "[section]\nkey1=значение1\n");
+
+    // english file changed
+    let payload = github.commit_files(REPOSITORY, &[
+        ("locale/en/locale.cfg", Some("[section]\nkey1=value1\nkey2=value2\n")),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(crowdin.file_content(DIRECTORY, "locale.ini").unwrap(), "[section]\nkey1=value1\nkey2=value2\n");
+
+    // english file renamed, translations on crowdin are kept
+    let payload = github.commit_files(REPOSITORY, &[
+        ("locale/en/locale.cfg", None),
+        ("locale/en/main.cfg", Some("[section]\nkey1=value1\nkey2=value2\n")),
+        ("locale/ru/locale.cfg", None),
+        ("locale/ru/main.cfg", Some("[section]\nkey1=значение1\n")),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["main.ini"]);
+    assert_eq!(crowdin.translation(DIRECTORY, "main.ini", "ru").unwrap(), "[section]\nkey1=значение1\n");
+
+    // weekly update pushes directly to default branch
+    crowdin.set_translation(DIRECTORY, "main.ini", "de", "[section]\nkey1=Wert1\n");
+    trigger_update_all_repositories().await;
+    let german = github.file_content(REPOSITORY, "main", "locale/de/main.cfg");
+    assert_eq!(german.as_deref(), Some("[section]\nkey1=Wert1\n"));
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/ru/main.cfg").as_deref(), Some("[section]\nkey1=значение1\n"));
+
+    // weekly update creates pull request from fork if branch is protected
+    github.set_branch_protected(REPOSITORY, "main");
+    crowdin.set_translation(DIRECTORY, "main.ini", "fr", "[section]\nkey1=valeur1\n");
+    trigger_update_all_repositories().await;
+    assert!(github.is_forked(REPOSITORY));
+    assert_eq!(github.pull_requests(), vec![(REPOSITORY.to_owned(), "main".to_owned())]);
+    let french = github.file_content("factorio-mods-helper/repo", "crowdin-fml", "locale/fr/main.cfg");
+    assert_eq!(french.as_deref(), Some("[section]\nkey1=valeur1\n"));
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/fr/main.cfg"), None);
+}
@@ -0,0 +1,56 @@
+//! Offline check of translating `title` and `description` of `info.json`:
+//! synthetic english file is uploaded to Crowdin and its translations are added to `locale/<lang>`.
+
+use fml::{github, webhooks};
+use fml::server::trigger_update::trigger_update_single_repository;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+const INFO_JSON: &str = r#"{"name": "my-mod", "version": "1.0.0", "title": "My mod", "description": "Adds pistol"}"#;
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    // import, existing translation of synthetic file is uploaded too
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("info.json", INFO_JSON),
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("locale/ru/info-json.cfg", "[mod-name]\nmy-mod=Мой мод\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+    let mut files = crowdin.file_names(DIRECTORY);
+    files.sort();
+    assert_eq!(files, vec!["info-json.ini", "locale.ini"]);
+    let content = crowdin.file_content(DIRECTORY, "info-json.ini").unwrap();
+    assert_eq!(content, "[mod-name]\nmy-mod=My mod\n[mod-description]\nmy-mod=Adds pistol\n");
+    let russian = crowdin.translation(DIRECTORY, "info-json.ini", "ru");
+    assert_eq!(russian.as_deref(), Some("[mod-name]\nmy-mod=Мой мод\n"));
+
+    // changed info.json is pushed to Crowdin
+    let info_json = INFO_JSON.replace("Adds pistol", "Adds pistol and tank");
+    let payload = github.commit_files(REPOSITORY, &[("info.json", Some(&info_json))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let content = crowdin.file_content(DIRECTORY, "info-json.ini").unwrap();
+    assert_eq!(content, "[mod-name]\nmy-mod=My mod\n[mod-description]\nmy-mod=Adds pistol and tank\n");
+
+    // translations are added to language folders, but not to english folder
+    let german = "[mod-name]\nmy-mod=Mein Mod\n[mod-description]\nmy-mod=Fügt Pistole und Panzer hinzu\n";
+    crowdin.set_translation(DIRECTORY, "info-json.ini", "de", german);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    trigger_update_single_repository(repo_info, installation_id).await;
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/de/info-json.cfg").as_deref(), Some(german));
+    let russian = github.file_content(REPOSITORY, "main", "locale/ru/info-json.cfg");
+    assert_eq!(russian.as_deref(), Some("[mod-name]\nmy-mod=Мой мод\n"));
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/en/info-json.cfg"), None);
+
+    // synthetic file is removed when mod localizes title and description itself
+    let payload = github.commit_files(REPOSITORY, &[
+        ("locale/en/locale.cfg", Some("[section]\nkey1=value1\n[mod-name]\nmy-mod=My mod\n[mod-description]\nmy-mod=Adds\n")),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+}
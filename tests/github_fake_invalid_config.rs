@@ -0,0 +1,55 @@
+//! Offline check of invalid `factorio-mods-localization.json`:
+//! repository is skipped and validation error is shown in commit status of commit which changes config.
+
+use fml::{github, webhooks};
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const CONFIG: &str = "factorio-mods-localization.json";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    // import of repository with invalid config is skipped
+    let installation_id = github.add_repository(REPOSITORY, &[
+        (CONFIG, r#"{"mods": ["mod1"], "weekly_update": false}"#),
+        ("mod1/locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+    ]);
+    let error = github::get_repo_info(installation_id, REPOSITORY).await.unwrap_err();
+    assert_eq!(format!("{:?}", error), r#"InvalidConfig(UnknownField("weekly_update"))"#);
+    webhooks::on_repositories_added(vec![REPOSITORY.to_owned()], installation_id).await;
+    assert!(crowdin.directory_names().is_empty());
+
+    // push with invalid config sets failure status
+    let payload = github.commit_files(REPOSITORY, &[(CONFIG, Some(r#"{"mods": ["mod1", "mod1"]}"#))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let statuses = github.commit_statuses(REPOSITORY);
+    assert_eq!(statuses.len(), 1);
+    let (sha, status) = &statuses[0];
+    assert_eq!(sha, &payload.after);
+    assert!(!status.success);
+    assert_eq!(status.description, "Invalid config: duplicate crowdinName `mod1`");
+    assert!(crowdin.directory_names().is_empty());
+
+    // push which doesn't change invalid config doesn't set status
+    let payload = github.commit_files(REPOSITORY, &[("README.md", Some("# Mod\n"))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(github.commit_statuses(REPOSITORY).len(), 1);
+
+    // fixed config replaces failure with success, mods are imported
+    let payload = github.commit_files(REPOSITORY, &[(CONFIG, Some(r#"{"mods": ["mod1"]}"#))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    let statuses = github.commit_statuses(REPOSITORY);
+    assert_eq!(statuses.len(), 2);
+    let (sha, status) = &statuses[1];
+    assert_eq!(sha, &payload.after);
+    assert!(status.success);
+    assert_eq!(crowdin.directory_names(), vec!["Repo - Mod1 (owner)"]);
+
+    // push which doesn't change valid config doesn't set status
+    let payload = github.commit_files(REPOSITORY, &[("mod1/locale/en/locale.cfg", Some("[section]\nkey1=value2\n"))]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(github.commit_statuses(REPOSITORY).len(), 2);
+}
@@ -0,0 +1,46 @@
+//! Offline check of `min_translation_progress` option:
+//! weekly update adds only new languages with enough translation progress on Crowdin,
+//! languages already present in repository are updated regardless of progress.
+
+use fml::{crowdin, github, webhooks};
+use fml::server::trigger_update::trigger_update_all_repositories;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("factorio-mods-localization.json", r#"{"min_translation_progress": {"de": 50, "fr": 50, "ru": 50}}"#),
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\nkey2=value2\nkey3=value3\nkey4=value4\n"),
+        ("locale/ru/locale.cfg", "[section]\nkey1=значение1\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+
+    crowdin.set_translation(DIRECTORY, "locale.ini", "de", "[section]\nkey1=Wert1\nkey2=Wert2\nkey3=Wert3\n");
+    crowdin.set_translation(DIRECTORY, "locale.ini", "fr", "[section]\nkey1=valeur1\n");
+    crowdin.set_translation(DIRECTORY, "locale.ini", "it", "[section]\nkey1=valore1\n");
+    crowdin.set_translation(DIRECTORY, "locale.ini", "ru", "[section]\nkey1=значение1\nkey2=значение2\n");
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    let progress = crowdin::get_translation_progress(&repo_info.mods[0]).await.unwrap();
+    assert_eq!(progress["de"], 75);
+    assert_eq!(progress["fr"], 25);
+    trigger_update_all_repositories().await;
+
+    // enough progress
+    let german = github.file_content(REPOSITORY, "main", "locale/de/locale.cfg");
+    assert_eq!(german.as_deref(), Some("[section]\nkey1=Wert1\nkey2=Wert2\nkey3=Wert3\n"));
+    // not enough progress
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/fr/locale.cfg"), None);
+    // no threshold for language
+    let italian = github.file_content(REPOSITORY, "main", "locale/it/locale.cfg");
+    assert_eq!(italian.as_deref(), Some("[section]\nkey1=valore1\n"));
+    // already present in repository
+    let russian = github.file_content(REPOSITORY, "main", "locale/ru/locale.cfg");
+    assert_eq!(russian.as_deref(), Some("[section]\nkey1=значение1\nkey2=значение2\n"));
+}
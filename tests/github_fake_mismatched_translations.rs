@@ -0,0 +1,33 @@
+//! Offline check of translations with mismatched placeholders:
+//! they are not committed, and translation file without matching translations is deleted.
+
+use fml::{github, webhooks};
+use fml::server::trigger_update::trigger_update_all_repositories;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("locale/en/locale.cfg", "[section]\nkey1=Value __1__\nkey2=Value2\n"),
+        ("locale/ru/locale.cfg", "[section]\nkey1=Значение __1__\nkey2=Значение2\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+
+    // mismatched translation is skipped, other translations are kept
+    crowdin.set_translation(DIRECTORY, "locale.ini", "ru", "[section]\nkey1=Значение\nkey2=Значение2\n");
+    trigger_update_all_repositories().await;
+    let russian = github.file_content(REPOSITORY, "main", "locale/ru/locale.cfg");
+    assert_eq!(russian.as_deref(), Some("[section]\nkey2=Значение2\n"));
+
+    // file is deleted when all translations are mismatched
+    crowdin.set_translation(DIRECTORY, "locale.ini", "ru", "[section]\nkey1=Значение\n");
+    trigger_update_all_repositories().await;
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/ru/locale.cfg"), None);
+}
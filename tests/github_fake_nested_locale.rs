@@ -0,0 +1,64 @@
+//! Offline check of mods with subfolders inside language folders, e.g. `locale/en/items/weapons.cfg`:
+//! subfolders are mirrored as nested Crowdin directories and kept on export.
+
+use fml::{github, webhooks};
+use fml::server::trigger_update::trigger_update_single_repository;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+const ITEMS_DIRECTORY: &str = "Repo (owner)/items";
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+
+    // import
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("locale/en/items/weapons.cfg", "[item-name]\npistol=Pistol\n"),
+        ("locale/ru/items/weapons.cfg", "[item-name]\npistol=Пистолет\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+    assert_eq!(crowdin.directory_names(), vec![DIRECTORY]);
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+    assert_eq!(crowdin.file_names(ITEMS_DIRECTORY), vec!["weapons.ini"]);
+    let russian = crowdin.translation(ITEMS_DIRECTORY, "weapons.ini", "ru");
+    assert_eq!(russian.as_deref(), Some("[item-name]\npistol=Пистолет\n"));
+
+    // renamed file inside subfolder keeps translations, new subfolder is created
+    let payload = github.commit_files(REPOSITORY, &[
+        ("locale/en/items/weapons.cfg", None),
+        ("locale/en/items/guns.cfg", Some("[item-name]\npistol=Pistol\n")),
+        ("locale/en/units/tanks/heavy.cfg", Some("[entity-name]\ntank=Tank\n")),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert_eq!(crowdin.file_names(ITEMS_DIRECTORY), vec!["guns.ini"]);
+    let russian = crowdin.translation(ITEMS_DIRECTORY, "guns.ini", "ru");
+    assert_eq!(russian.as_deref(), Some("[item-name]\npistol=Пистолет\n"));
+    assert_eq!(crowdin.file_names("Repo (owner)/units/tanks"), vec!["heavy.ini"]);
+
+    // subfolder without english files is deleted
+    let payload = github.commit_files(REPOSITORY, &[
+        ("locale/en/units/tanks/heavy.cfg", None),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, REPOSITORY.to_owned()).await;
+    assert!(crowdin.file_names("Repo (owner)/units/tanks").is_empty());
+    assert!(crowdin.file_names(ITEMS_DIRECTORY).contains(&"guns.ini".to_owned()));
+
+    // translations are exported with same structure
+    crowdin.set_translation(ITEMS_DIRECTORY, "guns.ini", "de", "[item-name]\npistol=Pistole\n");
+    crowdin.set_translation(DIRECTORY, "locale.ini", "de", "[section]\nkey1=Wert1\n");
+    let repo_info = github::get_repo_info(installation_id, REPOSITORY).await.unwrap();
+    trigger_update_single_repository(repo_info, installation_id).await;
+    let german = github.file_content(REPOSITORY, "main", "locale/de/items/guns.cfg");
+    assert_eq!(german.as_deref(), Some("[item-name]\npistol=Pistole\n"));
+    let german = github.file_content(REPOSITORY, "main", "locale/de/locale.cfg");
+    assert_eq!(german.as_deref(), Some("[section]\nkey1=Wert1\n"));
+    let russian = github.file_content(REPOSITORY, "main", "locale/ru/items/guns.cfg");
+    assert_eq!(russian.as_deref(), Some("[item-name]\npistol=Пистолет\n"));
+    // translation of renamed english file is removed
+    assert_eq!(github.file_content(REPOSITORY, "main", "locale/ru/items/weapons.cfg"), None);
+}
@@ -0,0 +1,78 @@
+//! Offline check of `source_language` option: mods written in other language than english
+//! are uploaded to separate Crowdin project, and translations (including english) are exported from it.
+
+use std::sync::Arc;
+
+use fml::{crowdin, github, webhooks};
+use fml::crowdin::fake::FakeCrowdinApi;
+use fml::github::fake::FakeGithubApi;
+use fml::server::trigger_update::trigger_update_single_repository;
+
+mod common;
+
+const ENGLISH_REPOSITORY: &str = "owner/english";
+const ENGLISH_DIRECTORY: &str = "English (owner)";
+const RUSSIAN_REPOSITORY: &str = "owner/russian";
+const RUSSIAN_DIRECTORY: &str = "Russian (owner)";
+
+async fn install(github: &FakeGithubApi, full_name: &str, files: &[(&str, &str)]) {
+    let installation_id = github.add_repository(full_name, files);
+    let repo_info = github::get_repo_info(installation_id, full_name).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+}
+
+#[tokio::test]
+async fn main() {
+    let github = common::setup_github_fake();
+    let crowdin_english = Arc::new(FakeCrowdinApi::new());
+    let russian_languages = vec!["de".to_owned(), "en".to_owned(), "fr".to_owned()];
+    let crowdin_russian = Arc::new(FakeCrowdinApi::with_source_language("ru", russian_languages));
+    crowdin::init_with_apis(vec![crowdin_english.clone(), crowdin_russian.clone()]).await;
+
+    // mods are uploaded to project with their source language
+    install(&github, ENGLISH_REPOSITORY, &[
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("locale/ru/locale.cfg", "[section]\nkey1=значение1\n"),
+    ]).await;
+    install(&github, RUSSIAN_REPOSITORY, &[
+        ("factorio-mods-localization.json", r#"{"source_language": "ru"}"#),
+        ("locale/ru/locale.cfg", "[section]\nkey1=значение1\n"),
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+    ]).await;
+    install(&github, "owner/japanese", &[
+        ("factorio-mods-localization.json", r#"{"source_language": "ja"}"#),
+        ("locale/ja/locale.cfg", "[section]\nkey1=値1\n"),
+    ]).await;
+    assert_eq!(crowdin_english.directory_names(), vec![ENGLISH_DIRECTORY]);
+    assert_eq!(crowdin_english.translation(ENGLISH_DIRECTORY, "locale.ini", "ru").unwrap(), "[section]\nkey1=значение1\n");
+    assert_eq!(crowdin_russian.directory_names(), vec![RUSSIAN_DIRECTORY]);
+    assert_eq!(crowdin_russian.file_content(RUSSIAN_DIRECTORY, "locale.ini").unwrap(), "[section]\nkey1=значение1\n");
+    assert_eq!(crowdin_russian.translation(RUSSIAN_DIRECTORY, "locale.ini", "en").unwrap(), "[section]\nkey1=value1\n");
+
+    // only changes of source language files are pushed to Crowdin
+    let installation_id = github::get_installation_id_for_repo(RUSSIAN_REPOSITORY).await.unwrap();
+    let payload = github.commit_files(RUSSIAN_REPOSITORY, &[
+        ("locale/ru/locale.cfg", Some("[section]\nkey1=значение1\nkey2=значение2\n")),
+    ]);
+    webhooks::on_push_event(&payload, installation_id, RUSSIAN_REPOSITORY.to_owned()).await;
+    let content = crowdin_russian.file_content(RUSSIAN_DIRECTORY, "locale.ini").unwrap();
+    assert_eq!(content, "[section]\nkey1=значение1\nkey2=значение2\n");
+
+    // translations are exported from both projects
+    crowdin_english.set_translation(ENGLISH_DIRECTORY, "locale.ini", "de", "[section]\nkey1=Wert1\n");
+    crowdin_russian.set_translation(RUSSIAN_DIRECTORY, "locale.ini", "de", "[section]\nkey2=Wert2\n");
+    crowdin_russian.set_translation(RUSSIAN_DIRECTORY, "locale.ini", "en", "[section]\nkey1=value1\nkey2=value2\n");
+    for full_name in [ENGLISH_REPOSITORY, RUSSIAN_REPOSITORY] {
+        let installation_id = github::get_installation_id_for_repo(full_name).await.unwrap();
+        let repo_info = github::get_repo_info(installation_id, full_name).await.unwrap();
+        trigger_update_single_repository(repo_info, installation_id).await;
+    }
+    let german = github.file_content(ENGLISH_REPOSITORY, "main", "locale/de/locale.cfg");
+    assert_eq!(german.as_deref(), Some("[section]\nkey1=Wert1\n"));
+    let german = github.file_content(RUSSIAN_REPOSITORY, "main", "locale/de/locale.cfg");
+    assert_eq!(german.as_deref(), Some("[section]\nkey2=Wert2\n"));
+    let english = github.file_content(RUSSIAN_REPOSITORY, "main", "locale/en/locale.cfg");
+    assert_eq!(english.as_deref(), Some("[section]\nkey1=value1\nkey2=value2\n"));
+    let russian = github.file_content(RUSSIAN_REPOSITORY, "main", "locale/ru/locale.cfg");
+    assert_eq!(russian.as_deref(), Some("[section]\nkey1=значение1\nkey2=значение2\n"));
+}
@@ -0,0 +1,75 @@
+//! Offline check of cleanup of Crowdin directories when app is uninstalled for repository:
+//! directory is archived, restored after installing again, and deleted after grace period.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fml::{crowdin, github, jobs, uninstall, util, webhooks};
+use fml::github::fake::FakeGithubApi;
+use fml::jobs::JobKind;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+const ARCHIVED_DIRECTORY: &str = "_archived/Repo (owner)";
+const STRAY_DIRECTORY: &str = "Repo - Stray (owner)";
+
+async fn install(github: &FakeGithubApi, full_name: &str) {
+    let installation_id = github.add_repository(full_name, &[
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+    ]);
+    let repo_info = github::get_repo_info(installation_id, full_name).await.unwrap();
+    webhooks::on_repository_added(repo_info, installation_id).await;
+}
+
+#[tokio::test]
+async fn main() {
+    std::env::set_var("UNINSTALLED_REPOSITORIES_POLICY", "delete");
+    std::env::set_var("UNINSTALLED_REPOSITORIES_GRACE_PERIOD_DAYS", "7");
+    let (github, crowdin) = common::setup_fakes().await;
+    let jobs_directory = util::create_temporary_directory();
+    jobs::init_with_directory(jobs_directory.path());
+
+    install(&github, REPOSITORY).await;
+    install(&github, "owner/other").await;
+    crowdin.set_translation(DIRECTORY, "locale.ini", "de", "[section]\nkey1=Wert1\n");
+    // name matches repository, but directory is not mapped to it
+    let stray_id = crowdin::create_directory(STRAY_DIRECTORY).await.unwrap();
+
+    // app uninstalled, directory is archived and deletion is scheduled
+    github.remove_repository(REPOSITORY);
+    uninstall::on_repositories_removed(vec![REPOSITORY.to_owned()]).await;
+    assert_eq!(crowdin.directory_names(), vec!["Other (owner)", STRAY_DIRECTORY, "_archived"]);
+    crowdin::delete_directory(stray_id).await.unwrap();
+    assert_eq!(crowdin.translation(ARCHIVED_DIRECTORY, "locale.ini", "de").unwrap(), "[section]\nkey1=Wert1\n");
+    let pending_jobs = jobs::pending_jobs();
+    assert_eq!(pending_jobs.len(), 1);
+    assert!(matches!(&pending_jobs[0].kind, JobKind::DeleteRepository { full_name } if full_name == REPOSITORY));
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    assert!(pending_jobs[0].next_attempt_at >= now + 6 * 24 * 60 * 60);
+
+    // orphaned directories
+    crowdin::create_directory("Stray (someone)").await.unwrap();
+    let directories = uninstall::get_orphaned_directories().await.unwrap();
+    assert_eq!(directories.orphaned, vec!["Stray (someone)"]);
+    assert_eq!(directories.archived, vec![DIRECTORY]);
+
+    // app installed again, translations are restored and deletion is skipped
+    install(&github, REPOSITORY).await;
+    assert_eq!(crowdin.translation(DIRECTORY, "locale.ini", "de").unwrap(), "[section]\nkey1=Wert1\n");
+    assert!(crowdin.file_names(ARCHIVED_DIRECTORY).is_empty());
+    uninstall::delete_archived_repository(REPOSITORY).await;
+    assert_eq!(crowdin.file_names(DIRECTORY), vec!["locale.ini"]);
+
+    // app uninstalled again, archived directory is deleted after grace period
+    github.remove_repository(REPOSITORY);
+    uninstall::on_repositories_removed(vec![REPOSITORY.to_owned()]).await;
+    let stray_id = crowdin::create_directory(STRAY_DIRECTORY).await.unwrap();
+    crowdin::archive_directory(stray_id, STRAY_DIRECTORY).await.unwrap();
+    uninstall::delete_archived_repository(REPOSITORY).await;
+    assert!(crowdin.file_names(ARCHIVED_DIRECTORY).is_empty());
+    let archived = crowdin::list_archived_directories().await.unwrap();
+    assert_eq!(archived.iter().map(|(name, _id)| name.as_str()).collect::<Vec<_>>(), vec![STRAY_DIRECTORY]);
+    assert_eq!(crowdin.directory_names(), vec!["Other (owner)", "_archived", "Stray (someone)"]);
+    assert_eq!(crowdin.file_names("Other (owner)"), vec!["locale.ini"]);
+}
@@ -18,8 +18,7 @@ async fn main() {
     std::env::set_var("RUST_LOG", "fml=warn");
     fml::init();
 
-    let api = github::as_app();
-    let repositories = github::get_all_repositories(&api).await;
+    let repositories = github::get_all_repositories().await;
     let mut all_matches = true;
     for (repo_info, installation_id) in repositories {
         let repository_directory = github::clone_repository(&repo_info, installation_id).await;
@@ -0,0 +1,72 @@
+//! Offline check of persistent job queue: unfinished job is resumed at startup,
+//! webhook job is executed in background and removed from disk after success,
+//! history of jobs is recorded for status page.
+
+use std::fs;
+use std::time::Duration;
+
+use fml::{jobs, util};
+use fml::jobs::history::{self, Outcome};
+use fml::jobs::JobKind;
+
+mod common;
+
+const REPOSITORY: &str = "owner/repo";
+const DIRECTORY: &str = "Repo (owner)";
+
+async fn wait_for_jobs() {
+    for _ in 0..600 {
+        if jobs::number_pending_jobs() == 0 { return; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Jobs are not finished");
+}
+
+#[tokio::test]
+async fn main() {
+    let (github, crowdin) = common::setup_fakes().await;
+    let installation_id = github.add_repository(REPOSITORY, &[
+        ("locale/en/locale.cfg", "[section]\nkey1=value1\n"),
+        ("locale/en/other.cfg", "[other]\nkey=value\n"),
+    ]);
+
+    // job saved before restart is resumed
+    let directory = util::create_temporary_directory();
+    let job = r#"{"id": 3, "kind": "import-repository", "full_name": "owner/repo", "subpath": null, "attempts": 0, "next_attempt_at": 0}"#;
+    fs::write(directory.path().join("3.json"), job).unwrap();
+    jobs::init_with_directory(directory.path());
+    wait_for_jobs().await;
+    let mut files = crowdin.file_names(DIRECTORY);
+    files.sort();
+    assert_eq!(files, vec!["locale.ini", "other.ini"]);
+    assert!(!directory.path().join("3.json").exists());
+
+    // push webhook, english file with warning (likely false positive) is still uploaded
+    let english = "[section]\nkey1=value1\nkey2=value2\nkey3=Type __1_ to continue\n";
+    let payload = github.commit_files(REPOSITORY, &[("locale/en/locale.cfg", Some(english))]);
+    let id = jobs::enqueue(JobKind::Push {
+        installation_id,
+        full_name: REPOSITORY.to_owned(),
+        payload: Box::new(payload),
+    });
+    assert_eq!(id, 4);
+    wait_for_jobs().await;
+    assert_eq!(crowdin.file_content(DIRECTORY, "locale.ini").unwrap(), english);
+
+    // update from crowdin
+    crowdin.set_translation(DIRECTORY, "locale.ini", "de", "[section]\nkey1=Wert1\n");
+    jobs::enqueue(JobKind::UpdateRepository { full_name: REPOSITORY.to_owned(), subpath: None });
+    wait_for_jobs().await;
+
+    let history = history::get(REPOSITORY);
+    let kinds = history.iter().map(|entry| entry.kind.as_str()).collect::<Vec<_>>();
+    assert_eq!(kinds, vec!["update-repository", "push", "import-repository"]);
+    assert!(history.iter().all(|entry| entry.outcome == Outcome::Success && entry.finished_at.is_some()));
+    // unchanged `other.cfg` is not listed
+    assert_eq!(history[1].changed_files, vec!["locale/en/locale.cfg"]);
+    assert_eq!(history[1].warnings.len(), 1);
+    assert!(history[1].warnings[0].contains("locale.cfg:4: warning: value of key `key3` has unbalanced `__` placeholder"), "{}", history[1].warnings[0]);
+    assert_eq!(history[0].changed_files, vec!["locale/de/locale.cfg"]);
+    let url = history[0].url.as_deref().unwrap();
+    assert!(url.starts_with("https://github.com/owner/repo/commit/"), "{}", url);
+}